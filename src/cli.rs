@@ -0,0 +1,628 @@
+use crate::models::inventory::{HostDocument, SCHEMA_VERSION};
+use crate::models::{AuditSeverity, Severity, SshHost};
+use crate::repositories::{KeyRepository, SettingsRepository, SshRepository};
+use crate::services::{run_ssh, AppService, Auditor, Linter};
+
+const EXIT_OK: i32 = 0;
+const EXIT_ERROR: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+
+/// Options after a subcommand: `--name value` / `--name=value` pairs,
+/// bare switches, and positional arguments. `--` ends option parsing.
+struct Flags {
+    values: Vec<(String, String)>,
+    switches: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Flags {
+    fn parse(args: &[String], with_value: &[&str], switches: &[&str]) -> Result<Self, String> {
+        let mut flags = Self {
+            values: Vec::new(),
+            switches: Vec::new(),
+            positional: Vec::new(),
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                flags.positional.extend(iter.by_ref().cloned());
+                break;
+            }
+            let Some(name) = arg.strip_prefix("--") else {
+                flags.positional.push(arg.clone());
+                continue;
+            };
+
+            let (name, inline) = match name.split_once('=') {
+                Some((n, v)) => (n, Some(v.to_string())),
+                None => (name, None),
+            };
+            if switches.contains(&name) {
+                flags.switches.push(name.to_string());
+            } else if with_value.contains(&name) {
+                let value = match inline {
+                    Some(v) => v,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| format!("--{} needs a value", name))?,
+                };
+                flags.values.push((name.to_string(), value));
+            } else {
+                return Err(format!("unknown option --{}", name));
+            }
+        }
+
+        Ok(flags)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn all(&self, name: &str) -> Vec<String> {
+        self.values
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+}
+
+const HOST_OPTIONS: &[&str] = &["alias", "hostname", "user", "port", "identity"];
+
+/// Runs one of the host subcommands against the same `AppService` the
+/// TUI uses, so validation, backups and conflict checks are identical.
+pub fn run(command: &str, args: &[String], app: &mut AppService, ssh_repo: &dyn SshRepository) -> i32 {
+    let result = match command {
+        "list" => Flags::parse(args, &[], &["all", "json", "names"]).map(|f| list(app, &f)),
+        "show" => Flags::parse(args, &[], &["json"]).and_then(|f| show(app, &f)),
+        "add" => Flags::parse(args, HOST_OPTIONS, &[]).and_then(|f| add(app, &f, ssh_repo)),
+        "edit" => Flags::parse(args, HOST_OPTIONS, &[]).and_then(|f| edit(app, &f, ssh_repo)),
+        "rm" => Flags::parse(args, &[], &[]).and_then(|f| remove(app, &f, ssh_repo)),
+        "connect" => Flags::parse(args, &[], &[]).and_then(|f| connect(app, &f)),
+        _ => Err(format!("unknown command '{}'", command)),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("lazyssh {}: {}", command, e);
+            EXIT_USAGE
+        }
+    }
+}
+
+fn list(app: &AppService, flags: &Flags) -> i32 {
+    match list_output(app, flags) {
+        Ok(text) => {
+            print!("{}", text);
+            EXIT_OK
+        }
+        Err(e) => fail(&e),
+    }
+}
+
+/// What `list` prints: the JSON inventory, bare names for shell completion,
+/// or a table. `--json` wins over `--names`; `--all` adds pattern blocks to
+/// the JSON and the table.
+fn list_output(app: &AppService, flags: &Flags) -> Result<String, String> {
+    let include_patterns = flags.has("all");
+    if flags.has("json") {
+        return serde_json::to_string_pretty(&app.inventory(include_patterns))
+            .map(|json| json + "\n")
+            .map_err(|e| format!("cannot encode JSON: {}", e));
+    }
+    if flags.has("names") {
+        return Ok(app
+            .hosts()
+            .iter()
+            .flat_map(SshHost::concrete_names)
+            .map(|name| format!("{}\n", name))
+            .collect());
+    }
+
+    let rows: Vec<[String; 5]> = app
+        .hosts()
+        .iter()
+        .filter(|h| include_patterns || !h.is_pattern())
+        .map(|h| {
+            [
+                h.alias().to_string(),
+                if h.is_pattern() { "(pattern)".into() } else { h.hostname.clone() },
+                h.user.clone(),
+                h.port.to_string(),
+                app.source_display(h.file_index),
+            ]
+        })
+        .collect();
+
+    let header = ["ALIAS", "HOSTNAME", "USER", "PORT", "SOURCE"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut out = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Resolves the single positional alias argument of show/edit/rm/connect.
+fn target(app: &AppService, flags: &Flags) -> Result<Result<usize, String>, String> {
+    let [alias, ..] = flags.positional.as_slice() else {
+        return Err("missing host alias".into());
+    };
+    Ok(app
+        .find_host(alias)
+        .ok_or_else(|| format!("no host named '{}'", alias)))
+}
+
+fn show(app: &AppService, flags: &Flags) -> Result<i32, String> {
+    let index = match target(app, flags)? {
+        Ok(index) => index,
+        Err(e) => return Ok(fail(&e)),
+    };
+    let Some(host) = app.host_at(index) else {
+        return Ok(fail("host is no longer loaded"));
+    };
+
+    if flags.has("json") {
+        let Some(host) = app.host_record(index) else {
+            return Ok(fail("host is no longer loaded"));
+        };
+        return Ok(print_json(&HostDocument {
+            schema_version: SCHEMA_VERSION,
+            host,
+        }));
+    }
+
+    let row = |key: &str, value: &str| println!("{:<16}{}", key, value);
+    row("Host", &host.patterns_text());
+    if !host.hostname.is_empty() {
+        row("HostName", &host.hostname);
+    }
+    row("Port", &host.port.to_string());
+    if !host.user.is_empty() {
+        row("User", &host.user);
+    }
+    for (keyword, values) in host.lists() {
+        for value in values {
+            row(keyword, value);
+        }
+    }
+    for (keyword, value) in &host.extra_options {
+        row(keyword, value);
+    }
+    row("Source", &app.source_display(host.file_index));
+
+    Ok(EXIT_OK)
+}
+
+/// Copies the `--alias/--hostname/...` options onto a draft. `--identity`
+/// may repeat and replaces the whole IdentityFile list.
+fn apply_host_options(draft: &mut SshHost, flags: &Flags) -> Result<(), String> {
+    if let Some(alias) = flags.value("alias") {
+        draft.patterns = alias.split_whitespace().map(str::to_string).collect();
+    }
+    if let Some(hostname) = flags.value("hostname") {
+        draft.hostname = hostname.to_string();
+    }
+    if let Some(user) = flags.value("user") {
+        draft.user = user.to_string();
+    }
+    if let Some(port) = flags.value("port") {
+        draft.port = port
+            .parse()
+            .ok()
+            .filter(|&p| p > 0)
+            .ok_or_else(|| format!("--port '{}' is not a port between 1 and 65535", port))?;
+        draft.invalid_port = None;
+    }
+    let identities = flags.all("identity");
+    if !identities.is_empty() {
+        draft.identity_files = identities;
+    }
+    Ok(())
+}
+
+fn add(app: &mut AppService, flags: &Flags, ssh_repo: &dyn SshRepository) -> Result<i32, String> {
+    if flags.value("alias").is_none() {
+        return Err("--alias is required".into());
+    }
+
+    let mut draft = SshHost::empty();
+    apply_host_options(&mut draft, flags)?;
+    Ok(report(app.add_host(draft, ssh_repo)))
+}
+
+fn edit(app: &mut AppService, flags: &Flags, ssh_repo: &dyn SshRepository) -> Result<i32, String> {
+    let index = match target(app, flags)? {
+        Ok(index) => index,
+        Err(e) => return Ok(fail(&e)),
+    };
+    let Some(mut draft) = app.host_at(index).cloned() else {
+        return Ok(fail("host is no longer loaded"));
+    };
+
+    apply_host_options(&mut draft, flags)?;
+    Ok(report(app.update_host(index, draft, ssh_repo)))
+}
+
+fn remove(app: &mut AppService, flags: &Flags, ssh_repo: &dyn SshRepository) -> Result<i32, String> {
+    let index = match target(app, flags)? {
+        Ok(index) => index,
+        Err(e) => return Ok(fail(&e)),
+    };
+    Ok(report(app.delete_host(index, ssh_repo)))
+}
+
+/// Runs ssh for the host and exits with ssh's own status. Arguments after
+/// the alias (use `--` before options) are passed on as the remote command.
+fn connect(app: &AppService, flags: &Flags) -> Result<i32, String> {
+    let index = match target(app, flags)? {
+        Ok(index) => index,
+        Err(e) => return Ok(fail(&e)),
+    };
+    let mut command = match app.connect_command(index) {
+        Ok(command) => command,
+        Err(e) => return Ok(fail(&e)),
+    };
+    command.args.extend(flags.positional.iter().skip(1).cloned());
+
+    match run_ssh(&command) {
+        Ok(status) => Ok(status.code().unwrap_or(EXIT_ERROR)),
+        Err(e) => Ok(fail(&format!("cannot run ssh: {}", e))),
+    }
+}
+
+/// `lazyssh completions <shell>`: prints a script that completes
+/// subcommands and flags, and host names through `lazyssh list --names`.
+pub fn completions(args: &[String]) -> i32 {
+    let script = match args.first().map(String::as_str) {
+        Some("bash") => include_str!("completions/lazyssh.bash"),
+        Some("zsh") => include_str!("completions/_lazyssh"),
+        Some("fish") => include_str!("completions/lazyssh.fish"),
+        _ => {
+            eprintln!("usage: lazyssh completions <bash|zsh|fish>");
+            return EXIT_USAGE;
+        }
+    };
+
+    print!("{}", script);
+    EXIT_OK
+}
+
+fn print_json(value: &impl serde::Serialize) -> i32 {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => {
+            println!("{}", json);
+            EXIT_OK
+        }
+        Err(e) => fail(&format!("cannot encode JSON: {}", e)),
+    }
+}
+
+fn report(result: Result<String, String>) -> i32 {
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            EXIT_OK
+        }
+        Err(e) => fail(&e),
+    }
+}
+
+fn fail(message: &str) -> i32 {
+    eprintln!("error: {}", message);
+    EXIT_ERROR
+}
+
+/// `lazyssh lint`: prints every problem as `file:line: severity: message`
+/// and returns the process exit code, 1 when any error was found.
+pub fn lint(ssh_repo: &dyn SshRepository) -> i32 {
+    let config = ssh_repo.load_all();
+    let diagnostics = Linter::diagnose(&config);
+
+    for diagnostic in &diagnostics {
+        let path = config
+            .path_of(diagnostic.file_index)
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        let location = if diagnostic.line == 0 {
+            path
+        } else {
+            format!("{}:{}", path, diagnostic.line)
+        };
+        println!("{}: {}: {}", location, diagnostic.severity.label(), diagnostic.message);
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    eprintln!(
+        "{} file(s) checked: {} error(s), {} warning(s)",
+        config.files.len(),
+        errors,
+        warnings
+    );
+
+    if errors > 0 {
+        EXIT_ERROR
+    } else {
+        EXIT_OK
+    }
+}
+
+/// `lazyssh audit`: the security audit grouped by severity. Returns 1 when
+/// any high severity finding is reported.
+pub fn audit(
+    ssh_repo: &dyn SshRepository,
+    settings_repo: &dyn SettingsRepository,
+    key_repo: &dyn KeyRepository,
+) -> i32 {
+    let config = ssh_repo.load_all();
+    let settings = settings_repo.load();
+    let findings = Auditor::run(&config, &config.hosts(), &settings.audit, key_repo);
+
+    for severity in AuditSeverity::all() {
+        let group: Vec<_> = findings.iter().filter(|f| f.severity == severity).collect();
+        if group.is_empty() {
+            continue;
+        }
+
+        println!("{} ({})", severity.label(), group.len());
+        for finding in group {
+            let path = config
+                .path_of(finding.file_index)
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            println!("  {:<20} {}  [{}]", finding.subject, finding.message, path);
+        }
+        println!();
+    }
+
+    let high = findings
+        .iter()
+        .filter(|f| f.severity == AuditSeverity::High)
+        .count();
+    eprintln!("{} finding(s), {} high", findings.len(), high);
+
+    if high > 0 {
+        EXIT_ERROR
+    } else {
+        EXIT_OK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Settings, Theme, ThemePreference};
+    use crate::repositories::ssh_repository::fixture_home;
+    use crate::repositories::theme_repository::ThemeCatalog;
+    use crate::repositories::{FileSshRepository, ThemeRepository};
+
+    const CONFIG: &str = "Host web w\n    HostName web.example\n    User deploy\n\
+                          \x20   IdentityFile ~/.ssh/web\n    IdentityFile ~/.ssh/shared\n\n\
+                          Host *.corp\n    User corp\n";
+
+    const SCHEMA_CONFIG: &str = "Host web w\n    HostName web.example\n    User deploy\n\
+                                 \x20   IdentityFile ~/.ssh/web\n    IdentityFile ~/.ssh/shared\n\
+                                 \x20   LocalForward 8080 localhost:80\n    ServerAliveInterval 30\n\n\
+                                 Host *.corp\n    Port ssh\n";
+
+    struct DefaultTheme;
+
+    impl ThemeRepository for DefaultTheme {
+        fn load_preference(&self) -> ThemePreference {
+            ThemePreference::default()
+        }
+        fn save_preference(&self, _preference: &ThemePreference) {}
+        fn catalog(&self) -> Vec<Theme> {
+            ThemeCatalog::all()
+        }
+    }
+
+    struct DefaultSettings;
+
+    impl SettingsRepository for DefaultSettings {
+        fn load(&self) -> Settings {
+            Settings::default()
+        }
+    }
+
+    fn fixture(name: &str) -> (FileSshRepository, AppService) {
+        let repo = fixture_home(name, &[(".ssh/config", CONFIG)]);
+        let app = AppService::initialize(&repo, &DefaultTheme, &DefaultSettings);
+        (repo, app)
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn list_with(app: &AppService, flags: &[&str]) -> String {
+        let flags = Flags::parse(&args(flags), &[], &["all", "json", "names"]).unwrap();
+        list_output(app, &flags).unwrap()
+    }
+
+    #[test]
+    fn parses_values_switches_and_positionals() {
+        let flags = Flags::parse(
+            &args(&["web", "--user=bob", "--identity", "a", "--identity", "b", "--", "--not-a-flag"]),
+            HOST_OPTIONS,
+            &["json"],
+        )
+        .unwrap();
+        assert_eq!(flags.value("user"), Some("bob"));
+        assert_eq!(flags.all("identity"), vec!["a", "b"]);
+        assert_eq!(flags.positional, vec!["web", "--not-a-flag"]);
+        assert!(!flags.has("json"));
+
+        assert_eq!(
+            Flags::parse(&args(&["--verbose"]), &[], &["json"]).err(),
+            Some("unknown option --verbose".to_string())
+        );
+        assert_eq!(
+            Flags::parse(&args(&["--user"]), HOST_OPTIONS, &[]).err(),
+            Some("--user needs a value".to_string())
+        );
+    }
+
+    #[test]
+    fn usage_errors_exit_2() {
+        let (repo, mut app) = fixture("cli-usage");
+        let cases: &[(&str, &[&str])] = &[
+            ("list", &["--verbose"]),
+            ("show", &[]),
+            ("rm", &[]),
+            ("edit", &["--user", "x"]),
+            ("connect", &[]),
+            ("add", &["--hostname", "h"]),
+            ("add", &["--alias", "db", "--port", "0"]),
+            ("frobnicate", &[]),
+        ];
+        for (command, rest) in cases {
+            assert_eq!(run(command, &args(rest), &mut app, &repo), EXIT_USAGE, "{} {:?}", command, rest);
+        }
+        assert_eq!(std::fs::read_to_string(repo.config_path()).unwrap(), CONFIG);
+    }
+
+    #[test]
+    fn unknown_hosts_exit_1() {
+        let (repo, mut app) = fixture("cli-missing");
+        for command in ["show", "rm", "edit", "connect"] {
+            assert_eq!(run(command, &args(&["nope"]), &mut app, &repo), EXIT_ERROR, "{}", command);
+        }
+        assert_eq!(std::fs::read_to_string(repo.config_path()).unwrap(), CONFIG);
+    }
+
+    #[test]
+    fn rm_removes_the_host_and_exits_0() {
+        let (repo, mut app) = fixture("cli-rm");
+        assert_eq!(run("rm", &args(&["w"]), &mut app, &repo), EXIT_OK);
+        assert_eq!(std::fs::read_to_string(repo.config_path()).unwrap(), "Host *.corp\n    User corp\n");
+        assert_eq!(run("rm", &args(&["web"]), &mut app, &repo), EXIT_ERROR);
+    }
+
+    #[test]
+    fn list_flags_combine() {
+        let (_repo, app) = fixture("cli-list");
+
+        let table = list_with(&app, &[]);
+        assert_eq!(table.lines().count(), 2, "{}", table);
+        assert!(table.starts_with("ALIAS  HOSTNAME     USER    PORT  SOURCE\nweb    web.example  deploy  22"));
+        let all = list_with(&app, &["--all"]);
+        assert_eq!(all.lines().nth(2).unwrap().split_whitespace().take(3).collect::<Vec<_>>(), ["*.corp", "(pattern)", "corp"]);
+
+        // Names are every concrete alias; patterns never complete.
+        assert_eq!(list_with(&app, &["--names"]), "web\nw\n");
+        assert_eq!(list_with(&app, &["--names", "--all"]), "web\nw\n");
+
+        let hosts = |flags: &[&str]| -> Vec<String> {
+            let json: serde_json::Value = serde_json::from_str(&list_with(&app, flags)).unwrap();
+            json["hosts"].as_array().unwrap().iter().map(|h| h["alias"].as_str().unwrap().to_string()).collect()
+        };
+        assert_eq!(hosts(&["--json"]), vec!["web"]);
+        assert_eq!(hosts(&["--json", "--all"]), vec!["web", "*.corp"]);
+        assert_eq!(hosts(&["--names", "--json"]), vec!["web"]);
+    }
+
+    /// `list --json` is a scripting interface: any change to a field name or
+    /// type here needs a `SCHEMA_VERSION` bump.
+    #[test]
+    fn json_schema_is_stable() {
+        let repo = fixture_home("cli-schema", &[(".ssh/config", SCHEMA_CONFIG)]);
+        let app = AppService::initialize(&repo, &DefaultTheme, &DefaultSettings);
+        let path = repo.config_path().display().to_string();
+        let json: serde_json::Value = serde_json::from_str(&list_with(&app, &["--json", "--all"])).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "schema_version": 1,
+                "config_path": path,
+                "files": [path],
+                "hosts": [
+                    {
+                        "alias": "web",
+                        "patterns": ["web", "w"],
+                        "is_pattern": false,
+                        "hostname": "web.example",
+                        "port": 22,
+                        "invalid_port": null,
+                        "user": "deploy",
+                        "identity_files": ["~/.ssh/web", "~/.ssh/shared"],
+                        "certificate_files": [],
+                        "local_forwards": ["8080 localhost:80"],
+                        "remote_forwards": [],
+                        "dynamic_forwards": [],
+                        "send_env": [],
+                        "extra_options": [{ "keyword": "ServerAliveInterval", "value": "30", "line": 7 }],
+                        "source": { "file": path, "line": 1, "end_line": 8 },
+                        "diagnostics": []
+                    },
+                    {
+                        "alias": "*.corp",
+                        "patterns": ["*.corp"],
+                        "is_pattern": true,
+                        "hostname": "",
+                        "port": 22,
+                        "invalid_port": "ssh",
+                        "user": "",
+                        "identity_files": [],
+                        "certificate_files": [],
+                        "local_forwards": [],
+                        "remote_forwards": [],
+                        "dynamic_forwards": [],
+                        "send_env": [],
+                        "extra_options": [],
+                        "source": { "file": path, "line": 9, "end_line": 10 },
+                        "diagnostics": [{
+                            "file": path,
+                            "line": 10,
+                            "severity": "error",
+                            "message": "Port 'ssh' is not a number between 1 and 65535"
+                        }]
+                    }
+                ],
+                "diagnostics": []
+            })
+        );
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn lint_exits_1_only_for_errors() {
+        let clean = fixture_home("cli-lint-clean", &[(".ssh/config", "Host web\n    ControlMaster false\n")]);
+        assert_eq!(lint(&clean), EXIT_OK);
+
+        let warnings = fixture_home("cli-lint-warn", &[(".ssh/config", "Host web\n    Protocol 2\n")]);
+        assert_eq!(lint(&warnings), EXIT_OK);
+
+        let errors = fixture_home("cli-lint-error", &[(".ssh/config", "Host web\n    Compression maybe\n")]);
+        assert_eq!(lint(&errors), EXIT_ERROR);
+    }
+}
@@ -0,0 +1,46 @@
+# bash completion for lazyssh
+# Install: lazyssh completions bash > ~/.local/share/bash-completion/completions/lazyssh
+
+_lazyssh_hosts() {
+    "${COMP_WORDS[0]}" list --names 2>/dev/null
+}
+
+_lazyssh() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    local command="${COMP_WORDS[1]}"
+
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "list show add edit rm connect lint audit completions --pick --help --version" -- "$cur"))
+        return
+    fi
+
+    case "$prev" in
+        --identity)
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return
+            ;;
+        --alias | --hostname | --user | --port | --separator)
+            return
+            ;;
+    esac
+
+    if [[ "$cur" == -* ]]; then
+        case "$command" in
+            list) COMPREPLY=($(compgen -W "--all --json --names" -- "$cur")) ;;
+            show) COMPREPLY=($(compgen -W "--json" -- "$cur")) ;;
+            add | edit) COMPREPLY=($(compgen -W "--alias --hostname --user --port --identity" -- "$cur")) ;;
+            --pick) COMPREPLY=($(compgen -W "--separator" -- "$cur")) ;;
+        esac
+        return
+    fi
+
+    if [[ $COMP_CWORD -eq 2 ]]; then
+        case "$command" in
+            show | edit | rm | connect) COMPREPLY=($(compgen -W "$(_lazyssh_hosts)" -- "$cur")) ;;
+            completions) COMPREPLY=($(compgen -W "bash zsh fish" -- "$cur")) ;;
+        esac
+    fi
+}
+
+complete -F _lazyssh lazyssh
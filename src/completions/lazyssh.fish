@@ -0,0 +1,42 @@
+# fish completion for lazyssh
+# Install: lazyssh completions fish > ~/.config/fish/completions/lazyssh.fish
+
+set -l commands list show add edit rm connect lint audit completions
+
+function __lazyssh_hosts
+    lazyssh list --names 2>/dev/null
+end
+
+function __lazyssh_first_argument
+    test (count (commandline -opc)) -eq 2
+end
+
+complete -c lazyssh -f
+
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a list -d 'List hosts'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a show -d 'Print one host'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a add -d 'Add a host'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a edit -d 'Change a host'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a rm -d 'Delete a host'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a connect -d 'Run ssh for a host'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a lint -d 'Check the config'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a audit -d 'Report risky settings'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -a completions -d 'Print a shell completion script'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -l pick -d 'Choose hosts and print their aliases'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -l separator -r -d 'Join picked aliases with'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -s h -l help -d 'Print help'
+complete -c lazyssh -n "not __fish_seen_subcommand_from $commands" -s V -l version -d 'Print version'
+
+complete -c lazyssh -n "__fish_seen_subcommand_from list" -l all -d 'Include pattern blocks'
+complete -c lazyssh -n "__fish_seen_subcommand_from list show" -l json -d 'Print JSON'
+complete -c lazyssh -n "__fish_seen_subcommand_from list" -l names -d 'Print host names only'
+
+complete -c lazyssh -n "__fish_seen_subcommand_from show edit rm connect; and __lazyssh_first_argument" -a "(__lazyssh_hosts)"
+
+complete -c lazyssh -n "__fish_seen_subcommand_from add edit" -l alias -r -d 'Host patterns'
+complete -c lazyssh -n "__fish_seen_subcommand_from add edit" -l hostname -r -d 'Address to connect to'
+complete -c lazyssh -n "__fish_seen_subcommand_from add edit" -l user -r -d 'Login name'
+complete -c lazyssh -n "__fish_seen_subcommand_from add edit" -l port -r -d 'Port number'
+complete -c lazyssh -n "__fish_seen_subcommand_from add edit" -l identity -r -F -d 'Private key file'
+
+complete -c lazyssh -n "__fish_seen_subcommand_from completions; and __lazyssh_first_argument" -a "bash zsh fish"
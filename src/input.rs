@@ -1,36 +1,108 @@
+use std::time::Duration;
+
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 
-use crate::models::Mode;
-use crate::repositories::{SshRepository, ThemeRepository};
-use crate::services::AppService;
+use crate::models::{KnownHostsEdit, Mode, ProbeResult};
+use crate::repositories::{BannerRepository, KeyRepository, KnownHostsRepository, SshRepository, ThemeRepository};
+use crate::services::{AppService, Prober};
 
-pub fn handle_next_event(
-    app: &mut AppService,
-    ssh_repo: &dyn SshRepository,
-    theme_repo: &dyn ThemeRepository,
-) -> std::io::Result<()> {
-    if let Event::Key(key) = event::read()? {
-        app.clear_notification();
-
-        match &app.mode {
-            Mode::Normal => on_normal(app, key, ssh_repo, theme_repo),
-            Mode::Search => on_search(app, key),
-            Mode::AddHost => on_form(app, key, ssh_repo),
-            Mode::EditHost(_) => on_form(app, key, ssh_repo),
-            Mode::ConfirmDelete(idx) => on_confirm_delete(app, key, *idx, ssh_repo),
-            Mode::SelectTheme => on_theme_select(app, key, theme_repo),
-            Mode::Help => on_help(app, key),
+/// The stores the TUI reads and writes, passed down to the key handlers.
+pub struct Repositories<'a> {
+    pub ssh: &'a dyn SshRepository,
+    pub theme: &'a dyn ThemeRepository,
+    pub key: &'a dyn KeyRepository,
+    pub banner: &'a dyn BannerRepository,
+    pub known_hosts: &'a dyn KnownHostsRepository,
+}
+
+/// How long to wait for a key before redrawing anyway, so background
+/// results show up while the user is idle.
+const TICK: Duration = Duration::from_millis(100);
+
+/// What woke the event loop.
+enum AppEvent {
+    Key(KeyEvent),
+    Probe(ProbeResult),
+    Tick,
+}
+
+fn next_event(prober: &Prober) -> std::io::Result<AppEvent> {
+    if let Some(result) = prober.try_result() {
+        return Ok(AppEvent::Probe(result));
+    }
+    if !event::poll(TICK)? {
+        return Ok(AppEvent::Tick);
+    }
+    match event::read()? {
+        Event::Key(key) => Ok(AppEvent::Key(key)),
+        _ => Ok(AppEvent::Tick),
+    }
+}
+
+pub fn handle_next_event(app: &mut AppService, repos: &Repositories, prober: &Prober) -> std::io::Result<()> {
+    let key = match next_event(prober)? {
+        AppEvent::Key(key) => key,
+        AppEvent::Probe(result) => {
+            app.apply_probe(result);
+            return Ok(());
         }
+        AppEvent::Tick => {
+            app.flush_banners(repos.banner);
+            return Ok(());
+        }
+    };
+
+    app.clear_notification();
+
+    match &app.mode {
+        Mode::Normal => on_normal(app, key, repos, prober),
+        Mode::Search => on_search(app, key),
+        Mode::AddHost => on_form(app, key, repos.ssh),
+        Mode::EditHost(_) => on_form(app, key, repos.ssh),
+        Mode::ConfirmDelete(idx) => on_confirm_delete(app, key, *idx, repos.ssh),
+        Mode::ConfirmKnownHostsEdit(edit) => on_confirm_known_hosts_edit(app, key, *edit, repos.known_hosts),
+        Mode::SaveConflict => on_save_conflict(app, key, repos.ssh),
+        Mode::SelectTheme => on_theme_select(app, key, repos.theme),
+        Mode::MatchRules => on_match_rules(app, key),
+        Mode::Diagnostics => on_diagnostics(app, key),
+        Mode::Audit => on_audit(app, key),
+        Mode::Backups => on_backups(app, key, repos.ssh),
+        Mode::KnownHosts => on_known_hosts(app, key),
+        Mode::Keys => on_keys(app, key),
+        Mode::SessionFailure => on_session_failure(app, key, repos, prober),
+        Mode::GenerateKey => on_generate_key(app, key, repos.key),
+        Mode::Help => on_help(app, key),
     }
     Ok(())
 }
 
-fn on_normal(
-    app: &mut AppService,
-    key: KeyEvent,
-    ssh_repo: &dyn SshRepository,
-    theme_repo: &dyn ThemeRepository,
-) {
+/// Keys of `lazyssh --pick`: typing always filters, like the search bar.
+pub fn handle_picker_event(app: &mut AppService) -> std::io::Result<()> {
+    let Event::Key(key) = event::read()? else {
+        return Ok(());
+    };
+    if is_quit_combo(key) {
+        app.request_quit();
+        return Ok(());
+    }
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.request_quit(),
+        KeyCode::Enter => app.confirm_pick(),
+        KeyCode::Tab => app.toggle_pick_mark(),
+        KeyCode::Up => app.move_cursor_up(),
+        KeyCode::Down => app.move_cursor_down(),
+        KeyCode::Char('p') if ctrl => app.move_cursor_up(),
+        KeyCode::Char('n') if ctrl => app.move_cursor_down(),
+        KeyCode::Backspace => app.search_backspace(),
+        KeyCode::Char(c) if !ctrl => app.search_type(c),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn on_normal(app: &mut AppService, key: KeyEvent, repos: &Repositories, prober: &Prober) {
     if is_quit_combo(key) {
         app.request_quit();
         return;
@@ -49,11 +121,21 @@ fn on_normal(
         KeyCode::Char('e') => app.begin_edit(),
         KeyCode::Char('d') => app.begin_delete(),
         KeyCode::Char('c') => app.toggle_command_preview(),
+        KeyCode::Char('v') => app.toggle_effective_view(),
         KeyCode::Char('/') => app.enter_search(),
-        KeyCode::Char('r') => app.reload_from_disk(ssh_repo),
+        KeyCode::Char('s') => app.toggle_sort(),
+        KeyCode::Char('r') => app.reload_from_disk(repos.ssh),
+        KeyCode::Char('p') => app.probe_all(prober),
+        KeyCode::Char('B') => app.accept_banner(repos.banner),
 
+        KeyCode::Char('m') => app.open_match_rules(),
+        KeyCode::Char('!') => app.open_diagnostics(),
+        KeyCode::Char('A') => app.open_audit(repos.key),
+        KeyCode::Char('b') => app.open_backups(repos.ssh),
+        KeyCode::Char('K') => app.open_known_hosts(),
+        KeyCode::Char('i') => app.open_keys(repos.key),
         KeyCode::Char('t') => app.open_theme_selector(),
-        KeyCode::Char('T') => app.toggle_transparency(theme_repo),
+        KeyCode::Char('T') => app.toggle_transparency(repos.theme),
         KeyCode::Char('?') => app.open_help(),
 
         _ => {}
@@ -94,11 +176,14 @@ fn on_form(app: &mut AppService, key: KeyEvent, ssh_repo: &dyn SshRepository) {
             }
         }
         KeyCode::BackTab => app.form_previous_field(),
+        KeyCode::Left => app.form_cycle_file(false),
+        KeyCode::Right => app.form_cycle_file(true),
 
         KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
             commit_form(app, ssh_repo);
         }
         KeyCode::Enter => commit_form(app, ssh_repo),
+        KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => app.begin_keygen(),
 
         KeyCode::Backspace => app.form_delete_char(),
         KeyCode::Char(c) => app.form_type_char(c),
@@ -127,6 +212,15 @@ fn on_confirm_delete(
     }
 }
 
+fn on_save_conflict(app: &mut AppService, key: KeyEvent, ssh_repo: &dyn SshRepository) {
+    match key.code {
+        KeyCode::Char('r') | KeyCode::Char('R') => app.resolve_conflict_reload(ssh_repo),
+        KeyCode::Char('o') | KeyCode::Char('O') => app.resolve_conflict_overwrite(ssh_repo),
+        KeyCode::Esc => app.cancel_mode(),
+        _ => {}
+    }
+}
+
 fn on_theme_select(
     app: &mut AppService,
     key: KeyEvent,
@@ -141,6 +235,139 @@ fn on_theme_select(
     }
 }
 
+fn on_match_rules(app: &mut AppService, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('m') => app.cancel_mode(),
+        KeyCode::Up | KeyCode::Char('k') => app.match_cursor_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.match_cursor_down(),
+        _ => {}
+    }
+}
+
+fn on_diagnostics(app: &mut AppService, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('!') => app.cancel_mode(),
+        KeyCode::Up | KeyCode::Char('k') => app.diagnostic_cursor_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.diagnostic_cursor_down(),
+        KeyCode::Enter => app.jump_to_diagnostic(),
+        _ => {}
+    }
+}
+
+fn on_audit(app: &mut AppService, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('A') => app.cancel_mode(),
+        KeyCode::Up | KeyCode::Char('k') => app.audit_cursor_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.audit_cursor_down(),
+        KeyCode::Enter => app.jump_to_audit_finding(),
+        _ => {}
+    }
+}
+
+fn on_backups(app: &mut AppService, key: KeyEvent, ssh_repo: &dyn SshRepository) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('b') => app.cancel_mode(),
+        KeyCode::Up | KeyCode::Char('k') => app.backup_cursor_up(ssh_repo),
+        KeyCode::Down | KeyCode::Char('j') => app.backup_cursor_down(ssh_repo),
+        KeyCode::Char('r') | KeyCode::Enter => app.restore_selected_backup(ssh_repo),
+        KeyCode::Char('d') => app.delete_selected_backup(ssh_repo),
+        KeyCode::Char('p') => app.prune_backups_now(ssh_repo),
+        _ => {}
+    }
+}
+
+fn on_known_hosts(app: &mut AppService, key: KeyEvent) {
+    if app.known_hosts_filtering {
+        match key.code {
+            KeyCode::Esc => app.cancel_known_hosts_filter(),
+            KeyCode::Enter => app.known_hosts_filtering = false,
+            KeyCode::Backspace => app.known_hosts_filter_backspace(),
+            KeyCode::Up => app.known_hosts_cursor_up(),
+            KeyCode::Down => app.known_hosts_cursor_down(),
+            KeyCode::Char(c) => app.known_hosts_filter_type(c),
+            _ => {}
+        }
+        return;
+    }
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('K') => app.cancel_mode(),
+        KeyCode::Up | KeyCode::Char('k') => app.known_hosts_cursor_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.known_hosts_cursor_down(),
+        KeyCode::Char('/') => app.known_hosts_filtering = true,
+        KeyCode::Char('d') => app.begin_known_hosts_edit(KnownHostsEdit::DeleteEntry),
+        KeyCode::Char('R') => app.begin_known_hosts_edit(KnownHostsEdit::RemoveHost),
+        KeyCode::Char('H') => app.begin_known_hosts_edit(KnownHostsEdit::HashNames),
+        _ => {}
+    }
+}
+
+fn on_confirm_known_hosts_edit(
+    app: &mut AppService,
+    key: KeyEvent,
+    edit: KnownHostsEdit,
+    known_hosts_repo: &dyn KnownHostsRepository,
+) {
+    match key.code {
+        KeyCode::Char('y') | KeyCode::Char('Y') => app.apply_known_hosts_edit(edit, known_hosts_repo),
+        _ => app.cancel_known_hosts_edit(),
+    }
+}
+
+fn on_keys(app: &mut AppService, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('i') => app.cancel_mode(),
+        KeyCode::Up | KeyCode::Char('k') => app.key_cursor_up(),
+        KeyCode::Down | KeyCode::Char('j') => app.key_cursor_down(),
+        _ => {}
+    }
+}
+
+fn on_session_failure(app: &mut AppService, key: KeyEvent, repos: &Repositories, prober: &Prober) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => {
+            app.session_diagnosis = None;
+            app.cancel_mode();
+        }
+        KeyCode::Char(c) => app.apply_remedy(c, repos.ssh, repos.known_hosts, prober),
+        _ => {}
+    }
+}
+
+fn on_generate_key(app: &mut AppService, key: KeyEvent, key_repo: &dyn KeyRepository) {
+    if is_quit_combo(key) {
+        app.request_quit();
+        return;
+    }
+
+    if app.keygen_created.is_some() {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => app.finish_keygen(true),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => app.finish_keygen(false),
+            _ => {}
+        }
+        return;
+    }
+
+    match key.code {
+        KeyCode::Esc => app.cancel_keygen(),
+        KeyCode::Tab => {
+            if key.modifiers.contains(KeyModifiers::SHIFT) {
+                app.keygen_previous_field();
+            } else {
+                app.keygen_next_field();
+            }
+        }
+        KeyCode::BackTab => app.keygen_previous_field(),
+        KeyCode::Left => app.keygen_cycle_algorithm(false),
+        KeyCode::Right => app.keygen_cycle_algorithm(true),
+        KeyCode::Enter => app.commit_keygen(key_repo),
+        KeyCode::Backspace => app.keygen_delete_char(),
+        KeyCode::Char(c) => app.keygen_type_char(c),
+        _ => {}
+    }
+}
+
 fn on_help(app: &mut AppService, key: KeyEvent) {
     match key.code {
         KeyCode::Esc | KeyCode::Char('?') | KeyCode::Char('q') => app.cancel_mode(),
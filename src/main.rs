@@ -1,3 +1,4 @@
+mod cli;
 mod input;
 mod models;
 mod repositories;
@@ -5,63 +6,149 @@ mod services;
 mod ui;
 
 use std::io;
-use std::process::Command;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
 
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{backend::CrosstermBackend, Terminal, TerminalOptions, Viewport};
 
-use models::Action;
-use repositories::{FileSshRepository, FileThemeRepository};
-use services::AppService;
+use models::{Action, HistoryEntry, LaunchCommand};
+use repositories::{
+    FileBannerRepository, FileHistoryRepository, FileKeyRepository, FileKnownHostsRepository,
+    FileSettingsRepository, FileSshRepository, FileThemeRepository,
+};
+use services::{run_ssh_capturing_stderr, AppService, Prober};
 
 pub fn main() -> io::Result<()> {
     let args: Vec<String> = std::env::args().collect();
+    let globals = global_flags(&args);
 
-    if args.iter().any(|a| a == "--version" || a == "-V") {
+    if globals.iter().any(|a| a == "--version" || a == "-V") {
         println!("v{}", env!("CARGO_PKG_VERSION"));
         return Ok(());
     }
 
-    if args.iter().any(|a| a == "--help" || a == "-h") {
+    if globals.iter().any(|a| a == "--help" || a == "-h") {
         println!("lazyssh {}", env!("CARGO_PKG_VERSION"));
         println!("A TUI SSH manager that reads/edits ~/.ssh/config directly\n");
-        println!("Usage: lazyssh [OPTIONS]\n");
+        println!("Usage: lazyssh [COMMAND] [OPTIONS]\n");
+        println!("Commands:");
+        println!("  list [--all] [--json]        List hosts (--all includes pattern blocks)");
+        println!("  list --names                 Print every concrete host name, one per line");
+        println!("  show <alias> [--json]        Print one host");
+        println!("  add --alias A --hostname H [--user U] [--port P] [--identity F]...");
+        println!("                               Add a host");
+        println!("  edit <alias> [--alias A] [--hostname H] [--user U] [--port P] [--identity F]...");
+        println!("                               Change a host; --identity replaces the list");
+        println!("  rm <alias>                   Delete a host (a backup is kept)");
+        println!("  connect <alias> [-- CMD]     Run ssh; exits with ssh's status");
+        println!("  lint                         Check the config; exits 1 when errors are found");
+        println!("  audit                        Report risky settings; exits 1 on high findings");
+        println!("  completions <bash|zsh|fish>  Print a shell completion script\n");
+        println!("Exit codes: 0 success, 1 failure, 2 usage error\n");
         println!("Options:");
-        println!("  -V, --version    Print version");
-        println!("  -h, --help       Print this help");
+        println!("  --pick [--separator SEP]     Choose hosts and print their aliases (Tab marks several;");
+        println!("                               one per line unless SEP is given); exits 1 if cancelled");
+        println!("  -V, --version                Print version");
+        println!("  -h, --help                   Print this help");
         return Ok(());
     }
 
     let ssh_repo = FileSshRepository::new();
+    let settings_repo = FileSettingsRepository::new();
+    let key_repo = FileKeyRepository::new();
     let theme_repo = FileThemeRepository::new();
+    let history_repo = FileHistoryRepository::new();
+    let banner_repo = FileBannerRepository::new();
+    let known_hosts_repo = FileKnownHostsRepository::new();
+
+    if globals.iter().any(|a| a == "--pick") {
+        let separator = globals
+            .iter()
+            .position(|a| a == "--separator")
+            .and_then(|i| globals.get(i + 1))
+            .map_or("\n", String::as_str);
+        let mut app = AppService::initialize(&ssh_repo, &theme_repo, &settings_repo);
+        app.load_history(&history_repo);
+        let aliases = run_picker(&mut app)?;
+        if aliases.is_empty() {
+            std::process::exit(1);
+        }
+        println!("{}", aliases.join(separator));
+        return Ok(());
+    }
 
-    let mut app = AppService::initialize(&ssh_repo, &theme_repo);
+    match args.get(1).map(String::as_str) {
+        Some("lint") => std::process::exit(cli::lint(&ssh_repo)),
+        Some("audit") => std::process::exit(cli::audit(&ssh_repo, &settings_repo, &key_repo)),
+        Some("completions") => std::process::exit(cli::completions(&args[2..])),
+        Some(command) => {
+            let mut app = AppService::initialize(&ssh_repo, &theme_repo, &settings_repo);
+            std::process::exit(cli::run(command, &args[2..], &mut app, &ssh_repo));
+        }
+        None => {}
+    }
+
+    let mut app = AppService::initialize(&ssh_repo, &theme_repo, &settings_repo);
+    app.load_history(&history_repo);
+    app.load_banners(&banner_repo);
+    app.load_known_hosts(&known_hosts_repo);
+
+    let repos = input::Repositories {
+        ssh: &ssh_repo,
+        theme: &theme_repo,
+        key: &key_repo,
+        banner: &banner_repo,
+        known_hosts: &known_hosts_repo,
+    };
+
+    let probe = app.probe_settings();
+    let prober = Prober::new(probe.concurrency, Duration::from_millis(probe.timeout_ms));
+    app.start_probes(&prober);
 
     loop {
-        let action = run_tui_until_action(&mut app, &ssh_repo, &theme_repo)?;
+        let action = run_tui_until_action(&mut app, &repos, &prober)?;
 
         match action {
             Action::Quit => break,
-            Action::LaunchSsh(args) => {
-                execute_ssh_session(args);
+            Action::LaunchSsh(command) => {
+                let (session, stderr) = execute_ssh_session(command);
+                let (alias, exit_code) = (session.alias.clone(), session.exit_code);
+                app.record_session(session, &history_repo);
                 app.reload_from_disk(&ssh_repo);
+                app.load_known_hosts(&known_hosts_repo);
+                app.diagnose_session(&alias, exit_code, &stderr);
             }
-            Action::Continue => {}
+            Action::Continue | Action::Pick(_) => {}
         }
     }
 
+    app.flush_banners(&banner_repo);
     Ok(())
 }
 
-fn run_tui_until_action(
-    app: &mut AppService,
-    ssh_repo: &FileSshRepository,
-    theme_repo: &FileThemeRepository,
-) -> io::Result<Action> {
+/// The options before the subcommand. Everything from the subcommand (or
+/// a `--`) on belongs to it, so `connect web -- df -h` is not taken for
+/// `--help`.
+fn global_flags(args: &[String]) -> &[String] {
+    let mut end = 1;
+    while let Some(arg) = args.get(end) {
+        match arg.as_str() {
+            "--separator" => end += 2,
+            "--" => break,
+            flag if flag.starts_with('-') => end += 1,
+            _ => break,
+        }
+    }
+    &args[1.min(args.len())..end.min(args.len())]
+}
+
+fn run_tui_until_action(app: &mut AppService, repos: &input::Repositories, prober: &Prober) -> io::Result<Action> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
@@ -71,7 +158,7 @@ fn run_tui_until_action(
 
     let action = loop {
         terminal.draw(|frame| ui::render(frame, app))?;
-        input::handle_next_event(app, ssh_repo, theme_repo)?;
+        input::handle_next_event(app, repos, prober)?;
 
         let action = app.take_action();
         match action {
@@ -91,34 +178,102 @@ fn run_tui_until_action(
     Ok(action)
 }
 
-fn execute_ssh_session(args: Vec<String>) {
-    let display = args.join(" ");
+/// Maximum height of the `--pick` list, so it fits under the prompt.
+const PICKER_ROWS: u16 = 10;
+
+/// Draws on stderr in an inline viewport so stdout carries only the
+/// result, e.g. `scp file $(lazyssh --pick):/tmp`.
+fn run_picker(app: &mut AppService) -> io::Result<Vec<String>> {
+    app.begin_pick();
+    let rows = (app.visible_indices.len() as u16).clamp(1, PICKER_ROWS);
+
+    enable_raw_mode()?;
+    let mut terminal = Terminal::with_options(
+        ui::PickerBackend::new(),
+        TerminalOptions {
+            viewport: Viewport::Inline(rows + ui::PICKER_CHROME),
+        },
+    )?;
+
+    let picked = loop {
+        terminal.draw(|frame| ui::render_picker(frame, app))?;
+        input::handle_picker_event(app)?;
 
-    println!("\x1b[1;36m══ ssh {} ══\x1b[0m\n", display);
+        match app.take_action() {
+            Action::Pick(aliases) => break aliases,
+            Action::Quit => break Vec::new(),
+            _ => continue,
+        }
+    };
+
+    terminal.clear()?;
+    disable_raw_mode()?;
+    terminal.show_cursor()?;
 
-    let status = Command::new("ssh")
-        .args(&[
-            "-o",
-            "ConnectTimeout=1",
-            "-o",
-            "ServerAliveInterval=2",
-            "-o",
-            "ServerAliveCountMax=2",
-        ])
-        .args(&args)
-        .status();
+    Ok(picked)
+}
 
-    match status {
+/// Runs the session and returns its history entry and what ssh wrote to
+/// stderr, for `diagnose_session`.
+fn execute_ssh_session(command: LaunchCommand) -> (HistoryEntry, String) {
+    println!("\x1b[1;36m══ {} ══\x1b[0m\n", command.display());
+
+    let started = Local::now().naive_local();
+    let timer = Instant::now();
+    let (status, stderr) = match run_ssh_capturing_stderr(&command) {
+        Ok((exit, stderr)) => (Ok(exit), stderr),
+        Err(e) => (Err(e), String::new()),
+    };
+
+    let exit_code = match status {
         Ok(exit) => {
             println!(
                 "\n\x1b[1;33m═══ SSH session ended (exit: {}) ═══\x1b[0m",
                 exit.code().unwrap_or(-1)
             );
+            exit.code()
         }
         Err(e) => {
             println!("\n\x1b[1;31m═══ SSH failed: {} ═══\x1b[0m", e);
+            None
         }
-    }
+    };
 
     println!("\x1b[90mReturning to SSH Manager...\x1b[0m\n");
+
+    let entry = HistoryEntry {
+        alias: command.alias,
+        started,
+        duration_secs: timer.elapsed().as_secs(),
+        exit_code,
+    };
+    (entry, stderr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::global_flags;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn flags_after_the_subcommand_are_not_global() {
+        assert!(global_flags(&args("lazyssh connect web -- df -h")).is_empty());
+        assert!(global_flags(&args("lazyssh connect web -- ls -V")).is_empty());
+        assert!(global_flags(&args("lazyssh show web --help")).is_empty());
+        assert!(global_flags(&args("lazyssh -- --help")).is_empty());
+        assert!(global_flags(&args("lazyssh")).is_empty());
+    }
+
+    #[test]
+    fn flags_before_the_subcommand_are_global() {
+        assert_eq!(global_flags(&args("lazyssh -h")), ["-h"]);
+        assert_eq!(global_flags(&args("lazyssh --version list")), ["--version"]);
+        assert_eq!(
+            global_flags(&args("lazyssh --pick --separator , -V")),
+            ["--pick", "--separator", ",", "-V"]
+        );
+    }
 }
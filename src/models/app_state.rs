@@ -1,3 +1,5 @@
+use crate::models::LaunchCommand;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
     Normal,
@@ -5,10 +7,31 @@ pub enum Mode {
     AddHost,
     EditHost(usize),
     ConfirmDelete(usize),
+    ConfirmKnownHostsEdit(KnownHostsEdit),
+    SaveConflict,
     SelectTheme,
+    MatchRules,
+    Diagnostics,
+    Audit,
+    Backups,
+    KnownHosts,
+    Keys,
+    SessionFailure,
+    GenerateKey,
     Help,
 }
 
+/// Changes the known_hosts manager asks about before writing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KnownHostsEdit {
+    /// `d`: the highlighted entry.
+    DeleteEntry,
+    /// `R`: every entry of the host selected in the host list.
+    RemoveHost,
+    /// `H`: hash the names in every user file.
+    HashNames,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FormField {
     Alias,
@@ -16,31 +39,44 @@ pub enum FormField {
     Port,
     User,
     IdentityFile,
+    CertificateFile,
+    LocalForward,
+    RemoteForward,
+    DynamicForward,
+    SendEnv,
+    ConfigFile,
 }
 
+/// Separates the entries of a repeatable keyword in its form field.
+pub const LIST_SEPARATOR: char = ';';
+
 impl FormField {
     pub fn all() -> Vec<Self> {
-        vec![Self::Alias, Self::HostName, Self::Port, Self::User, Self::IdentityFile]
+        vec![
+            Self::Alias,
+            Self::HostName,
+            Self::Port,
+            Self::User,
+            Self::IdentityFile,
+            Self::CertificateFile,
+            Self::LocalForward,
+            Self::RemoteForward,
+            Self::DynamicForward,
+            Self::SendEnv,
+            Self::ConfigFile,
+        ]
     }
 
     pub fn next(&self) -> Self {
-        match self {
-            Self::Alias => Self::HostName,
-            Self::HostName => Self::Port,
-            Self::Port => Self::User,
-            Self::User => Self::IdentityFile,
-            Self::IdentityFile => Self::Alias,
-        }
+        let fields = Self::all();
+        let i = fields.iter().position(|f| f == self).unwrap_or(0);
+        fields[(i + 1) % fields.len()].clone()
     }
 
     pub fn previous(&self) -> Self {
-        match self {
-            Self::Alias => Self::IdentityFile,
-            Self::HostName => Self::Alias,
-            Self::Port => Self::HostName,
-            Self::User => Self::Port,
-            Self::IdentityFile => Self::User,
-        }
+        let fields = Self::all();
+        let i = fields.iter().position(|f| f == self).unwrap_or(0);
+        fields[(i + fields.len() - 1) % fields.len()].clone()
     }
 
     pub fn label(&self) -> &str {
@@ -50,30 +86,116 @@ impl FormField {
             Self::Port => "Port",
             Self::User => "User",
             Self::IdentityFile => "IdentityFile",
+            Self::CertificateFile => "CertificateFile",
+            Self::LocalForward => "LocalForward",
+            Self::RemoteForward => "RemoteForward",
+            Self::DynamicForward => "DynamicForward",
+            Self::SendEnv => "SendEnv",
+            Self::ConfigFile => "Config File",
         }
     }
 
     pub fn placeholder(&self) -> &str {
         match self {
-            Self::Alias => "name used with ssh <alias>",
-            Self::HostName => "IP or domain",
+            Self::Alias => "ssh <alias>; space-separated patterns like web1 *.corp",
+            Self::HostName => "IP or domain (optional for patterns)",
             Self::Port => "default 22",
             Self::User => "login username",
-            Self::IdentityFile => "path to key (optional)",
+            Self::IdentityFile => "key paths, separated by ;",
+            Self::CertificateFile => "certificate paths, separated by ;",
+            Self::LocalForward => "e.g. 8080 localhost:80; 5432 db:5432",
+            Self::RemoteForward => "e.g. 9000 localhost:9000",
+            Self::DynamicForward => "SOCKS ports, separated by ;",
+            Self::SendEnv => "variable patterns, separated by ;",
+            Self::ConfigFile => "←/→ to choose, new hosts only",
+        }
+    }
+
+    /// The ssh keyword behind fields that hold a list of values.
+    pub fn list_keyword(&self) -> Option<&'static str> {
+        match self {
+            Self::IdentityFile => Some("IdentityFile"),
+            Self::CertificateFile => Some("CertificateFile"),
+            Self::LocalForward => Some("LocalForward"),
+            Self::RemoteForward => Some("RemoteForward"),
+            Self::DynamicForward => Some("DynamicForward"),
+            Self::SendEnv => Some("SendEnv"),
+            _ => None,
         }
     }
 
     pub fn accepts_char(&self, c: char) -> bool {
         match self {
             Self::Port => c.is_ascii_digit(),
+            Self::ConfigFile => false,
             _ => true,
         }
     }
 }
 
+/// Inputs of the key generation dialog.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyGenField {
+    Algorithm,
+    FileName,
+    Comment,
+    Passphrase,
+    ConfirmPassphrase,
+}
+
+impl KeyGenField {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::Algorithm,
+            Self::FileName,
+            Self::Comment,
+            Self::Passphrase,
+            Self::ConfirmPassphrase,
+        ]
+    }
+
+    pub fn next(&self) -> Self {
+        let fields = Self::all();
+        let i = fields.iter().position(|f| f == self).unwrap_or(0);
+        fields[(i + 1) % fields.len()].clone()
+    }
+
+    pub fn previous(&self) -> Self {
+        let fields = Self::all();
+        let i = fields.iter().position(|f| f == self).unwrap_or(0);
+        fields[(i + fields.len() - 1) % fields.len()].clone()
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Algorithm => "Type",
+            Self::FileName => "File name",
+            Self::Comment => "Comment",
+            Self::Passphrase => "Passphrase",
+            Self::ConfirmPassphrase => "Confirm",
+        }
+    }
+
+    pub fn placeholder(&self) -> &str {
+        match self {
+            Self::Algorithm => "←/→ to choose",
+            Self::FileName => "created in ~/.ssh, plus .pub",
+            Self::Comment => "stored in the public key",
+            Self::Passphrase => "optional, empty for none",
+            Self::ConfirmPassphrase => "type the passphrase again",
+        }
+    }
+
+    pub fn is_secret(&self) -> bool {
+        matches!(self, Self::Passphrase | Self::ConfirmPassphrase)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Action {
     Continue,
     Quit,
-    LaunchSsh(Vec<String>),
+    LaunchSsh(LaunchCommand),
+    /// `--pick`: the aliases to print instead of connecting.
+    Pick(Vec<String>),
 }
@@ -0,0 +1,32 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuditSeverity {
+    High,
+    Medium,
+    Low,
+}
+
+impl AuditSeverity {
+    pub fn all() -> [Self; 3] {
+        [Self::High, Self::Medium, Self::Low]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::High => "HIGH",
+            Self::Medium => "MEDIUM",
+            Self::Low => "LOW",
+        }
+    }
+}
+
+/// One risky setting found by the security audit.
+#[derive(Debug, Clone)]
+pub struct AuditFinding {
+    pub severity: AuditSeverity,
+    /// Alias of the host block, `Match ...` for a match block, or `(global)`
+    /// for lines outside any block.
+    pub subject: String,
+    pub file_index: usize,
+    pub block_index: Option<usize>,
+    pub message: String,
+}
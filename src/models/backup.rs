@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use chrono::{Local, NaiveDateTime};
+
+/// A `<file>.backup_YYYYmmdd_HHMMSS` copy written before a save.
+#[derive(Debug, Clone)]
+pub struct BackupEntry {
+    pub path: PathBuf,
+    /// The file this is a backup of, relative to `~/.ssh` unless it lives
+    /// elsewhere, e.g. `config` or `config.d/work`.
+    pub original_name: String,
+    /// `original_name` as a full path.
+    pub source: PathBuf,
+    pub created: NaiveDateTime,
+    pub size: u64,
+}
+
+impl BackupEntry {
+    pub const MARKER: &'static str = ".backup_";
+    pub const TIMESTAMP_FORMAT: &'static str = "%Y%m%d_%H%M%S";
+
+    /// Splits `config.backup_20240101_120000` into its parts.
+    pub fn parse_name(file_name: &str) -> Option<(String, NaiveDateTime)> {
+        let (original, stamp) = file_name.rsplit_once(Self::MARKER)?;
+        let created = NaiveDateTime::parse_from_str(stamp, Self::TIMESTAMP_FORMAT).ok()?;
+        Some((Self::decode_source(original), created))
+    }
+
+    /// Escapes the path separators of `original_name` so included files
+    /// keep their directory in the backup name: `config.d/work` becomes
+    /// `config.d%2Fwork`. Plain names like `config` are unchanged.
+    pub fn encode_source(original_name: &str) -> String {
+        original_name
+            .replace('%', "%25")
+            .replace('/', "%2F")
+            .replace('\\', "%5C")
+    }
+
+    fn decode_source(encoded: &str) -> String {
+        let mut out = String::new();
+        let mut rest = encoded;
+        while let Some(i) = rest.find('%') {
+            out.push_str(&rest[..i]);
+            let escape = rest.get(i..i + 3);
+            let decoded = match escape {
+                Some("%2F") => Some('/'),
+                Some("%5C") => Some('\\'),
+                Some("%25") => Some('%'),
+                _ => None,
+            };
+            match decoded {
+                Some(c) => {
+                    out.push(c);
+                    rest = &rest[i + 3..];
+                }
+                None => {
+                    out.push('%');
+                    rest = &rest[i + 1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn age_display(&self) -> String {
+        let age = Local::now().naive_local() - self.created;
+        if age.num_days() > 0 {
+            format!("{}d", age.num_days())
+        } else if age.num_hours() > 0 {
+            format!("{}h", age.num_hours())
+        } else {
+            format!("{}m", age.num_minutes().max(0))
+        }
+    }
+
+    pub fn size_display(&self) -> String {
+        if self.size >= 1024 {
+            format!("{:.1} KB", self.size as f64 / 1024.0)
+        } else {
+            format!("{} B", self.size)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_names_keep_the_include_path() {
+        assert_eq!(BackupEntry::encode_source("config"), "config");
+        assert_eq!(BackupEntry::encode_source("config.d/config"), "config.d%2Fconfig");
+        assert_eq!(BackupEntry::encode_source("/etc/ssh/50%"), "%2Fetc%2Fssh%2F50%25");
+
+        let (original, created) = BackupEntry::parse_name("config.d%2Fconfig.backup_20240102_030405").unwrap();
+        assert_eq!(original, "config.d/config");
+        assert_eq!(created.to_string(), "2024-01-02 03:04:05");
+    }
+
+    #[test]
+    fn decoding_reverses_encoding() {
+        for name in ["config", "config.d/work", "/abs/%2F/x", "odd%", "100%25"] {
+            let encoded = BackupEntry::encode_source(name);
+            assert!(!encoded.contains('/'));
+            let (decoded, _) = BackupEntry::parse_name(&format!("{}.backup_20240101_000000", encoded)).unwrap();
+            assert_eq!(decoded, name);
+        }
+    }
+}
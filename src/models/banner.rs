@@ -0,0 +1,113 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// The SSH identification string last seen for a host, e.g.
+/// `SSH-2.0-OpenSSH_9.6p1 Ubuntu-3`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BannerRecord {
+    pub banner: String,
+    pub first_seen: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    /// The banner before the last change, kept until the change is accepted.
+    pub previous: Option<String>,
+    pub changed: Option<NaiveDateTime>,
+}
+
+impl BannerRecord {
+    pub fn new(banner: &str, now: NaiveDateTime) -> Self {
+        Self {
+            banner: banner.to_string(),
+            first_seen: now,
+            last_seen: now,
+            previous: None,
+            changed: None,
+        }
+    }
+
+    /// Records a fresh probe. A different banner is flagged until accepted;
+    /// if it changes again, the oldest unaccepted banner is kept.
+    pub fn observe(&mut self, banner: &str, now: NaiveDateTime) {
+        if self.banner != banner {
+            if self.previous.is_none() {
+                self.previous = Some(self.banner.clone());
+            }
+            self.banner = banner.to_string();
+            self.first_seen = now;
+            self.changed = Some(now);
+        }
+        self.last_seen = now;
+    }
+
+    pub fn accept(&mut self) {
+        self.previous = None;
+        self.changed = None;
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.previous.as_ref().is_some_and(|p| *p != self.banner)
+    }
+
+    /// `2.0` from `SSH-2.0-OpenSSH_9.6p1 Ubuntu-3`.
+    pub fn protocol(&self) -> Option<&str> {
+        self.banner.strip_prefix("SSH-")?.split('-').next()
+    }
+
+    /// `OpenSSH_9.6p1 Ubuntu-3` from `SSH-2.0-OpenSSH_9.6p1 Ubuntu-3`.
+    pub fn software(&self) -> Option<&str> {
+        self.banner.strip_prefix("SSH-")?.split_once('-').map(|(_, rest)| rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minute: u32) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("2024-05-01 12:{:02}:00", minute), "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn same_banner_only_updates_last_seen() {
+        let mut record = BannerRecord::new("SSH-2.0-OpenSSH_9.6", at(0));
+        record.observe("SSH-2.0-OpenSSH_9.6", at(5));
+
+        assert!(!record.is_changed());
+        assert_eq!(record.first_seen, at(0));
+        assert_eq!(record.last_seen, at(5));
+    }
+
+    #[test]
+    fn different_banner_is_flagged_until_accepted() {
+        let mut record = BannerRecord::new("SSH-2.0-OpenSSH_9.6", at(0));
+        record.observe("SSH-2.0-OpenSSH_8.9", at(5));
+
+        assert!(record.is_changed());
+        assert_eq!(record.previous.as_deref(), Some("SSH-2.0-OpenSSH_9.6"));
+        assert_eq!(record.changed, Some(at(5)));
+
+        record.accept();
+        assert!(!record.is_changed());
+        record.observe("SSH-2.0-OpenSSH_8.9", at(9));
+        assert!(!record.is_changed());
+    }
+
+    #[test]
+    fn repeated_changes_keep_the_oldest_accepted_banner() {
+        let mut record = BannerRecord::new("SSH-2.0-A", at(0));
+        record.observe("SSH-2.0-B", at(1));
+        record.observe("SSH-2.0-C", at(2));
+        assert_eq!(record.previous.as_deref(), Some("SSH-2.0-A"));
+        assert!(record.is_changed());
+
+        // Going back to the accepted banner clears the flag.
+        record.observe("SSH-2.0-A", at(3));
+        assert!(!record.is_changed());
+    }
+
+    #[test]
+    fn splits_protocol_and_software() {
+        let record = BannerRecord::new("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3", at(0));
+        assert_eq!(record.protocol(), Some("2.0"));
+        assert_eq!(record.software(), Some("OpenSSH_9.6p1 Ubuntu-3"));
+    }
+}
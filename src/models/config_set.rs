@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+
+use crate::models::{Diagnostic, MatchRule, SshConfig, SshHost};
+
+/// ssh itself refuses to nest `Include` deeper than this.
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// What a file looked like on disk when it was loaded, used to notice
+/// edits made outside lazyssh before overwriting them. Only the content
+/// counts: mtimes are too coarse on some filesystems, and sync tools and
+/// editors may keep them across a change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileStamp {
+    pub hash: u64,
+}
+
+impl FileStamp {
+    pub fn unchanged(loaded: Option<Self>, on_disk: Option<Self>) -> bool {
+        loaded == on_disk
+    }
+}
+
+/// One file that contributes to the SSH configuration.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub document: SshConfig,
+    /// For each `Include` line number, the indices of the files it pulled in.
+    pub includes: Vec<(usize, Vec<usize>)>,
+    /// `None` when the file did not exist at load time.
+    pub stamp: Option<FileStamp>,
+    /// Problems found while reading the file or resolving its includes.
+    pub load_diagnostics: Vec<Diagnostic>,
+}
+
+/// `~/.ssh/config` followed by every file pulled in through `Include`,
+/// in the order ssh reads them. `files[0]` is always the main config.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSet {
+    pub files: Vec<ConfigFile>,
+}
+
+impl ConfigSet {
+    pub fn hosts(&self) -> Vec<SshHost> {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(file_index, file)| {
+                file.document.hosts().into_iter().map(move |mut host| {
+                    host.file_index = file_index;
+                    host
+                })
+            })
+            .collect()
+    }
+
+    pub fn match_rules(&self) -> Vec<MatchRule> {
+        self.files
+            .iter()
+            .enumerate()
+            .flat_map(|(file_index, file)| {
+                file.document.match_rules().into_iter().map(move |mut rule| {
+                    rule.file_index = file_index;
+                    rule
+                })
+            })
+            .collect()
+    }
+
+    /// Load problems plus line-level checks of every file, in file order.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut out: Vec<Diagnostic> = self
+            .files
+            .iter()
+            .enumerate()
+            .flat_map(|(file_index, file)| {
+                let mut found = file.load_diagnostics.clone();
+                found.extend(file.document.diagnostics());
+                found.into_iter().map(move |mut diagnostic| {
+                    diagnostic.file_index = file_index;
+                    diagnostic
+                })
+            })
+            .collect();
+
+        out.sort_by_key(|d| (d.file_index, d.line));
+        out
+    }
+
+    pub fn path_of(&self, file_index: usize) -> Option<&PathBuf> {
+        self.files.get(file_index).map(|f| &f.path)
+    }
+}
@@ -0,0 +1,46 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Error => "✖",
+            Self::Warning => "⚠",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+/// Something wrong with a config line that ssh would reject or ignore.
+/// The offending text is never rewritten; this only reports it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file_index: usize,
+    /// 1-based line number, or 0 when the problem concerns the whole file.
+    pub line: usize,
+    /// The `Host`/`Match` block the line belongs to, if any.
+    pub block_index: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(line: usize, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            file_index: 0,
+            line,
+            block_index: None,
+            severity,
+            message: message.into(),
+        }
+    }
+}
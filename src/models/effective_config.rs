@@ -0,0 +1,40 @@
+/// Where an effective value was taken from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionSource {
+    /// A line in one of the loaded config files.
+    Line {
+        file_index: usize,
+        line: usize,
+        /// The `Host ...` / `Match ...` header the line sits under, or
+        /// `None` for lines before the first block.
+        block: Option<String>,
+    },
+    /// Not set anywhere; the value ssh falls back to.
+    Default,
+}
+
+#[derive(Debug, Clone)]
+pub struct EffectiveOption {
+    pub keyword: String,
+    pub value: String,
+    pub source: OptionSource,
+}
+
+/// What ssh would use for one alias after applying every matching block,
+/// similar to `ssh -G <alias>` but computed without running ssh.
+#[derive(Debug, Clone, Default)]
+pub struct EffectiveConfig {
+    pub alias: String,
+    pub options: Vec<EffectiveOption>,
+    /// Blocks whose conditions can't be evaluated offline (`Match exec`, ...).
+    pub notes: Vec<String>,
+}
+
+impl EffectiveConfig {
+    pub fn value_of(&self, keyword: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|o| o.keyword.eq_ignore_ascii_case(keyword))
+            .map(|o| o.value.as_str())
+    }
+}
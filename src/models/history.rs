@@ -0,0 +1,159 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// One ssh session started from lazyssh.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub alias: String,
+    pub started: NaiveDateTime,
+    pub duration_secs: u64,
+    /// `None` when ssh could not be started or was killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+/// Connection counts and the latest session of one host.
+#[derive(Debug, Clone, Copy)]
+pub struct HostHistory<'a> {
+    pub count: usize,
+    pub last: &'a HistoryEntry,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    /// The order hosts are written in the config.
+    #[default]
+    Config,
+    /// Frequently and recently used hosts first.
+    Frecency,
+}
+
+impl SortMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Config => Self::Frecency,
+            Self::Frecency => Self::Config,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Config => "config order",
+            Self::Frecency => "most used",
+        }
+    }
+}
+
+/// Every recorded session, in the order they were recorded.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub fn for_host(&self, alias: &str) -> Option<HostHistory<'_>> {
+        let mut sessions = self.entries.iter().filter(|e| e.alias.eq_ignore_ascii_case(alias));
+        let first = sessions.next()?;
+        let (count, last) = sessions.fold((1, first), |(count, last), entry| {
+            (count + 1, if entry.started >= last.started { entry } else { last })
+        });
+        Some(HostHistory { count, last })
+    }
+
+    /// Each session counts for less the older it gets, so a host used
+    /// daily this week outranks one used often last year. Scores are keyed
+    /// by the lowercased alias and computed for every host in one pass.
+    pub fn frecency_scores(&self, now: NaiveDateTime) -> HashMap<String, u32> {
+        let mut scores = HashMap::new();
+        for entry in &self.entries {
+            *scores.entry(entry.alias.to_lowercase()).or_insert(0) += session_score(now - entry.started);
+        }
+        scores
+    }
+}
+
+fn session_score(age: Duration) -> u32 {
+    match age {
+        age if age < Duration::days(4) => 100,
+        age if age < Duration::days(14) => 70,
+        age if age < Duration::days(31) => 50,
+        age if age < Duration::days(90) => 30,
+        _ => 10,
+    }
+}
+
+/// "3h ago", "12d ago" for the detail panel.
+pub fn ago_display(then: NaiveDateTime, now: NaiveDateTime) -> String {
+    let age = now - then;
+    if age.num_days() > 0 {
+        format!("{}d ago", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h ago", age.num_hours())
+    } else if age.num_minutes() > 0 {
+        format!("{}m ago", age.num_minutes())
+    } else {
+        "just now".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn session(alias: &str, started: NaiveDateTime) -> HistoryEntry {
+        HistoryEntry { alias: alias.into(), started, duration_secs: 60, exit_code: Some(0) }
+    }
+
+    #[test]
+    fn sessions_score_by_age_bucket() {
+        let now = at(31, 12) + Duration::days(100);
+        let score = |age: Duration| History { entries: vec![session("web", now - age)] }.frecency_scores(now)["web"];
+
+        assert_eq!(score(Duration::zero()), 100);
+        assert_eq!(score(Duration::days(4) - Duration::seconds(1)), 100);
+        assert_eq!(score(Duration::days(4)), 70);
+        assert_eq!(score(Duration::days(14) - Duration::seconds(1)), 70);
+        assert_eq!(score(Duration::days(14)), 50);
+        assert_eq!(score(Duration::days(31) - Duration::seconds(1)), 50);
+        assert_eq!(score(Duration::days(31)), 30);
+        assert_eq!(score(Duration::days(90) - Duration::seconds(1)), 30);
+        assert_eq!(score(Duration::days(90)), 10);
+        assert_eq!(score(Duration::days(3650)), 10);
+    }
+
+    #[test]
+    fn scores_sum_per_alias_ignoring_case() {
+        let now = at(31, 12);
+        let history = History {
+            entries: vec![
+                session("web", at(31, 9)),
+                session("WEB", at(20, 9)),
+                session("db", at(1, 9)),
+                session("web", at(1, 9)),
+            ],
+        };
+
+        let scores = history.frecency_scores(now);
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores["web"], 100 + 70 + 50);
+        assert_eq!(scores["db"], 50);
+        assert!(!scores.contains_key("cache"));
+    }
+
+    #[test]
+    fn for_host_counts_sessions_and_keeps_the_latest() {
+        let history = History {
+            entries: vec![session("web", at(5, 9)), session("Web", at(7, 9)), session("web", at(6, 9))],
+        };
+        let host = history.for_host("WEB").unwrap();
+        assert_eq!(host.count, 3);
+        assert_eq!(host.last.started, at(7, 9));
+        assert!(history.for_host("db").is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Serialize;
+
+use crate::models::{Diagnostic, SshHost};
+
+/// Version of the `--json` output. Bumped only when a field is renamed,
+/// removed or changes meaning; new fields may appear within a version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// `lazyssh list --json`.
+#[derive(Debug, Serialize)]
+pub struct Inventory {
+    pub schema_version: u32,
+    pub config_path: String,
+    pub files: Vec<String>,
+    pub hosts: Vec<HostRecord>,
+    /// Problems outside any host block: global lines, `Match` blocks and
+    /// whole-file issues such as unreadable includes.
+    pub diagnostics: Vec<DiagnosticRecord>,
+}
+
+/// `lazyssh show --json`.
+#[derive(Debug, Serialize)]
+pub struct HostDocument {
+    pub schema_version: u32,
+    pub host: HostRecord,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HostRecord {
+    pub alias: String,
+    pub patterns: Vec<String>,
+    pub is_pattern: bool,
+    pub hostname: String,
+    pub port: u16,
+    /// The `Port` value as written when it is not a valid port number.
+    pub invalid_port: Option<String>,
+    pub user: String,
+    pub identity_files: Vec<String>,
+    pub certificate_files: Vec<String>,
+    pub local_forwards: Vec<String>,
+    pub remote_forwards: Vec<String>,
+    pub dynamic_forwards: Vec<String>,
+    pub send_env: Vec<String>,
+    pub extra_options: Vec<OptionRecord>,
+    pub source: SourceRecord,
+    pub diagnostics: Vec<DiagnosticRecord>,
+}
+
+impl HostRecord {
+    pub fn new(
+        host: &SshHost,
+        source: SourceRecord,
+        extra_options: Vec<OptionRecord>,
+        diagnostics: Vec<DiagnosticRecord>,
+    ) -> Self {
+        Self {
+            alias: host.alias().to_string(),
+            patterns: host.patterns.clone(),
+            is_pattern: host.is_pattern(),
+            hostname: host.hostname.clone(),
+            port: host.port,
+            invalid_port: host.invalid_port.clone(),
+            user: host.user.clone(),
+            identity_files: host.identity_files.clone(),
+            certificate_files: host.certificate_files.clone(),
+            local_forwards: host.local_forwards.clone(),
+            remote_forwards: host.remote_forwards.clone(),
+            dynamic_forwards: host.dynamic_forwards.clone(),
+            send_env: host.send_env.clone(),
+            extra_options,
+            source,
+            diagnostics,
+        }
+    }
+}
+
+/// Where a host block is written. Lines are 1-based and inclusive.
+#[derive(Debug, Serialize)]
+pub struct SourceRecord {
+    pub file: String,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OptionRecord {
+    pub keyword: String,
+    pub value: String,
+    pub line: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiagnosticRecord {
+    pub file: String,
+    /// 0 when the problem concerns the whole file.
+    pub line: usize,
+    pub severity: &'static str,
+    pub message: String,
+}
+
+impl DiagnosticRecord {
+    pub fn new(diagnostic: &Diagnostic, file: String) -> Self {
+        Self {
+            file,
+            line: diagnostic.line,
+            severity: diagnostic.severity.label(),
+            message: diagnostic.message.clone(),
+        }
+    }
+}
@@ -0,0 +1,457 @@
+/// What a keyword accepts as its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    /// `yes` or `no`.
+    Flag,
+    /// One of a fixed set of words.
+    Choice(&'static [&'static str]),
+    Number,
+    /// Seconds, optionally with `s`/`m`/`h`/`d`/`w` units, e.g. `1h30m`.
+    Duration,
+    /// `yes`, `no` or a duration, as used by `ControlPersist`.
+    FlagOrDuration,
+    /// `AddKeysToAgent`: `yes`/`no`/`ask`/`confirm`, optionally with a lifetime.
+    AgentLifetime,
+    Port,
+    Path,
+    /// `LocalForward`/`RemoteForward`: `[bind:]port host:hostport` or sockets.
+    Forward,
+    /// `DynamicForward`: `[bind:]port`.
+    DynamicForward,
+    /// Comma separated algorithm names, optionally prefixed with `+`, `-` or `^`.
+    Algorithms,
+    /// Anything, including several arguments.
+    Text,
+}
+
+pub struct KeywordSpec {
+    pub name: &'static str,
+    pub value: ValueType,
+}
+
+const fn spec(name: &'static str, value: ValueType) -> KeywordSpec {
+    KeywordSpec { name, value }
+}
+
+// ssh accepts `true`/`false` wherever it accepts `yes`/`no`.
+const YES_NO_ASK: &[&str] = &["yes", "no", "true", "false", "ask"];
+
+/// Client keywords from ssh_config(5) in OpenSSH 9.x.
+pub const KEYWORDS: &[KeywordSpec] = &[
+    spec("AddKeysToAgent", ValueType::AgentLifetime),
+    spec("AddressFamily", ValueType::Choice(&["any", "inet", "inet6"])),
+    spec("BatchMode", ValueType::Flag),
+    spec("BindAddress", ValueType::Text),
+    spec("BindInterface", ValueType::Text),
+    spec("CanonicalDomains", ValueType::Text),
+    spec("CanonicalizeFallbackLocal", ValueType::Flag),
+    spec("CanonicalizeHostname", ValueType::Choice(&["yes", "no", "true", "false", "always"])),
+    spec("CanonicalizeMaxDots", ValueType::Number),
+    spec("CanonicalizePermittedCNAMEs", ValueType::Text),
+    spec("CASignatureAlgorithms", ValueType::Algorithms),
+    spec("CertificateFile", ValueType::Path),
+    spec("ChannelTimeout", ValueType::Text),
+    spec("CheckHostIP", ValueType::Flag),
+    spec("Ciphers", ValueType::Algorithms),
+    spec("ClearAllForwardings", ValueType::Flag),
+    spec("Compression", ValueType::Flag),
+    spec("ConnectionAttempts", ValueType::Number),
+    spec("ConnectTimeout", ValueType::Duration),
+    spec(
+        "ControlMaster",
+        ValueType::Choice(&["yes", "no", "true", "false", "ask", "auto", "autoask"]),
+    ),
+    spec("ControlPath", ValueType::Path),
+    spec("ControlPersist", ValueType::FlagOrDuration),
+    spec("DynamicForward", ValueType::DynamicForward),
+    spec("EnableEscapeCommandline", ValueType::Flag),
+    spec("EnableSSHKeysign", ValueType::Flag),
+    spec("EscapeChar", ValueType::Text),
+    spec("ExitOnForwardFailure", ValueType::Flag),
+    spec("FingerprintHash", ValueType::Choice(&["md5", "sha256"])),
+    spec("ForkAfterAuthentication", ValueType::Flag),
+    // Also accepts an agent socket path or environment variable.
+    spec("ForwardAgent", ValueType::Text),
+    spec("ForwardX11", ValueType::Flag),
+    spec("ForwardX11Timeout", ValueType::Duration),
+    spec("ForwardX11Trusted", ValueType::Flag),
+    spec("GatewayPorts", ValueType::Flag),
+    spec("GlobalKnownHostsFile", ValueType::Text),
+    spec("GSSAPIAuthentication", ValueType::Flag),
+    spec("GSSAPIDelegateCredentials", ValueType::Flag),
+    spec("HashKnownHosts", ValueType::Flag),
+    spec("Host", ValueType::Text),
+    spec("HostbasedAcceptedAlgorithms", ValueType::Algorithms),
+    spec("HostbasedAuthentication", ValueType::Flag),
+    spec("HostKeyAlgorithms", ValueType::Algorithms),
+    spec("HostKeyAlias", ValueType::Text),
+    spec("HostName", ValueType::Text),
+    spec("IdentitiesOnly", ValueType::Flag),
+    spec("IdentityAgent", ValueType::Path),
+    spec("IdentityFile", ValueType::Path),
+    spec("IgnoreUnknown", ValueType::Text),
+    spec("Include", ValueType::Text),
+    spec("IPQoS", ValueType::Text),
+    spec("KbdInteractiveAuthentication", ValueType::Flag),
+    spec("KbdInteractiveDevices", ValueType::Text),
+    spec("KexAlgorithms", ValueType::Algorithms),
+    spec("KnownHostsCommand", ValueType::Text),
+    spec("LocalCommand", ValueType::Text),
+    spec("LocalForward", ValueType::Forward),
+    spec(
+        "LogLevel",
+        ValueType::Choice(&[
+            "quiet", "fatal", "error", "info", "verbose", "debug", "debug1", "debug2", "debug3",
+        ]),
+    ),
+    spec("LogVerbose", ValueType::Text),
+    spec("MACs", ValueType::Algorithms),
+    spec("Match", ValueType::Text),
+    spec("NoHostAuthenticationForLocalhost", ValueType::Flag),
+    spec("NumberOfPasswordPrompts", ValueType::Number),
+    spec("ObscureKeystrokeTiming", ValueType::Text),
+    spec("PasswordAuthentication", ValueType::Flag),
+    spec("PermitLocalCommand", ValueType::Flag),
+    spec("PermitRemoteOpen", ValueType::Text),
+    spec("PKCS11Provider", ValueType::Path),
+    spec("Port", ValueType::Port),
+    spec("PreferredAuthentications", ValueType::Text),
+    spec("ProxyCommand", ValueType::Text),
+    spec("ProxyJump", ValueType::Text),
+    spec("ProxyUseFdpass", ValueType::Flag),
+    spec("PubkeyAcceptedAlgorithms", ValueType::Algorithms),
+    spec(
+        "PubkeyAuthentication",
+        ValueType::Choice(&["yes", "no", "true", "false", "unbound", "host-bound"]),
+    ),
+    spec("RekeyLimit", ValueType::Text),
+    spec("RemoteCommand", ValueType::Text),
+    spec("RemoteForward", ValueType::Forward),
+    spec("RequestTTY", ValueType::Choice(&["yes", "no", "true", "false", "force", "auto"])),
+    spec("RequiredRSASize", ValueType::Number),
+    spec("RevokedHostKeys", ValueType::Path),
+    spec("SecurityKeyProvider", ValueType::Path),
+    spec("SendEnv", ValueType::Text),
+    spec("ServerAliveCountMax", ValueType::Number),
+    spec("ServerAliveInterval", ValueType::Duration),
+    spec("SessionType", ValueType::Choice(&["none", "subsystem", "default"])),
+    spec("SetEnv", ValueType::Text),
+    spec("StdinNull", ValueType::Flag),
+    spec("StreamLocalBindMask", ValueType::Text),
+    spec("StreamLocalBindUnlink", ValueType::Flag),
+    spec(
+        "StrictHostKeyChecking",
+        ValueType::Choice(&["yes", "no", "true", "false", "ask", "accept-new", "off"]),
+    ),
+    spec("SyslogFacility", ValueType::Text),
+    spec("Tag", ValueType::Text),
+    spec("TCPKeepAlive", ValueType::Flag),
+    spec(
+        "Tunnel",
+        ValueType::Choice(&["yes", "no", "true", "false", "point-to-point", "ethernet"]),
+    ),
+    spec("TunnelDevice", ValueType::Text),
+    spec("UpdateHostKeys", ValueType::Choice(YES_NO_ASK)),
+    // macOS builds only; harmless to accept everywhere.
+    spec("UseKeychain", ValueType::Flag),
+    spec("User", ValueType::Text),
+    spec("UserKnownHostsFile", ValueType::Text),
+    spec("VerifyHostKeyDNS", ValueType::Choice(YES_NO_ASK)),
+    spec("VisualHostKey", ValueType::Flag),
+    spec("XAuthLocation", ValueType::Path),
+];
+
+/// Keywords ssh still parses but ignores or has renamed, with what to use instead.
+pub const DEPRECATED_KEYWORDS: &[(&str, &str)] = &[
+    ("Protocol", "SSH protocol 1 is gone; remove it"),
+    ("Cipher", "protocol 1 only; use Ciphers"),
+    ("UseRoaming", "roaming was removed; remove it"),
+    ("RSAAuthentication", "protocol 1 only; remove it"),
+    ("RhostsRSAAuthentication", "protocol 1 only; remove it"),
+    ("CompressionLevel", "protocol 1 only; remove it"),
+    ("UsePrivilegedPort", "no longer supported; remove it"),
+    ("ChallengeResponseAuthentication", "renamed to KbdInteractiveAuthentication"),
+    ("PubkeyAcceptedKeyTypes", "renamed to PubkeyAcceptedAlgorithms"),
+    ("HostbasedKeyTypes", "renamed to HostbasedAcceptedAlgorithms"),
+];
+
+pub fn lookup(keyword: &str) -> Option<&'static KeywordSpec> {
+    KEYWORDS.iter().find(|k| k.name.eq_ignore_ascii_case(keyword))
+}
+
+pub fn deprecation(keyword: &str) -> Option<&'static str> {
+    DEPRECATED_KEYWORDS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(keyword))
+        .map(|(_, hint)| *hint)
+}
+
+/// The closest known keyword, for "did you mean" hints on typos.
+pub fn suggest(keyword: &str) -> Option<&'static str> {
+    let typed = keyword.to_lowercase();
+    KEYWORDS
+        .iter()
+        .map(|k| (k.name, edit_distance(&typed, &k.name.to_lowercase())))
+        .filter(|(_, distance)| *distance <= 2)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(name, _)| name)
+}
+
+/// Levenshtein distance that also counts a swap of adjacent letters as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+impl ValueType {
+    /// Checks the arguments of one directive, returning why they are invalid.
+    pub fn check(&self, args: &[String]) -> Result<(), String> {
+        let single = || -> Result<&str, String> {
+            match args {
+                [one] => Ok(one.as_str()),
+                _ => Err(format!("expects one value, got {}", args.len())),
+            }
+        };
+
+        match self {
+            Self::Text | Self::Path => Ok(()),
+            Self::Flag => {
+                let value = single()?;
+                if is_flag(value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not yes or no", value))
+                }
+            }
+            Self::Choice(options) => {
+                let value = single()?;
+                if options.iter().any(|o| o.eq_ignore_ascii_case(value)) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not one of {}", value, options.join(", ")))
+                }
+            }
+            Self::Number => {
+                let value = single()?;
+                value
+                    .parse::<u64>()
+                    .map(|_| ())
+                    .map_err(|_| format!("'{}' is not a number", value))
+            }
+            Self::Duration => {
+                let value = single()?;
+                if is_duration(value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not a duration like 30, 5m or 1h30m", value))
+                }
+            }
+            Self::FlagOrDuration => {
+                let value = single()?;
+                if is_flag(value) || is_duration(value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not yes, no or a duration", value))
+                }
+            }
+            Self::AgentLifetime => {
+                let value = single()?;
+                let valid = value.split(',').all(|part| {
+                    ["yes", "no", "true", "false", "ask", "confirm"]
+                        .iter()
+                        .any(|o| o.eq_ignore_ascii_case(part))
+                        || is_duration(part)
+                });
+                if valid {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not yes, no, ask, confirm or a lifetime", value))
+                }
+            }
+            Self::Port => {
+                let value = single()?;
+                if is_port(value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not a port between 1 and 65535", value))
+                }
+            }
+            Self::DynamicForward => {
+                let value = single()?;
+                if is_listen_spec(value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not [bind_address:]port", value))
+                }
+            }
+            Self::Forward => match args {
+                // RemoteForward with a single port is a dynamic SOCKS forward.
+                [listen] if is_listen_spec(listen) => Ok(()),
+                [listen, target] if is_listen_spec(listen) && is_target_spec(target) => Ok(()),
+                [listen, _] if !is_listen_spec(listen) => {
+                    Err(format!("'{}' is not [bind_address:]port or a socket path", listen))
+                }
+                [_, target] => Err(format!("'{}' is not host:hostport or a socket path", target)),
+                _ => Err("expects '[bind_address:]port host:hostport'".into()),
+            },
+            Self::Algorithms => {
+                let value = single()?;
+                let list = value.trim_start_matches(['+', '-', '^']);
+                if list.split(',').all(|name| !name.is_empty()) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' has an empty entry in the algorithm list", value))
+                }
+            }
+        }
+    }
+}
+
+fn is_flag(value: &str) -> bool {
+    ["yes", "no", "true", "false"]
+        .iter()
+        .any(|o| o.eq_ignore_ascii_case(value))
+}
+
+fn is_duration(value: &str) -> bool {
+    if value.is_empty() || !value.starts_with(|c: char| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let mut digits = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => digits = true,
+            's' | 'S' | 'm' | 'M' | 'h' | 'H' | 'd' | 'D' | 'w' | 'W' if digits => digits = false,
+            _ => return false,
+        }
+    }
+    true
+}
+
+fn is_port(value: &str) -> bool {
+    value.parse::<u16>().is_ok_and(|port| port > 0)
+}
+
+/// `port`, `bind:port`, `[v6]:port`, `*:port` or a unix socket path.
+fn is_listen_spec(value: &str) -> bool {
+    if value.contains('/') {
+        return true;
+    }
+    match value.rsplit_once(':') {
+        Some((bind, port)) => !bind.is_empty() && is_port(port),
+        None => is_port(value),
+    }
+}
+
+/// `host:port`, `[v6]:port` or a unix socket path.
+fn is_target_spec(value: &str) -> bool {
+    if value.contains('/') {
+        return true;
+    }
+    value
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && is_port(port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(keyword: &str, value: &str) -> Result<(), String> {
+        let args: Vec<String> = value.split_whitespace().map(str::to_string).collect();
+        lookup(keyword).unwrap().value.check(&args)
+    }
+
+    #[test]
+    fn multistate_keywords_take_true_and_false() {
+        for keyword in [
+            "ControlMaster",
+            "RequestTTY",
+            "StrictHostKeyChecking",
+            "CanonicalizeHostname",
+            "PubkeyAuthentication",
+            "Tunnel",
+            "UpdateHostKeys",
+            "VerifyHostKeyDNS",
+            "AddKeysToAgent",
+            "BatchMode",
+        ] {
+            for value in ["yes", "no", "true", "false", "TRUE"] {
+                assert_eq!(check(keyword, value), Ok(()), "{} {}", keyword, value);
+            }
+        }
+        assert_eq!(check("StrictHostKeyChecking", "off"), Ok(()));
+        assert_eq!(check("StrictHostKeyChecking", "accept-new"), Ok(()));
+        assert_eq!(check("ControlMaster", "autoask"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_values_ssh_rejects() {
+        assert_eq!(
+            check("CanonicalizeHostname", "none"),
+            Err("'none' is not one of yes, no, true, false, always".into())
+        );
+        assert_eq!(check("BatchMode", "on"), Err("'on' is not yes or no".into()));
+        assert!(check("RequestTTY", "sometimes").is_err());
+        assert_eq!(check("ConnectionAttempts", "three"), Err("'three' is not a number".into()));
+        assert_eq!(check("Port", "0"), Err("'0' is not a port between 1 and 65535".into()));
+        assert_eq!(check("Port", "22 23"), Err("expects one value, got 2".into()));
+    }
+
+    #[test]
+    fn checks_durations_forwards_and_algorithms() {
+        assert_eq!(check("ServerAliveInterval", "1h30m"), Ok(()));
+        assert!(check("ServerAliveInterval", "m5").is_err());
+        assert_eq!(check("ControlPersist", "no"), Ok(()));
+        assert_eq!(check("ControlPersist", "10m"), Ok(()));
+        assert_eq!(check("AddKeysToAgent", "confirm,1h"), Ok(()));
+
+        assert_eq!(check("LocalForward", "8080 localhost:80"), Ok(()));
+        assert_eq!(check("LocalForward", "127.0.0.1:8080 [::1]:80"), Ok(()));
+        assert_eq!(check("RemoteForward", "1080"), Ok(()));
+        assert_eq!(check("LocalForward", "/tmp/a.sock /tmp/b.sock"), Ok(()));
+        assert_eq!(
+            check("LocalForward", "8080 localhost"),
+            Err("'localhost' is not host:hostport or a socket path".into())
+        );
+        assert_eq!(check("DynamicForward", "localhost:1080"), Ok(()));
+
+        assert_eq!(check("Ciphers", "+aes128-ctr,aes256-ctr"), Ok(()));
+        assert!(check("Ciphers", "aes128-ctr,,aes256-ctr").is_err());
+        assert_eq!(check("IdentityFile", "~/.ssh/id_ed25519"), Ok(()));
+    }
+
+    #[test]
+    fn looks_keywords_up_without_case() {
+        assert_eq!(lookup("hostname").map(|k| k.name), Some("HostName"));
+        assert!(lookup("HostNmae").is_none());
+        assert_eq!(suggest("HostNmae"), Some("HostName"));
+        assert_eq!(suggest("Identityfiel"), Some("IdentityFile"));
+        assert_eq!(suggest("Completely"), None);
+        assert_eq!(deprecation("protocol"), Some("SSH protocol 1 is gone; remove it"));
+        assert_eq!(deprecation("User"), None);
+    }
+}
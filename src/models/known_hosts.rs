@@ -0,0 +1,528 @@
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use crate::models::pattern::wildcard_match;
+use crate::models::PublicKey;
+
+/// The `@marker` in front of a known_hosts line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    /// The key is a CA that signs host certificates for the listed hosts.
+    CertAuthority,
+    /// The key must never be accepted for the listed hosts.
+    Revoked,
+}
+
+/// The host field of a known_hosts line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HostField {
+    /// Comma-separated patterns such as `web,10.0.0.5` or `[git.corp]:2222`.
+    Patterns(Vec<String>),
+    /// `|1|salt|hash`: HMAC-SHA1 of the host name keyed with the salt, as
+    /// written by `HashKnownHosts yes`.
+    Hashed { salt: Vec<u8>, hash: Vec<u8> },
+}
+
+#[derive(Debug, Clone)]
+pub struct KnownHostEntry {
+    pub file_index: usize,
+    /// 1-based line number.
+    pub line: usize,
+    pub marker: Option<Marker>,
+    pub hosts: HostField,
+    pub key: PublicKey,
+}
+
+/// One known_hosts file as read, kept line by line so edits can leave
+/// comments and unparsable lines untouched.
+#[derive(Debug, Clone)]
+pub struct KnownHostsFile {
+    pub path: PathBuf,
+    pub lines: Vec<String>,
+    /// `/etc/ssh/ssh_known_hosts`, which lazyssh only reads.
+    pub system: bool,
+}
+
+impl KnownHostEntry {
+    /// Parses one line; comments, blank lines and malformed entries give `None`.
+    pub fn parse(text: &str, file_index: usize, line: usize) -> Option<Self> {
+        let text = text.trim();
+        if text.is_empty() || text.starts_with('#') {
+            return None;
+        }
+
+        let (marker, rest) = match text.split_once(char::is_whitespace) {
+            Some(("@cert-authority", rest)) => (Some(Marker::CertAuthority), rest.trim_start()),
+            Some(("@revoked", rest)) => (Some(Marker::Revoked), rest.trim_start()),
+            Some((word, _)) if word.starts_with('@') => return None,
+            _ => (None, text),
+        };
+
+        let (hosts, key) = rest.split_once(char::is_whitespace)?;
+        Some(Self {
+            file_index,
+            line,
+            marker,
+            hosts: Self::parse_hosts(hosts)?,
+            key: PublicKey::parse_line(key.trim_start())?,
+        })
+    }
+
+    /// The host names a line stands for: its literal patterns, or for a
+    /// hashed line whichever of `candidates` hash to it.
+    pub fn names(&self, candidates: &[String]) -> Vec<String> {
+        match &self.hosts {
+            HostField::Patterns(patterns) => patterns
+                .iter()
+                .filter(|p| !p.starts_with('!') && !p.contains(['*', '?']))
+                .cloned()
+                .collect(),
+            HostField::Hashed { .. } => candidates.iter().filter(|c| self.matches_name(c)).cloned().collect(),
+        }
+    }
+
+    pub fn is_hashed(&self) -> bool {
+        matches!(self.hosts, HostField::Hashed { .. })
+    }
+
+    /// The host field as written; hashed entries reveal nothing.
+    pub fn hosts_display(&self) -> String {
+        match &self.hosts {
+            HostField::Patterns(patterns) => patterns.join(","),
+            HostField::Hashed { .. } => "(hashed)".into(),
+        }
+    }
+
+    fn parse_hosts(field: &str) -> Option<HostField> {
+        match field.strip_prefix("|1|") {
+            Some(hashed) => {
+                let (salt, hash) = hashed.split_once('|')?;
+                Some(HostField::Hashed {
+                    salt: STANDARD.decode(salt).ok()?,
+                    hash: STANDARD.decode(hash).ok()?,
+                })
+            }
+            None => Some(HostField::Patterns(
+                field.split(',').filter(|p| !p.is_empty()).map(str::to_lowercase).collect(),
+            )),
+        }
+    }
+
+    /// Whether ssh would consult this line for `host` on `port`.
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        self.matches_name(&lookup_name(host, port))
+    }
+
+    /// Like `matches`, for a name already in `lookup_name` form.
+    pub fn matches_name(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        match &self.hosts {
+            HostField::Hashed { salt, hash } => hash_name(salt, &name).as_deref() == Some(hash.as_slice()),
+            HostField::Patterns(patterns) => {
+                let mut matched = false;
+                for pattern in patterns {
+                    match pattern.strip_prefix('!') {
+                        Some(negated) if wildcard_match(negated, &name) => return false,
+                        Some(_) => {}
+                        None if wildcard_match(pattern, &name) => matched = true,
+                        None => {}
+                    }
+                }
+                matched
+            }
+        }
+    }
+}
+
+/// How known_hosts names a host: the bare name on port 22, otherwise
+/// `[name]:port`.
+pub fn lookup_name(host: &str, port: u16) -> String {
+    let host = host.to_lowercase();
+    if port == 22 {
+        host
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+pub fn hash_name(salt: &[u8], name: &str) -> Option<Vec<u8>> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(salt).ok()?;
+    mac.update(name.as_bytes());
+    Some(mac.finalize().into_bytes().to_vec())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrustStatus {
+    /// A key or certificate authority for the host is on file.
+    Trusted,
+    /// Nothing on file; ssh will ask on the first connection.
+    Unknown,
+    /// A key on file for the host is also listed as `@revoked`.
+    Revoked,
+}
+
+impl TrustStatus {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Trusted => "✔",
+            Self::Unknown => "?",
+            Self::Revoked => "✖",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Trusted => "trusted",
+            Self::Unknown => "unknown",
+            Self::Revoked => "revoked",
+        }
+    }
+}
+
+/// A known_hosts line that applies to a host, ready for display.
+#[derive(Debug, Clone)]
+pub struct KnownKey {
+    pub marker: Option<Marker>,
+    pub algorithm: String,
+    pub fingerprint: String,
+    pub file_index: usize,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct HostTrust {
+    pub status: TrustStatus,
+    /// The name looked up: `HostKeyAlias`, or `HostName` with the port.
+    pub lookup_name: String,
+    pub keys: Vec<KnownKey>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KnownHosts {
+    /// The files read, user files first, then the system-wide file.
+    pub files: Vec<KnownHostsFile>,
+    pub entries: Vec<KnownHostEntry>,
+}
+
+impl KnownHosts {
+    pub fn trust_for(&self, host: &str, port: u16) -> HostTrust {
+        let matching: Vec<&KnownHostEntry> = self.entries.iter().filter(|e| e.matches(host, port)).collect();
+
+        let revoked = |key: &PublicKey| {
+            matching
+                .iter()
+                .any(|e| e.marker == Some(Marker::Revoked) && e.key.blob == key.blob)
+        };
+        let status = if matching.iter().any(|e| e.marker.is_none() && revoked(&e.key)) {
+            TrustStatus::Revoked
+        } else if matching.iter().any(|e| e.marker != Some(Marker::Revoked)) {
+            TrustStatus::Trusted
+        } else {
+            TrustStatus::Unknown
+        };
+
+        HostTrust {
+            status,
+            lookup_name: lookup_name(host, port),
+            keys: matching
+                .iter()
+                .map(|e| KnownKey {
+                    marker: e.marker,
+                    algorithm: e.key.algorithm().to_string(),
+                    fingerprint: e.key.fingerprint(),
+                    file_index: e.file_index,
+                    line: e.line,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssueKind {
+    /// The same key is listed more than once for a name.
+    Duplicate,
+    /// Different keys of the same type are listed for a name; ssh accepts
+    /// either, and one of them is usually a rebuilt server's old key.
+    Conflict,
+}
+
+#[derive(Debug, Clone)]
+pub struct KnownHostsIssue {
+    pub kind: IssueKind,
+    pub name: String,
+    pub key_type: String,
+    /// Indices into `KnownHosts::entries`, in file order.
+    pub entries: Vec<usize>,
+}
+
+impl KnownHostsIssue {
+    pub fn describe(&self, known: &KnownHosts) -> String {
+        let lines: Vec<String> = self
+            .entries
+            .iter()
+            .filter_map(|&i| known.entries.get(i))
+            .map(|e| e.line.to_string())
+            .collect();
+        match self.kind {
+            IssueKind::Duplicate => format!(
+                "{} {} key listed on lines {}",
+                self.name,
+                self.key_type,
+                lines.join(", ")
+            ),
+            IssueKind::Conflict => format!(
+                "{} has different {} keys on lines {}",
+                self.name,
+                self.key_type,
+                lines.join(", ")
+            ),
+        }
+    }
+}
+
+impl KnownHosts {
+    /// Duplicate and conflicting plain entries. Hashed lines take part when
+    /// one of `candidates` (lookup names of configured hosts) hashes to them.
+    pub fn issues(&self, candidates: &[String]) -> Vec<KnownHostsIssue> {
+        let mut groups: Vec<((String, String), Vec<usize>)> = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            if entry.marker.is_some() {
+                continue;
+            }
+            for name in entry.names(candidates) {
+                let group = (name, entry.key.key_type.clone());
+                match groups.iter_mut().find(|(g, _)| *g == group) {
+                    Some((_, members)) => members.push(index),
+                    None => groups.push((group, vec![index])),
+                }
+            }
+        }
+
+        let mut issues = Vec::new();
+        for ((name, key_type), members) in groups.into_iter().filter(|(_, m)| m.len() > 1) {
+            let first = &self.entries[members[0]].key.blob;
+            let kind = if members.iter().all(|&i| self.entries[i].key.blob == *first) {
+                IssueKind::Duplicate
+            } else {
+                IssueKind::Conflict
+            };
+            issues.push(KnownHostsIssue {
+                kind,
+                name,
+                key_type,
+                entries: members,
+            });
+        }
+        issues
+    }
+
+    /// The file's text with the given 1-based lines left out.
+    pub fn text_without(&self, file_index: usize, lines: &[usize]) -> String {
+        let Some(file) = self.files.get(file_index) else {
+            return String::new();
+        };
+        join_lines(
+            file.lines
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !lines.contains(&(i + 1)))
+                .map(|(_, l)| l.clone()),
+        )
+    }
+
+    /// The file's text with every plain host name hashed, one name per line,
+    /// the way `ssh-keygen -H` does it. Lines with markers, wildcards or
+    /// negations stay as they are. Returns the text and the number of lines
+    /// hashed.
+    pub fn hashed_text(&self, file_index: usize, mut salt: impl FnMut() -> Vec<u8>) -> (String, usize) {
+        let Some(file) = self.files.get(file_index) else {
+            return (String::new(), 0);
+        };
+
+        let mut hashed = 0;
+        let mut out = Vec::with_capacity(file.lines.len());
+        for (i, text) in file.lines.iter().enumerate() {
+            let entry = self.entries.iter().find(|e| e.file_index == file_index && e.line == i + 1);
+            let patterns = match entry.map(|e| (e.marker, &e.hosts)) {
+                Some((None, HostField::Patterns(patterns)))
+                    if patterns.iter().all(|p| !p.starts_with('!') && !p.contains(['*', '?'])) =>
+                {
+                    patterns
+                }
+                _ => {
+                    out.push(text.clone());
+                    continue;
+                }
+            };
+
+            let key = text.trim().split_once(char::is_whitespace).map_or("", |(_, k)| k.trim_start());
+            for name in patterns {
+                let salt = salt();
+                let Some(hash) = hash_name(&salt, name) else { continue };
+                out.push(format!("|1|{}|{} {}", STANDARD.encode(&salt), STANDARD.encode(hash), key));
+            }
+            hashed += 1;
+        }
+        (join_lines(out.into_iter()), hashed)
+    }
+}
+
+fn join_lines(lines: impl Iterator<Item = String>) -> String {
+    let mut text: String = lines.map(|l| l + "\n").collect();
+    if text.trim().is_empty() {
+        text.clear();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ED25519: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIBYLF4TDJr3RU4wgaIoCHdzjK6qpd/YqNHGS3HHE3Frj";
+    const ECDSA: &str = "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBAvRIyOqm5bxOA/x46n/B8AZRL0MGOo0R9xi3RAYNEq9f1reU9A7fgYucz7b4xR7R4HegJfuqaAOOhcLNItSDJo=";
+
+    /// Written by `ssh-keygen -H` for `web.example.com` and
+    /// `[git.example.com]:2222`.
+    const HASHED_WEB: &str = "|1|WXLsVexEVoks1jIuyS3KFOEuyWQ=|fhNXTWLA8rb87z8j8sovhn1TuDo=";
+    const HASHED_GIT: &str = "|1|AFCUgn5G8xHS57hKVwaU5xV0o4s=|0Mm3hPggIPjlbca8QZiXIK1aEDs=";
+
+    fn entry(line: &str) -> KnownHostEntry {
+        KnownHostEntry::parse(line, 0, 1).unwrap()
+    }
+
+    fn known(lines: &[String]) -> KnownHosts {
+        KnownHosts {
+            files: vec![KnownHostsFile {
+                path: PathBuf::from("known_hosts"),
+                lines: lines.to_vec(),
+                system: false,
+            }],
+            entries: lines
+                .iter()
+                .enumerate()
+                .filter_map(|(i, l)| KnownHostEntry::parse(l, 0, i + 1))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn hash_name_reproduces_ssh_keygen() {
+        let HostField::Hashed { salt, hash } = entry(&format!("{} {}", HASHED_WEB, ED25519)).hosts else {
+            panic!("not hashed");
+        };
+        assert_eq!(hash_name(&salt, "web.example.com"), Some(hash));
+    }
+
+    #[test]
+    fn hashed_lines_match_only_their_name() {
+        let web = entry(&format!("{} {}", HASHED_WEB, ED25519));
+        assert!(web.is_hashed());
+        assert!(web.matches("web.example.com", 22));
+        assert!(web.matches("WEB.example.com", 22));
+        assert!(!web.matches("web.example.com", 2222));
+        assert!(!web.matches("db.example.com", 22));
+        assert_eq!(web.hosts_display(), "(hashed)");
+
+        let git = entry(&format!("{} {}", HASHED_GIT, ED25519));
+        assert!(git.matches_name("[git.example.com]:2222"));
+        assert!(!git.matches("git.example.com", 22));
+        let candidates = vec!["git.example.com".to_string(), "[git.example.com]:2222".to_string()];
+        assert_eq!(git.names(&candidates), vec!["[git.example.com]:2222".to_string()]);
+    }
+
+    #[test]
+    fn bracketed_entries_only_match_their_port() {
+        assert_eq!(lookup_name("Git.Example.com", 22), "git.example.com");
+        assert_eq!(lookup_name("git.example.com", 2222), "[git.example.com]:2222");
+
+        let git = entry(&format!("[git.example.com]:2222,10.0.0.5 {}", ED25519));
+        assert!(git.matches("git.example.com", 2222));
+        assert!(!git.matches("git.example.com", 22));
+        assert!(!git.matches("git.example.com", 2200));
+        assert!(git.matches("10.0.0.5", 22));
+    }
+
+    #[test]
+    fn negated_patterns_win_over_wildcards() {
+        let wild = entry(&format!("*.example.com,!bad.example.com {}", ED25519));
+        assert!(wild.matches("web.example.com", 22));
+        assert!(!wild.matches("bad.example.com", 22));
+        assert!(wild.names(&[]).is_empty());
+    }
+
+    #[test]
+    fn parses_markers_and_skips_unknown_ones() {
+        let ca = entry(&format!("@cert-authority *.example.com {}", ED25519));
+        assert_eq!(ca.marker, Some(Marker::CertAuthority));
+        assert!(ca.matches("web.example.com", 22));
+
+        let revoked = entry(&format!("@revoked web.example.com {}", ED25519));
+        assert_eq!(revoked.marker, Some(Marker::Revoked));
+
+        assert!(KnownHostEntry::parse(&format!("@bogus web.example.com {}", ED25519), 0, 1).is_none());
+        assert!(KnownHostEntry::parse("# web.example.com", 0, 1).is_none());
+        assert!(KnownHostEntry::parse("web.example.com ssh-ed25519 garbage", 0, 1).is_none());
+    }
+
+    #[test]
+    fn trust_for_plain_and_hashed_entries() {
+        let known = known(&[
+            format!("{} {}", HASHED_WEB, ED25519),
+            format!("[git.example.com]:2222 {}", ECDSA),
+            "# comment".to_string(),
+        ]);
+
+        let web = known.trust_for("web.example.com", 22);
+        assert_eq!(web.status, TrustStatus::Trusted);
+        assert_eq!(web.lookup_name, "web.example.com");
+        assert_eq!(web.keys.len(), 1);
+        assert_eq!(web.keys[0].algorithm, "ED25519");
+        assert_eq!(web.keys[0].line, 1);
+
+        let git = known.trust_for("git.example.com", 2222);
+        assert_eq!(git.status, TrustStatus::Trusted);
+        assert_eq!(git.keys[0].algorithm, "ECDSA");
+        assert_eq!(git.keys[0].line, 2);
+
+        assert_eq!(known.trust_for("git.example.com", 22).status, TrustStatus::Unknown);
+        assert!(known.trust_for("db.example.com", 22).keys.is_empty());
+    }
+
+    #[test]
+    fn revoked_key_overrides_a_plain_entry() {
+        let known = known(&[
+            format!("web.example.com {}", ED25519),
+            format!("@revoked *.example.com {}", ED25519),
+            format!("db.example.com {}", ECDSA),
+        ]);
+
+        let web = known.trust_for("web.example.com", 22);
+        assert_eq!(web.status, TrustStatus::Revoked);
+        assert_eq!(web.keys.len(), 2);
+        assert_eq!(web.keys[1].marker, Some(Marker::Revoked));
+
+        // Revoking a different key leaves the host trusted.
+        assert_eq!(known.trust_for("db.example.com", 22).status, TrustStatus::Trusted);
+        // A revocation alone vouches for nothing.
+        assert_eq!(known.trust_for("mail.example.com", 22).status, TrustStatus::Unknown);
+    }
+
+    #[test]
+    fn cert_authority_makes_a_host_trusted() {
+        let known = known(&[format!("@cert-authority *.example.com {}", ECDSA)]);
+
+        let web = known.trust_for("web.example.com", 22);
+        assert_eq!(web.status, TrustStatus::Trusted);
+        assert_eq!(web.keys[0].marker, Some(Marker::CertAuthority));
+        // On another port ssh looks up `[web.example.com]:2222`, which the
+        // pattern does not cover.
+        assert_eq!(known.trust_for("web.example.com", 2222).status, TrustStatus::Unknown);
+        assert_eq!(known.trust_for("web.example.org", 22).status, TrustStatus::Unknown);
+    }
+}
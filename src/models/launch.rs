@@ -0,0 +1,31 @@
+/// The program and arguments lazyssh runs to connect to a host.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchCommand {
+    /// The host this connects to, for the connection history.
+    pub alias: String,
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl LaunchCommand {
+    /// The command as it would be typed in a POSIX shell.
+    pub fn display(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|word| shell_quote(word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,65 @@
+use crate::models::ssh_config::split_arguments;
+
+/// Criteria that stand alone; every other criterion takes one argument.
+const FLAG_CRITERIA: [&str; 3] = ["all", "canonical", "final"];
+
+/// A single `Match` criterion such as `host web*`, `!user root` or `final`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchCriterion {
+    pub keyword: String,
+    pub negated: bool,
+    pub argument: Option<String>,
+}
+
+impl MatchCriterion {
+    pub fn display(&self) -> String {
+        let bang = if self.negated { "!" } else { "" };
+        match &self.argument {
+            Some(arg) if arg.contains(char::is_whitespace) => {
+                format!("{}{} \"{}\"", bang, self.keyword, arg)
+            }
+            Some(arg) => format!("{}{} {}", bang, self.keyword, arg),
+            None => format!("{}{}", bang, self.keyword),
+        }
+    }
+}
+
+/// A `Match` block. Shown read-only; its lines are never rewritten.
+#[derive(Debug, Clone)]
+pub struct MatchRule {
+    pub criteria: Vec<MatchCriterion>,
+    pub options: Vec<(String, String)>,
+    pub file_index: usize,
+    pub line: usize,
+}
+
+impl MatchRule {
+    pub fn parse_criteria(value: &str) -> Vec<MatchCriterion> {
+        let mut tokens = split_arguments(value).into_iter();
+        let mut criteria = Vec::new();
+
+        while let Some(token) = tokens.next() {
+            let (negated, keyword) = match token.strip_prefix('!') {
+                Some(rest) => (true, rest.to_lowercase()),
+                None => (false, token.to_lowercase()),
+            };
+            let argument = if FLAG_CRITERIA.contains(&keyword.as_str()) {
+                None
+            } else {
+                tokens.next()
+            };
+
+            criteria.push(MatchCriterion { keyword, negated, argument });
+        }
+
+        criteria
+    }
+
+    pub fn summary(&self) -> String {
+        self.criteria
+            .iter()
+            .map(MatchCriterion::display)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
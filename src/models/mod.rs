@@ -1,7 +1,42 @@
 pub mod app_state;
+pub mod audit;
+pub mod backup;
+pub mod banner;
+pub mod config_set;
+pub mod diagnostic;
+pub mod effective_config;
+pub mod history;
+pub mod inventory;
+pub mod keyword_catalog;
+pub mod known_hosts;
+pub mod launch;
+pub mod match_rule;
+pub mod pattern;
+pub mod probe;
+pub mod session_failure;
+pub mod settings;
+pub mod ssh_config;
 pub mod ssh_host;
+pub mod ssh_key;
 pub mod theme;
 
-pub use app_state::{Action, FormField, Mode};
+pub use app_state::{Action, FormField, KeyGenField, KnownHostsEdit, Mode, LIST_SEPARATOR};
+pub use audit::{AuditFinding, AuditSeverity};
+pub use backup::BackupEntry;
+pub use banner::BannerRecord;
+pub use config_set::{ConfigFile, ConfigSet, FileStamp, MAX_INCLUDE_DEPTH};
+pub use diagnostic::{Diagnostic, Severity};
+pub use effective_config::{EffectiveConfig, EffectiveOption, OptionSource};
+pub use history::{History, HistoryEntry, HostHistory, SortMode};
+pub use known_hosts::{
+    HostTrust, IssueKind, KnownHostEntry, KnownHosts, KnownHostsFile, KnownHostsIssue, Marker, TrustStatus,
+};
+pub use launch::LaunchCommand;
+pub use match_rule::MatchRule;
+pub use probe::{ProbeResult, ProbeStatus, ProbeTarget};
+pub use session_failure::{Remedy, SessionDiagnosis, SshFailure};
+pub use settings::{AuditSettings, ProbeSettings, Settings};
+pub use ssh_config::{ConfigBlock, ConfigLine, Directive, SshConfig};
 pub use ssh_host::SshHost;
+pub use ssh_key::{KeyFile, KeyGenRequest, KeyUsage, PublicKey};
 pub use theme::{Rgb, Theme, ThemePreference};
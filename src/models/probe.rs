@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+/// A TCP endpoint to check, named by the host alias it belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeTarget {
+    pub alias: String,
+    pub host: String,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProbeStatus {
+    Pending,
+    Reachable(Duration),
+    Unreachable(String),
+    /// Not probed because ssh reaches the host through a proxy.
+    Skipped(String),
+}
+
+impl ProbeStatus {
+    /// The latency column of the host list.
+    pub fn short_display(&self) -> String {
+        match self {
+            Self::Pending => "…".into(),
+            Self::Reachable(latency) if latency.as_millis() == 0 => "<1ms".into(),
+            Self::Reachable(latency) => format!("{}ms", latency.as_millis()),
+            Self::Unreachable(_) => "down".into(),
+            Self::Skipped(_) => "proxy".into(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Pending => "checking…".into(),
+            Self::Reachable(latency) if latency.as_millis() == 0 => "reachable in under 1 ms".into(),
+            Self::Reachable(latency) => format!("reachable in {} ms", latency.as_millis()),
+            Self::Unreachable(reason) => format!("unreachable: {}", reason),
+            Self::Skipped(reason) => format!("not checked, connects via {}", reason),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeResult {
+    pub alias: String,
+    pub status: ProbeStatus,
+    /// The server's SSH identification line, when one was sent.
+    pub banner: Option<String>,
+}
@@ -0,0 +1,279 @@
+use std::path::PathBuf;
+
+/// How many trailing stderr lines the diagnosis popup quotes.
+const EVIDENCE_LINES: usize = 6;
+
+/// A way an ssh run fails that lazyssh recognises from ssh's stderr.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SshFailure {
+    /// `REMOTE HOST IDENTIFICATION HAS CHANGED`, with the `Offending ... key
+    /// in file:line` locations ssh names.
+    HostKeyChanged { offending: Vec<(PathBuf, usize)> },
+    /// `Permission denied (publickey...)`.
+    PermissionDenied,
+    /// The agent offered more keys than the server's `MaxAuthTries`.
+    TooManyAuthFailures,
+    NoRoute,
+    ConnectionRefused,
+    /// Nothing answered before the connect timeout.
+    TimedOut,
+    /// `Could not resolve hostname`.
+    UnknownHost,
+}
+
+/// A one-key fix offered for a failure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Remedy {
+    RemoveKnownHost,
+    IdentitiesOnly,
+    EditHost,
+    Probe,
+}
+
+impl Remedy {
+    pub fn key(&self) -> char {
+        match self {
+            Self::RemoveKnownHost => 'r',
+            Self::IdentitiesOnly => 'i',
+            Self::EditHost => 'e',
+            Self::Probe => 'p',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::RemoveKnownHost => "remove the stale known_hosts key",
+            Self::IdentitiesOnly => "add IdentitiesOnly yes to the host",
+            Self::EditHost => "edit the host",
+            Self::Probe => "check reachability again",
+        }
+    }
+}
+
+impl SshFailure {
+    /// The first recognised failure in `stderr`. The host key warning
+    /// comes first since ssh may print other errors after it.
+    pub fn detect(stderr: &str) -> Option<Self> {
+        let text = stderr.to_lowercase();
+        if text.contains("remote host identification has changed") || text.contains("host key verification failed") {
+            let offending = stderr.lines().filter_map(offending_location).collect();
+            return Some(Self::HostKeyChanged { offending });
+        }
+
+        let signatures = [
+            ("too many authentication failures", Self::TooManyAuthFailures),
+            ("permission denied (", Self::PermissionDenied),
+            ("could not resolve hostname", Self::UnknownHost),
+            ("no route to host", Self::NoRoute),
+            ("connection refused", Self::ConnectionRefused),
+            // Linux says "Connection timed out", macOS "Operation timed out".
+            ("timed out", Self::TimedOut),
+        ];
+        signatures
+            .into_iter()
+            .find(|(signature, _)| text.contains(signature))
+            .map(|(_, failure)| failure)
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::HostKeyChanged { .. } => "Host key changed",
+            Self::PermissionDenied => "Permission denied",
+            Self::TooManyAuthFailures => "Too many authentication failures",
+            Self::NoRoute => "No route to host",
+            Self::ConnectionRefused => "Connection refused",
+            Self::TimedOut => "Connection timed out",
+            Self::UnknownHost => "Unknown host name",
+        }
+    }
+
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            Self::HostKeyChanged { .. } => {
+                "The server presented a different key than the one in known_hosts. Expected after a \
+                 rebuild or reinstall; otherwise someone may be intercepting the connection."
+            }
+            Self::PermissionDenied => {
+                "The server accepted none of the offered keys or passwords. Check User and \
+                 IdentityFile, and that the public key is in the server's authorized_keys."
+            }
+            Self::TooManyAuthFailures => {
+                "ssh tried every key in the agent and the server gave up before the right one. \
+                 IdentitiesOnly makes ssh offer only the host's IdentityFile."
+            }
+            Self::NoRoute => "The network has no path to the address. Check HostName, VPN and routing.",
+            Self::ConnectionRefused => {
+                "The host answered but nothing listens on the port. Check Port and that sshd runs."
+            }
+            Self::TimedOut => {
+                "Nothing answered in time. Check HostName and Port, and for a firewall or a missing VPN."
+            }
+            Self::UnknownHost => "DNS could not resolve HostName. Check for a typo or a missing VPN.",
+        }
+    }
+
+    pub fn remedies(&self) -> &'static [Remedy] {
+        match self {
+            Self::HostKeyChanged { .. } => &[Remedy::RemoveKnownHost],
+            Self::PermissionDenied => &[Remedy::EditHost, Remedy::IdentitiesOnly],
+            Self::TooManyAuthFailures => &[Remedy::IdentitiesOnly, Remedy::EditHost],
+            Self::NoRoute | Self::ConnectionRefused | Self::TimedOut => &[Remedy::EditHost, Remedy::Probe],
+            Self::UnknownHost => &[Remedy::EditHost],
+        }
+    }
+}
+
+/// Parses `Offending ECDSA key in /home/me/.ssh/known_hosts:12`.
+fn offending_location(line: &str) -> Option<(PathBuf, usize)> {
+    let rest = line.trim().strip_prefix("Offending ")?;
+    let (_, location) = rest.rsplit_once(" in ")?;
+    let (path, number) = location.trim().rsplit_once(':')?;
+    Some((PathBuf::from(path), number.parse().ok()?))
+}
+
+/// A failed session, shown when control returns to the TUI.
+#[derive(Debug, Clone)]
+pub struct SessionDiagnosis {
+    pub alias: String,
+    pub failure: SshFailure,
+    /// The last lines ssh printed, without the `@@@` warning frame.
+    pub evidence: Vec<String>,
+}
+
+impl SessionDiagnosis {
+    /// Only ssh's own errors (exit status 255) are diagnosed; any other
+    /// status belongs to the remote command.
+    pub fn from_session(alias: &str, exit_code: Option<i32>, stderr: &str) -> Option<Self> {
+        if exit_code != Some(255) {
+            return None;
+        }
+        let failure = SshFailure::detect(stderr)?;
+
+        let lines: Vec<&str> = stderr
+            .lines()
+            .map(str::trim_end)
+            .filter(|l| !l.is_empty() && !l.starts_with("@@@"))
+            .collect();
+        let evidence = lines[lines.len().saturating_sub(EVIDENCE_LINES)..]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+
+        Some(Self {
+            alias: alias.to_string(),
+            failure,
+            evidence,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// OpenSSH 9.x output for a server whose host key was replaced.
+    const HOST_KEY_CHANGED: &str = "\
+@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+@    WARNING: REMOTE HOST IDENTIFICATION HAS CHANGED!     @
+@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@@
+IT IS POSSIBLE THAT SOMEONE IS DOING SOMETHING NASTY!
+Someone could be eavesdropping on you right now (man-in-the-middle attack)!
+It is also possible that a host key has just been changed.
+The fingerprint for the ED25519 key sent by the remote host is
+SHA256:QyJamjBujz9xI3I7hQtYdZ3Z9Mv8mDdBawsCd/ZUuqI.
+Please contact your system administrator.
+Add correct host key in /home/alice/.ssh/known_hosts to get rid of this message.
+Offending ECDSA key in /home/alice/.ssh/known_hosts:12
+  remove with:
+  ssh-keygen -f '/home/alice/.ssh/known_hosts' -R 'web.example.com'
+Host key for web.example.com has changed and you have requested strict checking.
+Host key verification failed.
+";
+
+    #[test]
+    fn host_key_change_names_the_offending_lines() {
+        let failure = SshFailure::detect(HOST_KEY_CHANGED).unwrap();
+        assert_eq!(
+            failure,
+            SshFailure::HostKeyChanged {
+                offending: vec![(PathBuf::from("/home/alice/.ssh/known_hosts"), 12)]
+            }
+        );
+        assert_eq!(failure.remedies(), &[Remedy::RemoveKnownHost]);
+
+        let diagnosis = SessionDiagnosis::from_session("web", Some(255), HOST_KEY_CHANGED).unwrap();
+        assert_eq!(diagnosis.evidence.len(), EVIDENCE_LINES);
+        assert_eq!(diagnosis.evidence.last().unwrap(), "Host key verification failed.");
+        assert!(diagnosis.evidence.iter().all(|l| !l.starts_with("@@@")));
+    }
+
+    #[test]
+    fn parses_offending_locations() {
+        let ip_warning = "\
+Warning: the ECDSA host key for 'web.example.com' differs from the key for the IP address '10.0.0.5'
+Offending key for IP in /etc/ssh/ssh_known_hosts:3
+Matching host key in /home/alice/.ssh/known_hosts:40
+Offending RSA key in /home/al ice/.ssh/known_hosts:7
+Host key verification failed.
+";
+        assert_eq!(
+            SshFailure::detect(ip_warning),
+            Some(SshFailure::HostKeyChanged {
+                offending: vec![
+                    (PathBuf::from("/etc/ssh/ssh_known_hosts"), 3),
+                    (PathBuf::from("/home/al ice/.ssh/known_hosts"), 7),
+                ]
+            })
+        );
+        assert_eq!(offending_location("Offending ECDSA key in known_hosts"), None);
+        assert_eq!(offending_location("Offending ECDSA key in /x:twelve"), None);
+        assert_eq!(offending_location("Matching host key in /x:1"), None);
+    }
+
+    #[test]
+    fn recognises_authentication_failures() {
+        assert_eq!(
+            SshFailure::detect("alice@web.example.com: Permission denied (publickey,password).\n"),
+            Some(SshFailure::PermissionDenied)
+        );
+        assert_eq!(
+            SshFailure::detect(
+                "Received disconnect from 10.0.0.5 port 22:2: Too many authentication failures\n\
+                 Disconnected from 10.0.0.5 port 22\n"
+            ),
+            Some(SshFailure::TooManyAuthFailures)
+        );
+    }
+
+    #[test]
+    fn recognises_connection_failures() {
+        let cases = [
+            ("ssh: connect to host web.example.com port 2222: Connection refused\n", SshFailure::ConnectionRefused),
+            ("ssh: connect to host 10.0.0.5 port 22: Connection timed out\n", SshFailure::TimedOut),
+            ("ssh: connect to host 10.0.0.5 port 22: Operation timed out\n", SshFailure::TimedOut),
+            ("ssh: connect to host 10.9.9.9 port 22: No route to host\n", SshFailure::NoRoute),
+            (
+                "ssh: Could not resolve hostname web.exmaple.com: Name or service not known\n",
+                SshFailure::UnknownHost,
+            ),
+        ];
+        for (stderr, expected) in cases {
+            let diagnosis = SessionDiagnosis::from_session("web", Some(255), stderr).unwrap();
+            assert_eq!(diagnosis.failure, expected);
+            assert_eq!(diagnosis.evidence, vec![stderr.trim_end().to_string()]);
+        }
+    }
+
+    #[test]
+    fn unrecognised_or_remote_failures_are_not_diagnosed() {
+        let reset = "kex_exchange_identification: read: Connection reset by peer\n\
+                     Connection reset by 10.0.0.5 port 22\n";
+        assert_eq!(SshFailure::detect(reset), None);
+        assert!(SessionDiagnosis::from_session("web", Some(255), reset).is_none());
+
+        // The remote command failed; its stderr is not ssh's.
+        let remote = "cat: /nope: Permission denied (os error 13)\n";
+        assert!(SessionDiagnosis::from_session("web", Some(1), remote).is_none());
+        assert!(SessionDiagnosis::from_session("web", None, HOST_KEY_CHANGED).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{BackupEntry, LaunchCommand, SortMode, SshHost};
+
+/// Contents of `~/.config/ssh-manager/settings.json`. Missing keys fall
+/// back to their defaults so older files keep working.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub backups: BackupRetention,
+    pub audit: AuditSettings,
+    pub launch: LaunchSettings,
+    /// Initial order of the host list; `s` switches it in the TUI.
+    pub sort: SortMode,
+    pub probe: ProbeSettings,
+}
+
+/// Background TCP checks of each host's `HostName:Port`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProbeSettings {
+    /// Probe every host when the TUI starts. Off by default, since it opens a
+    /// connection to every host in the config; `p` probes on demand.
+    pub on_start: bool,
+    /// Connects in flight at once.
+    pub concurrency: usize,
+    pub timeout_ms: u64,
+}
+
+impl Default for ProbeSettings {
+    fn default() -> Self {
+        Self {
+            on_start: false,
+            concurrency: 8,
+            timeout_ms: 2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditSettings {
+    /// Host patterns that may receive `ForwardAgent yes` without a finding.
+    pub forward_agent_allowlist: Vec<String>,
+}
+
+/// How connections are started. Nothing is added to the ssh command line
+/// unless configured here, so the ssh config stays in charge.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchSettings {
+    /// Program to run: a name looked up on `PATH` or a full path.
+    pub ssh_binary: String,
+    /// Arguments placed before the host alias.
+    pub extra_args: Vec<String>,
+    /// Per-host overrides keyed by host alias.
+    pub hosts: BTreeMap<String, HostLaunch>,
+}
+
+impl Default for LaunchSettings {
+    fn default() -> Self {
+        Self {
+            ssh_binary: "ssh".into(),
+            extra_args: Vec::new(),
+            hosts: BTreeMap::new(),
+        }
+    }
+}
+
+/// Launch settings for one host. Each field that is set replaces the
+/// default rather than adding to it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostLaunch {
+    pub ssh_binary: Option<String>,
+    pub extra_args: Option<Vec<String>>,
+}
+
+impl LaunchSettings {
+    /// The override for any of the host's concrete names.
+    pub fn host_override(&self, host: &SshHost) -> Option<&HostLaunch> {
+        host.concrete_names().find_map(|name| {
+            self.hosts
+                .iter()
+                .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+                .map(|(_, launch)| launch)
+        })
+    }
+
+    pub fn command_for(&self, host: &SshHost) -> LaunchCommand {
+        let custom = self.host_override(host);
+        let program = custom
+            .and_then(|c| c.ssh_binary.clone())
+            .unwrap_or_else(|| self.ssh_binary.clone());
+        let mut args = custom
+            .and_then(|c| c.extra_args.clone())
+            .unwrap_or_else(|| self.extra_args.clone());
+        args.extend(host.as_ssh_args());
+
+        LaunchCommand {
+            alias: host.alias().to_string(),
+            program,
+            args,
+        }
+    }
+}
+
+/// Which `*.backup_*` files to keep. Both limits apply when set; with
+/// neither set (the default) no backup is ever deleted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupRetention {
+    /// Newest backups to keep per config file.
+    pub keep_last: Option<usize>,
+    /// Backups older than this many days are removed.
+    pub max_age_days: Option<i64>,
+}
+
+impl BackupRetention {
+    pub fn expired<'a>(&self, backups: &'a [BackupEntry], now: NaiveDateTime) -> Vec<&'a BackupEntry> {
+        let mut sorted: Vec<&BackupEntry> = backups.iter().collect();
+        sorted.sort_by_key(|b| std::cmp::Reverse(b.created));
+
+        let mut seen: Vec<(&str, usize)> = Vec::new();
+        sorted
+            .into_iter()
+            .filter(|backup| {
+                let rank = match seen.iter_mut().find(|(name, _)| *name == backup.original_name) {
+                    Some((_, count)) => {
+                        *count += 1;
+                        *count
+                    }
+                    None => {
+                        seen.push((&backup.original_name, 1));
+                        1
+                    }
+                };
+
+                let too_many = self.keep_last.is_some_and(|n| rank > n);
+                let too_old = self
+                    .max_age_days
+                    .is_some_and(|days| now - backup.created > Duration::days(days));
+                too_many || too_old
+            })
+            .collect()
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.keep_last, self.max_age_days) {
+            (Some(n), Some(d)) => format!("keep last {} per file, none older than {} days", n, d),
+            (Some(n), None) => format!("keep last {} per file", n),
+            (None, Some(d)) => format!("keep backups newer than {} days", d),
+            (None, None) => "keep everything".into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn backup(original_name: &str, days_old: i64, now: NaiveDateTime) -> BackupEntry {
+        BackupEntry {
+            path: PathBuf::from(format!("{}-{}", original_name, days_old)),
+            original_name: original_name.into(),
+            source: PathBuf::from(original_name),
+            created: now - Duration::days(days_old),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn probes_only_start_on_request_by_default() {
+        assert!(!Settings::default().probe.on_start);
+        let settings: Settings = serde_json::from_str(r#"{"probe": {"timeout_ms": 500}}"#).unwrap();
+        assert!(!settings.probe.on_start);
+        assert_eq!(settings.probe.concurrency, 8);
+        let settings: Settings = serde_json::from_str(r#"{"probe": {"on_start": true}}"#).unwrap();
+        assert!(settings.probe.on_start);
+    }
+
+    #[test]
+    fn default_retention_deletes_nothing() {
+        let now = chrono::Local::now().naive_local();
+        let backups: Vec<BackupEntry> = (0..100).map(|d| backup("config", d * 30, now)).collect();
+        assert!(BackupRetention::default().expired(&backups, now).is_empty());
+    }
+
+    #[test]
+    fn keep_last_counts_each_file_separately() {
+        let now = chrono::Local::now().naive_local();
+        let backups = vec![
+            backup("config", 1, now),
+            backup("config", 2, now),
+            backup("config.d/work", 3, now),
+            backup("config", 4, now),
+        ];
+        let retention = BackupRetention { keep_last: Some(2), max_age_days: None };
+
+        let expired: Vec<&PathBuf> = retention.expired(&backups, now).into_iter().map(|b| &b.path).collect();
+        assert_eq!(expired, [&PathBuf::from("config-4")]);
+    }
+}
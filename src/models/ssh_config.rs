@@ -0,0 +1,690 @@
+use crate::models::ssh_host::is_repeatable;
+use crate::models::{Diagnostic, MatchRule, Severity, SshHost};
+
+const DEFAULT_INDENT: &str = "    ";
+const DEFAULT_SEPARATOR: &str = " ";
+
+/// One physical line of an ssh_config file, kept exactly as it was read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigLine {
+    Blank(String),
+    Comment(String),
+    Directive(Directive),
+}
+
+impl ConfigLine {
+    pub fn as_directive(&self) -> Option<&Directive> {
+        match self {
+            Self::Directive(d) => Some(d),
+            _ => None,
+        }
+    }
+
+    fn is_blank(&self) -> bool {
+        matches!(self, Self::Blank(_))
+    }
+
+    fn to_text(&self) -> String {
+        match self {
+            Self::Blank(raw) | Self::Comment(raw) => raw.clone(),
+            Self::Directive(d) => d.to_text(),
+        }
+    }
+
+    /// The end of the line, where a `\r` from a CRLF file is kept.
+    fn end_mut(&mut self) -> &mut String {
+        match self {
+            Self::Blank(raw) | Self::Comment(raw) => raw,
+            Self::Directive(d) => &mut d.trailing,
+        }
+    }
+}
+
+/// A `Keyword value` line split into the pieces needed to rewrite the value
+/// without touching its indentation, separator style or inline comment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub indent: String,
+    pub keyword: String,
+    pub separator: String,
+    pub value: String,
+    pub trailing: String,
+}
+
+impl Directive {
+    pub fn new(indent: &str, keyword: &str, separator: &str, value: &str) -> Self {
+        Self {
+            indent: indent.to_string(),
+            keyword: keyword.to_string(),
+            separator: separator.to_string(),
+            value: value.to_string(),
+            trailing: String::new(),
+        }
+    }
+
+    pub fn is(&self, keyword: &str) -> bool {
+        self.keyword.eq_ignore_ascii_case(keyword)
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "{}{}{}{}{}",
+            self.indent, self.keyword, self.separator, self.value, self.trailing
+        )
+    }
+}
+
+/// A `Host` or `Match` line together with everything up to the next block.
+///
+/// `leading` holds comments written directly above the header line so they
+/// travel with the block when it is deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigBlock {
+    pub leading: Vec<ConfigLine>,
+    pub header: Directive,
+    pub body: Vec<ConfigLine>,
+}
+
+impl ConfigBlock {
+    /// `line_end` is `"\r"` for a CRLF file; the block's other lines follow
+    /// its header.
+    fn from_host(host: &SshHost, line_end: &str) -> Self {
+        let mut header = Directive::new("", "Host", DEFAULT_SEPARATOR, &host.patterns_text());
+        header.trailing = line_end.to_string();
+        let mut block = Self {
+            leading: Vec::new(),
+            header,
+            body: Vec::new(),
+        };
+
+        let mut fields: Vec<(&str, String)> = vec![
+            ("HostName", host.hostname.clone()),
+            ("User", host.user.clone()),
+        ];
+        if host.has_custom_port() {
+            fields.push(("Port", host.port.to_string()));
+        }
+
+        for (keyword, value) in fields {
+            if !value.is_empty() {
+                block.insert_directive(keyword, &value);
+            }
+        }
+        for (keyword, values) in host.lists() {
+            for value in values {
+                block.insert_directive(keyword, value);
+            }
+        }
+        for (keyword, value) in &host.extra_options {
+            block.insert_directive(keyword, value);
+        }
+
+        block
+    }
+
+    pub fn is_match(&self) -> bool {
+        self.header.is("match")
+    }
+
+    fn options(&self) -> Vec<(String, String)> {
+        self.directives()
+            .map(|d| (d.keyword.clone(), d.value.clone()))
+            .collect()
+    }
+
+    fn line_count(&self) -> usize {
+        self.leading.len() + 1 + self.body.len()
+    }
+
+    /// Like ssh, the first `HostName`, `Port` and `User` in a block wins.
+    /// An unparsable `Port` is kept in `invalid_port` instead of being
+    /// replaced, so saving the host leaves that line as written.
+    pub fn to_host(&self, index: usize) -> SshHost {
+        let mut host = SshHost {
+            patterns: split_arguments(&self.header.value),
+            block_index: Some(index),
+            ..SshHost::empty()
+        };
+        let mut seen: Vec<String> = Vec::new();
+
+        for directive in self.directives() {
+            let value = directive.value.clone();
+            let keyword = directive.keyword.to_lowercase();
+            if is_single_value(&keyword) {
+                if seen.contains(&keyword) {
+                    continue;
+                }
+                seen.push(keyword.clone());
+            }
+
+            match keyword.as_str() {
+                "hostname" => host.hostname = value,
+                "port" => match parse_port(&value) {
+                    Some(port) => host.port = port,
+                    None => host.invalid_port = Some(value),
+                },
+                "user" => host.user = value,
+                keyword => match host.list_mut(keyword) {
+                    Some(list) => list.push(value),
+                    None => host.extra_options.push((directive.keyword.clone(), value)),
+                },
+            }
+        }
+
+        host
+    }
+
+    fn directives(&self) -> impl Iterator<Item = &Directive> {
+        self.body
+            .iter()
+            .filter_map(ConfigLine::as_directive)
+            .filter(|d| !d.value.is_empty())
+    }
+
+    /// Rewrites only the lines whose values differ between the block and `host`.
+    fn apply(&mut self, host: &SshHost) {
+        let current = self.to_host(0);
+
+        if current.patterns != host.patterns {
+            self.header.value = host.patterns_text();
+        }
+        if current.hostname != host.hostname {
+            self.set_option("HostName", &host.hostname);
+        }
+        if current.user != host.user {
+            self.set_option("User", &host.user);
+        }
+        if current.port != host.port || current.invalid_port != host.invalid_port {
+            let port = if host.has_custom_port() { host.port.to_string() } else { String::new() };
+            self.set_option("Port", &port);
+        }
+        for ((keyword, old), (_, new)) in current.lists().into_iter().zip(host.lists()) {
+            if old != new {
+                self.set_list(keyword, new);
+            }
+        }
+        if current.extra_options != host.extra_options {
+            self.set_extra_options(&host.extra_options);
+        }
+    }
+
+    fn position_of(&self, keyword: &str) -> Option<usize> {
+        self.body.iter().position(|line| {
+            line.as_directive()
+                .is_some_and(|d| d.is(keyword) && !d.value.is_empty())
+        })
+    }
+
+    fn set_option(&mut self, keyword: &str, value: &str) {
+        match (self.position_of(keyword), value.is_empty()) {
+            (Some(i), true) => {
+                self.body.remove(i);
+            }
+            (Some(i), false) => {
+                if let ConfigLine::Directive(d) = &mut self.body[i] {
+                    d.value = value.to_string();
+                }
+            }
+            (None, true) => {}
+            (None, false) => self.insert_directive(keyword, value),
+        }
+    }
+
+    /// Updates repeatable lines so that only the entries that changed touch
+    /// the file; see `sync_lines`.
+    fn set_list(&mut self, keyword: &str, values: &[String]) {
+        let positions = self.positions_where(|d| d.is(keyword));
+        let wanted: Vec<(String, String)> = values
+            .iter()
+            .map(|value| (keyword.to_string(), value.clone()))
+            .collect();
+        self.sync_lines(&positions, &wanted);
+    }
+
+    fn set_extra_options(&mut self, options: &[(String, String)]) {
+        let positions = self.positions_where(|d| !is_core_keyword(&d.keyword));
+        self.sync_lines(&positions, options);
+    }
+
+    /// Body indices of the non-empty directives accepted by `filter`.
+    fn positions_where(&self, filter: impl Fn(&Directive) -> bool) -> Vec<usize> {
+        self.body
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                line.as_directive()
+                    .is_some_and(|d| !d.value.is_empty() && filter(d))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Turns the directives at `positions` into `wanted`. Lines whose entry
+    /// is still wanted are left alone, a changed entry rewrites its own
+    /// line, a removed entry drops only its line, and new entries go next to
+    /// their neighbours, so comments and unrelated lines stay where they are.
+    fn sync_lines(&mut self, positions: &[usize], wanted: &[(String, String)]) {
+        let current: Vec<(String, String)> = positions
+            .iter()
+            .filter_map(|&i| self.body[i].as_directive())
+            .map(|d| (d.keyword.to_lowercase(), d.value.clone()))
+            .collect();
+        let wanted_keys: Vec<(String, String)> = wanted
+            .iter()
+            .map(|(keyword, value)| (keyword.to_lowercase(), value.clone()))
+            .collect();
+
+        let mut insert_at = positions.first().copied().unwrap_or_else(|| self.append_position());
+        let mut shift = 0isize;
+        for step in align(&current, &wanted_keys) {
+            match step {
+                (Some(old), Some(new)) => {
+                    let i = positions[old].saturating_add_signed(shift);
+                    let (keyword, value) = &wanted[new];
+                    if let ConfigLine::Directive(d) = &mut self.body[i] {
+                        if !d.is(keyword) {
+                            d.keyword = keyword.clone();
+                        }
+                        if d.value != *value {
+                            d.value = value.clone();
+                        }
+                    }
+                    insert_at = i + 1;
+                }
+                (Some(old), None) => {
+                    let i = positions[old].saturating_add_signed(shift);
+                    self.body.remove(i);
+                    shift -= 1;
+                    insert_at = i;
+                }
+                (None, Some(new)) => {
+                    let (keyword, value) = &wanted[new];
+                    let line = self.new_directive(keyword, value);
+                    self.body.insert(insert_at, line);
+                    shift += 1;
+                    insert_at += 1;
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    /// Appends a directive after the last non-blank line.
+    fn insert_directive(&mut self, keyword: &str, value: &str) {
+        let at = self.append_position();
+        let line = self.new_directive(keyword, value);
+        self.body.insert(at, line);
+    }
+
+    fn append_position(&self) -> usize {
+        self.body
+            .iter()
+            .rposition(|line| !line.is_blank())
+            .map_or(0, |i| i + 1)
+    }
+
+    /// A new line that copies the indentation, separator style and line
+    /// ending already used in the block.
+    fn new_directive(&self, keyword: &str, value: &str) -> ConfigLine {
+        let template = self.body.iter().find_map(ConfigLine::as_directive);
+        let indent = template.map_or(DEFAULT_INDENT, |d| d.indent.as_str());
+        let separator = template.map_or(DEFAULT_SEPARATOR, |d| d.separator.as_str());
+        let mut directive = Directive::new(indent, keyword, separator, value);
+        if self.ends_lines_with_cr() {
+            directive.trailing.push('\r');
+        }
+        ConfigLine::Directive(directive)
+    }
+
+    fn ends_lines_with_cr(&self) -> bool {
+        self.header.trailing.ends_with('\r') || self.body.iter().any(|line| line.to_text().ends_with('\r'))
+    }
+
+    fn collect_lines(&self, lines: &mut Vec<String>) {
+        lines.extend(self.leading.iter().map(ConfigLine::to_text));
+        lines.push(self.header.to_text());
+        lines.extend(self.body.iter().map(ConfigLine::to_text));
+    }
+}
+
+/// Matches `old` entries to `new` ones in order: entries on the longest
+/// common subsequence pair up with themselves, and the entries between two
+/// such pairs pair up one to one, the rest becoming removals `(Some, None)`
+/// or insertions `(None, Some)`.
+fn align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(Option<usize>, Option<usize>)> {
+    // common[i][j]: length of the longest common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut steps = Vec::new();
+    let (mut gap_old, mut gap_new) = (Vec::new(), Vec::new());
+    let flush = |steps: &mut Vec<_>, gap_old: &mut Vec<usize>, gap_new: &mut Vec<usize>| {
+        let paired = gap_old.len().min(gap_new.len());
+        steps.extend((0..paired).map(|k| (Some(gap_old[k]), Some(gap_new[k]))));
+        steps.extend(gap_old.drain(..).skip(paired).map(|o| (Some(o), None)));
+        steps.extend(gap_new.drain(..).skip(paired).map(|n| (None, Some(n))));
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] && common[i][j] == common[i + 1][j + 1] + 1 {
+            flush(&mut steps, &mut gap_old, &mut gap_new);
+            steps.push((Some(i), Some(j)));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || common[i][j + 1] >= common[i + 1][j]) {
+            gap_new.push(j);
+            j += 1;
+        } else {
+            gap_old.push(i);
+            i += 1;
+        }
+    }
+    flush(&mut steps, &mut gap_old, &mut gap_new);
+    steps
+}
+
+/// Splits a directive value into whitespace separated arguments, keeping
+/// double-quoted arguments together and dropping the quotes.
+pub fn split_arguments(value: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quote = false;
+
+    for c in value.chars() {
+        match c {
+            '"' => in_quote = !in_quote,
+            c if c.is_whitespace() && !in_quote => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+
+    args
+}
+
+fn is_single_value(keyword: &str) -> bool {
+    ["hostname", "port", "user"]
+        .iter()
+        .any(|k| keyword.eq_ignore_ascii_case(k))
+}
+
+fn is_core_keyword(keyword: &str) -> bool {
+    is_single_value(keyword) || is_repeatable(keyword)
+}
+
+fn parse_port(value: &str) -> Option<u16> {
+    value.parse().ok().filter(|&port| port > 0)
+}
+
+/// Problems ssh would report (or silently ignore) for a single line.
+fn check_directive(directive: &Directive) -> Option<(Severity, String)> {
+    let keyword = directive.keyword.as_str();
+    let value = directive.value.as_str();
+
+    if value.is_empty() {
+        let message = if directive.is("host") {
+            "Host line has no patterns".to_string()
+        } else if directive.is("match") {
+            "Match line has no criteria".to_string()
+        } else {
+            format!("{} has no value and is ignored", keyword)
+        };
+        return Some((Severity::Warning, message));
+    }
+
+    if value.matches('"').count() % 2 == 1 {
+        return Some((Severity::Error, format!("unterminated quote in {} value", keyword)));
+    }
+
+    if directive.is("port") && parse_port(value).is_none() {
+        return Some((
+            Severity::Error,
+            format!("Port '{}' is not a number between 1 and 65535", value),
+        ));
+    }
+
+    None
+}
+
+/// Concrete syntax tree of an ssh_config file.
+///
+/// Every line is kept verbatim so rendering an unmodified config reproduces
+/// the original text byte for byte; edits only replace the affected lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SshConfig {
+    pub preamble: Vec<ConfigLine>,
+    pub blocks: Vec<ConfigBlock>,
+    pub trailing_newline: bool,
+    /// Lines end in `\r\n`; lines added by edits end the same way.
+    pub crlf: bool,
+}
+
+impl SshConfig {
+    pub fn hosts(&self) -> Vec<SshHost> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| !block.is_match())
+            .map(|(i, block)| block.to_host(i))
+            .filter(|h| !h.patterns.is_empty())
+            .collect()
+    }
+
+    pub fn match_rules(&self) -> Vec<MatchRule> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.is_match())
+            .map(|(i, block)| MatchRule {
+                criteria: MatchRule::parse_criteria(&block.header.value),
+                options: block.options(),
+                file_index: 0,
+                line: self.header_line(i),
+            })
+            .collect()
+    }
+
+    /// 1-based line number of a block's `Host`/`Match` line.
+    pub fn header_line(&self, index: usize) -> usize {
+        let before: usize = self.blocks[..index].iter().map(ConfigBlock::line_count).sum();
+        self.preamble.len() + before + self.blocks[index].leading.len() + 1
+    }
+
+    /// 1-based first and last line of a block, header included.
+    pub fn block_lines(&self, index: usize) -> (usize, usize) {
+        let header = self.header_line(index);
+        (header, header + self.blocks[index].body.len())
+    }
+
+    /// Every directive in file order with its 1-based line number,
+    /// `Host`/`Match` headers included.
+    pub fn numbered_directives(&self) -> Vec<(usize, &Directive)> {
+        self.located_directives()
+            .into_iter()
+            .map(|(line, _, directive)| (line, directive))
+            .collect()
+    }
+
+    /// Like `numbered_directives`, with the index of the block each line
+    /// belongs to (`None` for the preamble).
+    pub fn located_directives(&self) -> Vec<(usize, Option<usize>, &Directive)> {
+        let mut out = Vec::new();
+        let mut line = 0;
+
+        for entry in &self.preamble {
+            line += 1;
+            if let Some(d) = entry.as_directive() {
+                out.push((line, None, d));
+            }
+        }
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            line += block.leading.len() + 1;
+            out.push((line, Some(index), &block.header));
+            for entry in &block.body {
+                line += 1;
+                if let Some(d) = entry.as_directive() {
+                    out.push((line, Some(index), d));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Line-level problems: missing values, bad port numbers, unbalanced
+    /// quotes, and single-value keywords repeated within a block.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut out = Vec::new();
+        let mut line = 0;
+
+        for entry in &self.preamble {
+            line += 1;
+            if let Some((severity, message)) = entry.as_directive().and_then(check_directive) {
+                out.push(Diagnostic::new(line, severity, message));
+            }
+        }
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            line += block.leading.len() + 1;
+            let mut found = Vec::new();
+            if let Some((severity, message)) = check_directive(&block.header) {
+                found.push(Diagnostic::new(line, severity, message));
+            }
+
+            let mut first_seen: Vec<(String, usize)> = Vec::new();
+            for entry in &block.body {
+                line += 1;
+                let Some(directive) = entry.as_directive() else { continue };
+                if let Some((severity, message)) = check_directive(directive) {
+                    found.push(Diagnostic::new(line, severity, message));
+                }
+                if directive.value.is_empty() || !is_single_value(&directive.keyword) {
+                    continue;
+                }
+
+                let keyword = directive.keyword.to_lowercase();
+                match first_seen.iter().find(|(k, _)| *k == keyword) {
+                    Some((_, first)) => found.push(Diagnostic::new(
+                        line,
+                        Severity::Warning,
+                        format!(
+                            "{} is set again; ssh uses the value from line {}",
+                            directive.keyword, first
+                        ),
+                    )),
+                    None => first_seen.push((keyword, line)),
+                }
+            }
+
+            for mut diagnostic in found {
+                diagnostic.block_index = Some(index);
+                out.push(diagnostic);
+            }
+        }
+
+        out
+    }
+
+    /// `Include` lines with their line number and the patterns they name.
+    pub fn include_directives(&self) -> Vec<(usize, Vec<String>)> {
+        self.numbered_directives()
+            .into_iter()
+            .filter(|(_, d)| d.is("include"))
+            .map(|(line, d)| (line, split_arguments(&d.value)))
+            .collect()
+    }
+
+    /// ssh takes the first value it finds for each option, so a concrete
+    /// host goes above the first `Match` or pattern-only block (typically
+    /// `Host *` at the end); appended after it, the defaults would win.
+    /// Pattern-only blocks are still appended, below the existing ones.
+    pub fn add_host(&mut self, host: &SshHost) {
+        let line_end = if self.crlf { "\r" } else { "" };
+        let mut block = ConfigBlock::from_host(host, line_end);
+
+        let position = if host.is_pattern() {
+            self.blocks.len()
+        } else {
+            self.blocks
+                .iter()
+                .enumerate()
+                .position(|(i, b)| b.is_match() || b.to_host(i).is_pattern())
+                .unwrap_or(self.blocks.len())
+        };
+
+        let above = match position.checked_sub(1) {
+            Some(i) => &self.blocks[i].body,
+            None => &self.preamble,
+        };
+        let needs_gap = match position {
+            0 => above.last().is_some_and(|line| !line.is_blank()),
+            _ => above.last().is_none_or(|line| !line.is_blank()),
+        };
+        if needs_gap {
+            block.leading.push(ConfigLine::Blank(line_end.to_string()));
+        }
+        if position < self.blocks.len() {
+            block.body.push(ConfigLine::Blank(line_end.to_string()));
+        }
+
+        if !self.trailing_newline {
+            if let Some(last) = self.last_line_mut() {
+                last.push_str(line_end);
+            }
+        }
+        self.blocks.insert(position, block);
+        self.trailing_newline = true;
+    }
+
+    pub fn update_host(&mut self, index: usize, host: &SshHost) {
+        if let Some(block) = self.blocks.get_mut(index) {
+            block.apply(host);
+        }
+    }
+
+    pub fn remove_host(&mut self, index: usize) {
+        if index < self.blocks.len() {
+            self.blocks.remove(index);
+        }
+    }
+
+    /// The end of the file's last line, or `None` for an empty file.
+    fn last_line_mut(&mut self) -> Option<&mut String> {
+        match self.blocks.last_mut() {
+            Some(block) => Some(match block.body.last_mut() {
+                Some(line) => line.end_mut(),
+                None => &mut block.header.trailing,
+            }),
+            None => self.preamble.last_mut().map(ConfigLine::end_mut),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines: Vec<String> = self.preamble.iter().map(ConfigLine::to_text).collect();
+        for block in &self.blocks {
+            block.collect_lines(&mut lines);
+        }
+
+        let mut out = lines.join("\n");
+        if self.trailing_newline && !lines.is_empty() {
+            out.push('\n');
+        }
+        out
+    }
+}
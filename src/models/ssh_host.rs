@@ -1,45 +1,173 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::pattern::has_wildcard;
+use crate::models::{FormField, LIST_SEPARATOR};
+
+/// Keywords ssh accumulates instead of keeping only the first value.
+pub const REPEATABLE_KEYWORDS: [&str; 6] = [
+    "IdentityFile",
+    "CertificateFile",
+    "LocalForward",
+    "RemoteForward",
+    "DynamicForward",
+    "SendEnv",
+];
+
+pub fn is_repeatable(keyword: &str) -> bool {
+    REPEATABLE_KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(keyword))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SshHost {
-    pub alias: String,
+    /// Every pattern on the `Host` line, negated `!pattern` entries included.
+    pub patterns: Vec<String>,
     pub hostname: String,
     pub port: u16,
     pub user: String,
-    pub identity_file: String,
+    pub identity_files: Vec<String>,
+    pub certificate_files: Vec<String>,
+    pub local_forwards: Vec<String>,
+    pub remote_forwards: Vec<String>,
+    pub dynamic_forwards: Vec<String>,
+    pub send_env: Vec<String>,
     pub extra_options: Vec<(String, String)>,
+    /// The `Port` value as written when it is not a valid port number.
+    #[serde(skip)]
+    pub invalid_port: Option<String>,
+    #[serde(skip)]
+    pub file_index: usize,
+    #[serde(skip)]
+    pub block_index: Option<usize>,
 }
 
 impl SshHost {
     pub fn empty() -> Self {
         Self {
-            alias: String::new(),
+            patterns: Vec::new(),
             hostname: String::new(),
             port: 22,
             user: String::new(),
-            identity_file: String::new(),
+            identity_files: Vec::new(),
+            certificate_files: Vec::new(),
+            local_forwards: Vec::new(),
+            remote_forwards: Vec::new(),
+            dynamic_forwards: Vec::new(),
+            send_env: Vec::new(),
             extra_options: Vec::new(),
+            invalid_port: None,
+            file_index: 0,
+            block_index: None,
         }
     }
 
+    /// The name shown in the host list: the first concrete alias, or the
+    /// first pattern when the block has none.
+    pub fn alias(&self) -> &str {
+        self.connect_alias()
+            .or_else(|| self.patterns.iter().find(|p| !p.is_empty()).map(String::as_str))
+            .unwrap_or("")
+    }
+
+    /// First pattern that names exactly one host, i.e. something `ssh` can
+    /// be pointed at.
+    pub fn connect_alias(&self) -> Option<&str> {
+        self.concrete_names().next()
+    }
+
+    /// Every pattern that names exactly one host; what shell completion
+    /// offers for this block.
+    pub fn concrete_names(&self) -> impl Iterator<Item = &str> {
+        self.patterns
+            .iter()
+            .filter(|p| !p.is_empty() && !p.starts_with('!') && !has_wildcard(p))
+            .map(String::as_str)
+    }
+
+    pub fn other_patterns(&self) -> Vec<&str> {
+        let primary = self.alias();
+        self.patterns
+            .iter()
+            .map(String::as_str)
+            .filter(|p| !p.is_empty() && *p != primary)
+            .collect()
+    }
+
+    /// The `Host` line value as edited in the form. Splitting on single
+    /// spaces keeps what the user typed intact until the form is saved.
+    pub fn patterns_text(&self) -> String {
+        self.patterns.join(" ")
+    }
+
+    pub fn set_patterns_text(&mut self, text: &str) {
+        self.patterns = text.split(' ').map(str::to_string).collect();
+    }
+
+    pub fn normalize_patterns(&mut self) {
+        self.patterns.retain(|p| !p.is_empty());
+    }
+
+    /// First non-negated pattern that also appears on `other`'s `Host` line.
+    pub fn shared_pattern<'a>(&'a self, other: &SshHost) -> Option<&'a str> {
+        self.patterns
+            .iter()
+            .filter(|p| !p.is_empty() && !p.starts_with('!'))
+            .find(|p| other.patterns.iter().any(|o| o.eq_ignore_ascii_case(p)))
+            .map(String::as_str)
+    }
+
     pub fn display_host(&self) -> &str {
         if self.hostname.is_empty() {
-            &self.alias
+            self.alias()
         } else {
             &self.hostname
         }
     }
 
-    pub fn as_ssh_command(&self) -> String {
-        format!("ssh {}", self.alias)
+    /// Expands the `%h`, `%n`, `%p` and `%r` tokens ssh allows in paths
+    /// such as `IdentityFile`, as far as this block alone tells. Without a
+    /// `User`, `%r` becomes `%u`, the local user. Pattern blocks name no
+    /// single host, so their values are returned as written; other tokens
+    /// are left for the key repository.
+    pub fn expand_host_tokens(&self, value: &str) -> String {
+        if self.is_pattern() {
+            return value.to_string();
+        }
+
+        let mut out = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('h') => out.push_str(self.display_host()),
+                Some('n') => out.push_str(self.alias()),
+                Some('p') => out.push_str(&self.port.to_string()),
+                Some('r') if self.user.is_empty() => out.push_str("%u"),
+                Some('r') => out.push_str(&self.user),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
     }
 
     pub fn as_ssh_args(&self) -> Vec<String> {
-        vec![self.alias.clone()]
+        vec![self.alias().to_string()]
+    }
+
+    /// Blocks without a concrete alias, such as `Host *` or `Host *.corp`,
+    /// only supply defaults to other hosts and are never connection targets.
+    pub fn is_pattern(&self) -> bool {
+        self.connect_alias().is_none()
     }
 
     pub fn is_valid(&self) -> bool {
-        !self.alias.is_empty() && !self.hostname.is_empty()
+        !self.alias().is_empty() && (self.is_pattern() || !self.hostname.is_empty())
     }
 
     pub fn has_custom_port(&self) -> bool {
@@ -47,7 +175,63 @@ impl SshHost {
     }
 
     pub fn has_identity_file(&self) -> bool {
-        !self.identity_file.is_empty()
+        !self.identity_files.is_empty()
+    }
+
+    /// The repeatable keywords paired with their values, in the order they
+    /// are written for a new host.
+    pub fn lists(&self) -> [(&'static str, &Vec<String>); 6] {
+        [
+            ("IdentityFile", &self.identity_files),
+            ("CertificateFile", &self.certificate_files),
+            ("LocalForward", &self.local_forwards),
+            ("RemoteForward", &self.remote_forwards),
+            ("DynamicForward", &self.dynamic_forwards),
+            ("SendEnv", &self.send_env),
+        ]
+    }
+
+    pub fn list_mut(&mut self, keyword: &str) -> Option<&mut Vec<String>> {
+        match keyword.to_lowercase().as_str() {
+            "identityfile" => Some(&mut self.identity_files),
+            "certificatefile" => Some(&mut self.certificate_files),
+            "localforward" => Some(&mut self.local_forwards),
+            "remoteforward" => Some(&mut self.remote_forwards),
+            "dynamicforward" => Some(&mut self.dynamic_forwards),
+            "sendenv" => Some(&mut self.send_env),
+            _ => None,
+        }
+    }
+
+    /// A list field as edited in the form. Like `patterns_text`, the raw
+    /// split keeps separators the user just typed.
+    pub fn list_text(&self, field: &FormField) -> String {
+        let values = field.list_keyword().and_then(|k| {
+            self.lists().into_iter().find(|(name, _)| *name == k).map(|(_, v)| v)
+        });
+        values
+            .map(|v| v.join(&LIST_SEPARATOR.to_string()))
+            .unwrap_or_default()
+    }
+
+    pub fn set_list_text(&mut self, field: &FormField, text: &str) {
+        if let Some(list) = field.list_keyword().and_then(|k| self.list_mut(k)) {
+            *list = if text.is_empty() {
+                Vec::new()
+            } else {
+                text.split(LIST_SEPARATOR).map(str::to_string).collect()
+            };
+        }
+    }
+
+    /// Drops the empty entries the form leaves behind while typing.
+    pub fn normalize_lists(&mut self) {
+        for keyword in REPEATABLE_KEYWORDS {
+            if let Some(list) = self.list_mut(keyword) {
+                list.iter_mut().for_each(|v| *v = v.trim().to_string());
+                list.retain(|v| !v.is_empty());
+            }
+        }
     }
 
     pub fn has_extra_options(&self) -> bool {
@@ -134,17 +134,8 @@ impl Theme {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ThemePreference {
     pub theme_index: usize,
     pub transparent: bool,
 }
-
-impl Default for ThemePreference {
-    fn default() -> Self {
-        Self {
-            theme_index: 0,
-            transparent: false,
-        }
-    }
-}
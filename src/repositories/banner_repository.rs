@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::models::BannerRecord;
+
+pub trait BannerRepository {
+    fn load(&self) -> HashMap<String, BannerRecord>;
+    fn save(&self, banners: &HashMap<String, BannerRecord>) -> Result<(), String>;
+}
+
+/// `~/.config/ssh-manager/banners.json`, keyed by host alias.
+pub struct FileBannerRepository {
+    path: PathBuf,
+}
+
+impl FileBannerRepository {
+    pub fn new() -> Self {
+        let dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ssh-manager");
+        fs::create_dir_all(&dir).ok();
+        Self { path: dir.join("banners.json") }
+    }
+}
+
+impl BannerRepository for FileBannerRepository {
+    fn load(&self) -> HashMap<String, BannerRecord> {
+        fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, banners: &HashMap<String, BannerRecord>) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(banners).map_err(|e| e.to_string())?;
+        fs::write(&self.path, json).map_err(|e| format!("Cannot write {}: {}", self.path.display(), e))
+    }
+}
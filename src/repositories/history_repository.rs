@@ -0,0 +1,49 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::models::{History, HistoryEntry};
+
+pub trait HistoryRepository {
+    fn load(&self) -> History;
+    fn append(&self, entry: &HistoryEntry) -> Result<(), String>;
+}
+
+/// `~/.config/ssh-manager/history.jsonl`, one JSON object per session.
+/// Appending never rewrites earlier lines; unreadable lines are skipped.
+pub struct FileHistoryRepository {
+    path: PathBuf,
+}
+
+impl FileHistoryRepository {
+    pub fn new() -> Self {
+        let dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ssh-manager");
+        fs::create_dir_all(&dir).ok();
+        Self { path: dir.join("history.jsonl") }
+    }
+}
+
+impl HistoryRepository for FileHistoryRepository {
+    fn load(&self) -> History {
+        let entries = fs::read_to_string(&self.path)
+            .map(|text| {
+                text.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        History { entries }
+    }
+
+    fn append(&self, entry: &HistoryEntry) -> Result<(), String> {
+        let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Cannot open {}: {}", self.path.display(), e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Cannot write {}: {}", self.path.display(), e))
+    }
+}
@@ -0,0 +1,440 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::models::ssh_config::split_arguments;
+use crate::models::ssh_key::{parse_private_key_header, pem_key_type, private_key_encrypted};
+use crate::models::{KeyFile, KeyGenRequest, PublicKey};
+
+/// Larger files in `~/.ssh` are not keys and are not read while scanning.
+const MAX_KEY_FILE_SIZE: u64 = 64 * 1024;
+
+pub trait KeyRepository {
+    /// Looks at the key an `IdentityFile` value points to without ever
+    /// reading secret material beyond the public header.
+    fn inspect(&self, identity: &str) -> KeyFile;
+    /// Private keys in `~/.ssh`, recognised by their PEM header, plus keys
+    /// whose `.pub` file is left but whose private half is gone.
+    fn scan(&self) -> Vec<KeyFile>;
+    /// Creates a key pair in `~/.ssh` with `ssh-keygen` and returns the
+    /// private key's path. Existing files are never overwritten.
+    fn generate(&self, request: &KeyGenRequest) -> Result<PathBuf, String>;
+}
+
+pub struct FileKeyRepository {
+    home: PathBuf,
+}
+
+impl FileKeyRepository {
+    pub fn new() -> Self {
+        Self::with_home(dirs::home_dir().unwrap_or_else(|| PathBuf::from("~")))
+    }
+
+    /// The repository for keys under `home`, which `~` and `%d` stand for.
+    pub(crate) fn with_home(home: PathBuf) -> Self {
+        Self { home }
+    }
+
+    /// Drops the quotes ssh allows around the value and expands `~` and the
+    /// `%d`/`%u`/`%%` tokens; other relative paths are taken from the home
+    /// directory. `None` when another token is left, since only ssh knows
+    /// what it stands for.
+    fn resolve(&self, identity: &str) -> Option<PathBuf> {
+        let identity = split_arguments(identity).join(" ");
+        let user = std::env::var("USER").unwrap_or_default();
+
+        let mut expanded = String::with_capacity(identity.len());
+        let mut chars = identity.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next()? {
+                'd' => expanded.push_str(&self.home.to_string_lossy()),
+                'u' => expanded.push_str(&user),
+                '%' => expanded.push('%'),
+                _ => return None,
+            }
+        }
+
+        Some(match expanded.strip_prefix("~/") {
+            Some(rest) => self.home.join(rest),
+            None => self.home.join(expanded),
+        })
+    }
+
+    fn public_path(path: &Path) -> PathBuf {
+        let mut pub_path = path.as_os_str().to_owned();
+        pub_path.push(".pub");
+        PathBuf::from(pub_path)
+    }
+
+    fn read_public(path: &Path) -> Option<PublicKey> {
+        if let Some(key) = fs::read_to_string(Self::public_path(path))
+            .ok()
+            .and_then(|text| PublicKey::parse_line(text.trim()))
+        {
+            return Some(key);
+        }
+
+        let text = fs::read_to_string(path).ok()?;
+        parse_private_key_header(&text).or_else(|| {
+            pem_key_type(&text).map(|key_type| PublicKey {
+                key_type: key_type.to_string(),
+                bits: None,
+                comment: String::new(),
+                blob: Vec::new(),
+            })
+        })
+    }
+
+    fn inspect_path(path: PathBuf) -> KeyFile {
+        let metadata = fs::metadata(&path).ok();
+
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            metadata.as_ref().map(|m| m.permissions().mode() & 0o777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        let public_path = Some(Self::public_path(&path)).filter(|p| p.is_file());
+        let encrypted = metadata
+            .as_ref()
+            .and_then(|_| fs::read_to_string(&path).ok())
+            .and_then(|text| private_key_encrypted(&text));
+
+        KeyFile {
+            exists: metadata.is_some(),
+            public: Self::read_public(&path),
+            path,
+            mode,
+            encrypted,
+            public_path,
+            unresolved: false,
+        }
+    }
+
+    /// The last thing ssh-keygen complained about, without the passphrase
+    /// prompts it prints on the same line.
+    fn keygen_error(stderr: &[u8]) -> String {
+        String::from_utf8_lossy(stderr)
+            .lines()
+            .map(|l| l.rsplit("again: ").next().unwrap_or(l).trim().to_string())
+            .rfind(|l| !l.is_empty())
+            .unwrap_or_else(|| "no output".into())
+    }
+
+    /// ssh-keygen asks for a new passphrase on `/dev/tty` whenever it has
+    /// a controlling terminal, and would hang behind the TUI. In a session
+    /// of its own it has none and reads the answers from stdin instead.
+    #[cfg(unix)]
+    fn detach_from_terminal(command: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        // SAFETY: setsid is async-signal-safe and touches no memory of the
+        // parent, which is all pre_exec requires.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn detach_from_terminal(_command: &mut Command) {}
+
+    #[cfg(unix)]
+    fn set_mode(path: &Path, mode: u32) -> Result<(), String> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+            .map_err(|e| format!("Cannot set permissions of {}: {}", path.display(), e))
+    }
+
+    #[cfg(not(unix))]
+    fn set_mode(_path: &Path, _mode: u32) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn is_private_key(path: &Path) -> bool {
+        let small = fs::metadata(path).is_ok_and(|m| m.is_file() && m.len() <= MAX_KEY_FILE_SIZE);
+        small
+            && fs::read_to_string(path).is_ok_and(|text| {
+                text.lines()
+                    .next()
+                    .is_some_and(|l| l.starts_with("-----BEGIN ") && l.trim_end().ends_with("PRIVATE KEY-----"))
+            })
+    }
+}
+
+impl KeyRepository for FileKeyRepository {
+    fn inspect(&self, identity: &str) -> KeyFile {
+        match self.resolve(identity) {
+            Some(path) => Self::inspect_path(path),
+            None => KeyFile::unresolved(&split_arguments(identity).join(" ")),
+        }
+    }
+
+    fn scan(&self) -> Vec<KeyFile> {
+        let Ok(entries) = fs::read_dir(self.home.join(".ssh")) else {
+            return Vec::new();
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter_map(|path| match path.to_string_lossy().strip_suffix(".pub") {
+                Some(private) if !Path::new(private).exists() => {
+                    Self::read_public(Path::new(private)).map(|_| PathBuf::from(private))
+                }
+                Some(_) => None,
+                None => Some(path.clone()).filter(|p| Self::is_private_key(p)),
+            })
+            .collect();
+        paths.sort();
+
+        paths.into_iter().map(Self::inspect_path).collect()
+    }
+
+    fn generate(&self, request: &KeyGenRequest) -> Result<PathBuf, String> {
+        request.validate_file_name()?;
+        if request.passphrase.contains('\n') {
+            return Err("Passphrase cannot contain a line break".into());
+        }
+
+        let ssh_dir = self.home.join(".ssh");
+        if !ssh_dir.exists() {
+            fs::create_dir_all(&ssh_dir).map_err(|e| format!("Cannot create {}: {}", ssh_dir.display(), e))?;
+            Self::set_mode(&ssh_dir, 0o700)?;
+        }
+
+        let path = ssh_dir.join(&request.file_name);
+        let public_path = Self::public_path(&path);
+        if let Some(existing) = [&path, &public_path].into_iter().find(|p| p.exists()) {
+            return Err(format!("{} already exists", existing.display()));
+        }
+
+        let mut command = Command::new("ssh-keygen");
+        command
+            .arg("-q")
+            .args(request.algorithm.keygen_args())
+            .arg("-f")
+            .arg(&path);
+        // Without -C ssh-keygen fills in user@host.
+        if !request.comment.is_empty() {
+            command.arg("-C").arg(&request.comment);
+        }
+
+        // The passphrase goes through stdin so it never shows up in the
+        // process list. ssh-keygen only reads it from there when it has no
+        // terminal to ask on, see `detach_from_terminal`.
+        Self::detach_from_terminal(&mut command);
+        let mut child = command
+            .env("SSH_ASKPASS_REQUIRE", "never")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Cannot run ssh-keygen: {}", e))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            let answer = format!("{0}\n{0}\n", request.passphrase);
+            if let Err(e) = stdin.write_all(answer.as_bytes()) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Cannot pass the passphrase to ssh-keygen: {}", e));
+            }
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("ssh-keygen failed: {}", e))?;
+        if !output.status.success() || !path.exists() {
+            return Err(format!("ssh-keygen failed: {}", Self::keygen_error(&output.stderr)));
+        }
+
+        Self::set_mode(&path, 0o600)?;
+        if public_path.exists() {
+            Self::set_mode(&public_path, 0o644)?;
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ssh_key::KeyAlgorithm;
+    use crate::models::SshHost;
+
+    fn temp_home(name: &str) -> FileKeyRepository {
+        let home = std::env::temp_dir().join(format!("lazyssh-keys-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&home);
+        fs::create_dir_all(&home).unwrap();
+        FileKeyRepository { home }
+    }
+
+    fn request(file_name: &str, passphrase: &str) -> KeyGenRequest {
+        KeyGenRequest {
+            algorithm: KeyAlgorithm::Ed25519,
+            file_name: file_name.into(),
+            comment: "test@lazyssh".into(),
+            passphrase: passphrase.into(),
+        }
+    }
+
+    fn has_ssh_keygen() -> bool {
+        Command::new("ssh-keygen").arg("-?").stderr(Stdio::null()).status().is_ok()
+    }
+
+    fn unlocks(path: &Path, passphrase: &str) -> bool {
+        Command::new("ssh-keygen")
+            .args(["-y", "-P", passphrase, "-f"])
+            .arg(path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn generates_key_with_passphrase() {
+        if !has_ssh_keygen() {
+            return;
+        }
+        let repo = temp_home("passphrase");
+        let path = repo.generate(&request("id_test", "correct horse")).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(private_key_encrypted(&text), Some(true));
+        assert!(unlocks(&path, "correct horse"));
+        assert!(!unlocks(&path, ""));
+
+        let public = fs::read_to_string(FileKeyRepository::public_path(&path)).unwrap();
+        assert!(public.starts_with("ssh-ed25519 ") && public.trim_end().ends_with("test@lazyssh"));
+
+        #[cfg(unix)]
+        {
+            assert_eq!(mode(&path), 0o600);
+            assert_eq!(mode(&FileKeyRepository::public_path(&path)), 0o644);
+            assert_eq!(mode(&repo.home.join(".ssh")), 0o700);
+        }
+        let _ = fs::remove_dir_all(&repo.home);
+    }
+
+    #[test]
+    fn generates_key_without_passphrase() {
+        if !has_ssh_keygen() {
+            return;
+        }
+        let repo = temp_home("empty");
+        let mut rsa = request("id_rsa_test", "");
+        rsa.algorithm = KeyAlgorithm::Rsa4096;
+        let path = repo.generate(&rsa).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(private_key_encrypted(&text), Some(false));
+        assert!(unlocks(&path, ""));
+        assert_eq!(FileKeyRepository::inspect_path(path.clone()).public.and_then(|k| k.bits), Some(4096));
+        let _ = fs::remove_dir_all(&repo.home);
+    }
+
+    #[test]
+    fn never_overwrites_an_existing_key() {
+        let repo = temp_home("exists");
+        fs::create_dir_all(repo.home.join(".ssh")).unwrap();
+        fs::write(repo.home.join(".ssh/id_test.pub"), "keep me").unwrap();
+
+        let err = repo.generate(&request("id_test", "")).unwrap_err();
+        assert!(err.contains("already exists"));
+        assert_eq!(fs::read_to_string(repo.home.join(".ssh/id_test.pub")).unwrap(), "keep me");
+        let _ = fs::remove_dir_all(&repo.home);
+    }
+
+    #[test]
+    fn rejects_names_outside_ssh_dir() {
+        let repo = temp_home("names");
+        for name in ["", "../id_x", ".hidden", "id_x.pub"] {
+            assert!(repo.generate(&request(name, "")).is_err(), "{name}");
+        }
+        let _ = fs::remove_dir_all(&repo.home);
+    }
+
+    fn write_key(repo: &FileKeyRepository, name: &str) -> PathBuf {
+        let path = repo.home.join(".ssh").join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keys/ed25519"))).unwrap();
+        path
+    }
+
+    #[test]
+    fn quoted_identity_files_are_unquoted() {
+        let repo = temp_home("quoted");
+        let path = write_key(&repo, "id web");
+
+        let key = repo.inspect("\"~/.ssh/id web\"");
+        assert_eq!(key.path, path);
+        assert!(key.exists);
+        assert_eq!(key.public.unwrap().key_type, "ssh-ed25519");
+        assert_eq!(repo.inspect("\".ssh/id web\"").path, path);
+    }
+
+    #[test]
+    fn host_tokens_are_expanded_for_concrete_hosts() {
+        let repo = temp_home("tokens");
+        let path = write_key(&repo, "web.example.com-deploy.key");
+        let host = SshHost {
+            patterns: vec!["web".to_string()],
+            hostname: "web.example.com".to_string(),
+            user: "deploy".to_string(),
+            ..SshHost::empty()
+        };
+
+        let identity = host.expand_host_tokens("~/.ssh/%h-%r.key");
+        assert_eq!(identity, "~/.ssh/web.example.com-deploy.key");
+        let key = repo.inspect(&identity);
+        assert_eq!(key.path, path);
+        assert!(key.exists && !key.unresolved);
+
+        assert_eq!(host.expand_host_tokens("%n_%p_100%%"), "web_22_100%%");
+        assert_eq!(repo.inspect("~/.ssh/100%%").path, repo.home.join(".ssh/100%"));
+        assert_eq!(repo.inspect("%d/.ssh/k").path, repo.home.join(".ssh/k"));
+    }
+
+    #[test]
+    fn tokens_only_ssh_knows_are_unresolved_not_missing() {
+        let repo = temp_home("unresolved");
+        let pattern = SshHost {
+            patterns: vec!["*.corp".to_string()],
+            ..SshHost::empty()
+        };
+
+        for identity in [pattern.expand_host_tokens("~/.ssh/%h.key"), "~/.ssh/%C".to_string(), "~/.ssh/key%".to_string()] {
+            let key = repo.inspect(&identity);
+            assert!(key.unresolved, "{}", identity);
+            assert!(!key.exists);
+            assert_eq!(key.path, PathBuf::from(&identity));
+
+            let usage = crate::models::KeyUsage {
+                key,
+                hosts: vec!["*.corp".to_string()],
+            };
+            assert!(!usage.is_missing());
+        }
+
+        let missing = repo.inspect("~/.ssh/nope");
+        assert!(!missing.unresolved && !missing.exists);
+    }
+}
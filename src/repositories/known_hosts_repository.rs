@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::models::{BackupEntry, KnownHostEntry, KnownHosts, KnownHostsFile};
+use crate::repositories::FileSshRepository;
+
+const SYSTEM_KNOWN_HOSTS: &str = "/etc/ssh/ssh_known_hosts";
+
+pub trait KnownHostsRepository {
+    fn load(&self) -> KnownHosts;
+    /// Copies `path` aside, then replaces it with `content`. Returns the
+    /// backup path.
+    fn save(&self, path: &Path, content: &str) -> Result<PathBuf, String>;
+}
+
+/// Reads ssh's default `UserKnownHostsFile` and `GlobalKnownHostsFile`
+/// locations; files that don't exist are skipped.
+pub struct FileKnownHostsRepository {
+    paths: Vec<PathBuf>,
+}
+
+impl FileKnownHostsRepository {
+    pub fn new() -> Self {
+        let ssh_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(".ssh");
+        Self {
+            paths: vec![
+                ssh_dir.join("known_hosts"),
+                ssh_dir.join("known_hosts2"),
+                PathBuf::from(SYSTEM_KNOWN_HOSTS),
+            ],
+        }
+    }
+
+    /// `known_hosts.old_<timestamp>` next to the file, after ssh-keygen's
+    /// `known_hosts.old`. Not `.backup_`, so the config backup browser
+    /// never offers to restore one over `~/.ssh/config`. Several edits in
+    /// the same second get numbered so the oldest copy survives.
+    fn create_backup(path: &Path) -> Result<PathBuf, String> {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "known_hosts".into());
+        let stem = format!("{}.old_{}", file_name, Local::now().format(BackupEntry::TIMESTAMP_FORMAT));
+        let mut backup_path = path.with_file_name(&stem);
+        let mut n = 1;
+        while backup_path.exists() {
+            n += 1;
+            backup_path = path.with_file_name(format!("{}_{}", stem, n));
+        }
+
+        fs::copy(path, &backup_path).map_err(|e| format!("backup failed: {}", e))?;
+        Ok(backup_path)
+    }
+}
+
+impl KnownHostsRepository for FileKnownHostsRepository {
+    fn load(&self) -> KnownHosts {
+        let mut known = KnownHosts::default();
+        for path in &self.paths {
+            let Ok(text) = fs::read_to_string(path) else { continue };
+            let file_index = known.files.len();
+            known.entries.extend(
+                text.lines()
+                    .enumerate()
+                    .filter_map(|(i, line)| KnownHostEntry::parse(line, file_index, i + 1)),
+            );
+            known.files.push(KnownHostsFile {
+                path: path.clone(),
+                lines: text.lines().map(String::from).collect(),
+                system: path == Path::new(SYSTEM_KNOWN_HOSTS),
+            });
+        }
+        known
+    }
+
+    fn save(&self, path: &Path, content: &str) -> Result<PathBuf, String> {
+        let backup_path = Self::create_backup(path)?;
+        FileSshRepository::write_atomically(path, content)?;
+        Ok(backup_path)
+    }
+}
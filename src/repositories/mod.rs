@@ -1,5 +1,15 @@
+pub mod banner_repository;
+pub mod history_repository;
+pub mod key_repository;
+pub mod known_hosts_repository;
+pub mod settings_repository;
 pub mod ssh_repository;
 pub mod theme_repository;
 
+pub use banner_repository::{BannerRepository, FileBannerRepository};
+pub use history_repository::{FileHistoryRepository, HistoryRepository};
+pub use key_repository::{FileKeyRepository, KeyRepository};
+pub use known_hosts_repository::{FileKnownHostsRepository, KnownHostsRepository};
+pub use settings_repository::{FileSettingsRepository, SettingsRepository};
 pub use ssh_repository::{FileSshRepository, SshRepository};
 pub use theme_repository::{FileThemeRepository, ThemeRepository};
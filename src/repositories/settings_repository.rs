@@ -0,0 +1,31 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::models::Settings;
+
+pub trait SettingsRepository {
+    fn load(&self) -> Settings;
+}
+
+pub struct FileSettingsRepository {
+    path: PathBuf,
+}
+
+impl FileSettingsRepository {
+    pub fn new() -> Self {
+        let dir = dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ssh-manager");
+        fs::create_dir_all(&dir).ok();
+        Self { path: dir.join("settings.json") }
+    }
+}
+
+impl SettingsRepository for FileSettingsRepository {
+    fn load(&self) -> Settings {
+        fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+}
@@ -1,206 +1,406 @@
-use std::fs;
-use std::path::PathBuf;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
 
 use chrono::Local;
 
-use crate::models::SshHost;
+use crate::models::pattern::{has_wildcard, wildcard_match};
+use crate::models::{
+    BackupEntry, ConfigBlock, ConfigFile, ConfigLine, ConfigSet, Diagnostic, Directive, FileStamp,
+    Severity, SshConfig, MAX_INCLUDE_DEPTH,
+};
 
 pub trait SshRepository {
-    fn load_all(&self) -> (String, Vec<SshHost>);
-    fn save_all(&self, preamble: &str, hosts: &[SshHost]) -> Result<PathBuf, String>;
+    fn load_all(&self) -> ConfigSet;
+    /// Writes one file atomically. Refuses when the file on disk no longer
+    /// matches `file.stamp`, i.e. someone else changed it since loading.
+    fn save_file(&self, file: &ConfigFile) -> Result<PathBuf, String>;
+    fn disk_stamp(&self, path: &Path) -> Option<FileStamp>;
     fn config_path(&self) -> PathBuf;
+
+    fn list_backups(&self) -> Vec<BackupEntry>;
+    fn read_text(&self, path: &Path) -> Result<String, String>;
+    /// Backs up `target` as it is now, then replaces it with the backup.
+    fn restore_backup(&self, backup: &BackupEntry, target: &Path) -> Result<PathBuf, String>;
+    fn delete_backup(&self, backup: &BackupEntry) -> Result<(), String>;
 }
 
 pub struct FileSshRepository {
     path: PathBuf,
+    /// What `~` in an `Include` stands for.
+    home: PathBuf,
 }
 
 impl FileSshRepository {
     pub fn new() -> Self {
-        let path = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("~"))
-            .join(".ssh")
-            .join("config");
-        Self { path }
+        Self::with_home(dirs::home_dir().unwrap_or_else(|| PathBuf::from("~")))
     }
 
-    fn read_file(&self) -> String {
-        fs::read_to_string(&self.path).unwrap_or_default()
+    /// The repository for `home/.ssh/config`.
+    pub(crate) fn with_home(home: PathBuf) -> Self {
+        Self {
+            path: home.join(".ssh").join("config"),
+            home,
+        }
     }
 
-    fn create_backup(&self) -> Result<PathBuf, String> {
-        if !self.path.exists() {
-            return Ok(self.path.clone());
+    fn ssh_dir(&self) -> &Path {
+        self.path.parent().unwrap()
+    }
+
+    fn load_file(&self, path: &Path, set: &mut ConfigSet, depth: usize) {
+        if set.files.iter().any(|f| f.path == path) {
+            return;
+        }
+
+        let mut load_diagnostics = Vec::new();
+        let content = match fs::read_to_string(path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => {
+                load_diagnostics.push(Diagnostic::new(
+                    0,
+                    Severity::Error,
+                    format!("cannot read file: {}", e),
+                ));
+                None
+            }
+        };
+        let stamp = content.as_deref().map(Self::stamp);
+
+        let document = SshConfigParser::parse(&content.unwrap_or_default());
+        let include_lines = document.include_directives();
+
+        let index = set.files.len();
+        set.files.push(ConfigFile {
+            path: path.to_path_buf(),
+            document,
+            includes: Vec::new(),
+            stamp,
+            load_diagnostics,
+        });
+
+        for (line, patterns) in include_lines {
+            if depth >= MAX_INCLUDE_DEPTH {
+                set.files[index].load_diagnostics.push(Diagnostic::new(
+                    line,
+                    Severity::Error,
+                    format!("Include nested deeper than {} levels; not loaded", MAX_INCLUDE_DEPTH),
+                ));
+                continue;
+            }
+
+            let mut resolved = Vec::new();
+            for pattern in patterns {
+                let matches = self.expand_include(&pattern);
+                if matches.is_empty() {
+                    set.files[index].load_diagnostics.push(Diagnostic::new(
+                        line,
+                        Severity::Warning,
+                        format!("Include '{}' matches no files", pattern),
+                    ));
+                }
+                for included in matches {
+                    self.load_file(&included, set, depth + 1);
+                    if let Some(i) = set.files.iter().position(|f| f.path == included) {
+                        resolved.push(i);
+                    }
+                }
+            }
+            set.files[index].includes.push((line, resolved));
         }
+    }
 
+    /// Resolves an `Include` argument the way ssh does for user configs:
+    /// `~` is the home directory and relative paths start in `~/.ssh`.
+    fn expand_include(&self, pattern: &str) -> Vec<PathBuf> {
+        let path = match pattern.strip_prefix("~/") {
+            Some(rest) => self.home.join(rest),
+            None if Path::new(pattern).is_absolute() => PathBuf::from(pattern),
+            None => self.ssh_dir().join(pattern),
+        };
+
+        let mut candidates = vec![PathBuf::new()];
+        for component in path.components() {
+            let name = component.as_os_str().to_string_lossy();
+            if !has_wildcard(&name) {
+                for candidate in &mut candidates {
+                    candidate.push(component);
+                }
+                continue;
+            }
+
+            let mut expanded = Vec::new();
+            for dir in &candidates {
+                let Ok(entries) = fs::read_dir(dir) else { continue };
+                let mut matches: Vec<PathBuf> = entries
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .filter(|n| !n.starts_with('.') || name.starts_with('.'))
+                    .filter(|n| wildcard_match(&name, n))
+                    .map(|n| dir.join(n))
+                    .collect();
+                matches.sort();
+                expanded.extend(matches);
+            }
+            candidates = expanded;
+        }
+
+        candidates.into_iter().filter(|p| p.is_file()).collect()
+    }
+
+    /// Backups of every file, included ones too, live next to the main
+    /// config so an `Include config.d/*` glob never picks them up. The name
+    /// keeps the file's path, so a restore goes back to the same file.
+    fn create_backup(&self, path: &Path) -> Result<PathBuf, String> {
+        if !path.exists() {
+            return Ok(path.to_path_buf());
+        }
+
+        let original_name = path.strip_prefix(self.ssh_dir()).unwrap_or(path);
         let backup_name = format!(
-            "config.backup_{}",
-            Local::now().format("%Y%m%d_%H%M%S")
+            "{}{}{}",
+            BackupEntry::encode_source(&original_name.to_string_lossy()),
+            BackupEntry::MARKER,
+            Local::now().format(BackupEntry::TIMESTAMP_FORMAT)
         );
-        let backup_path = self.path.parent().unwrap().join(backup_name);
+        let backup_path = self.ssh_dir().join(backup_name);
 
-        fs::copy(&self.path, &backup_path)
+        fs::copy(path, &backup_path)
             .map_err(|e| format!("backup failed: {}", e))?;
 
         Ok(backup_path)
     }
 
-    fn serialize(preamble: &str, hosts: &[SshHost]) -> String {
-        let mut output = String::new();
-
-        if !preamble.is_empty() {
-            output.push_str(preamble);
-            if !preamble.ends_with('\n') {
-                output.push('\n');
-            }
+    fn stamp(content: &str) -> FileStamp {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        FileStamp {
+            hash: hasher.finish(),
         }
+    }
 
-        for host in hosts {
-            output.push_str(&format!("Host {}\n", host.alias));
+    /// Writes to a temporary file in the same directory, fsyncs it and
+    /// renames it over the target so a crash never leaves a truncated config.
+    /// Symlinked configs (dotfile managers) are written through to the target.
+    /// The target keeps its permissions; a new file is created 0600.
+    pub(crate) fn write_atomically(path: &Path, content: &str) -> Result<(), String> {
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let dir = target.parent().unwrap_or(Path::new("."));
+        let file_name = target
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "config".into());
+        let temp = dir.join(format!(".{}.lazyssh-{}", file_name, std::process::id()));
+
+        let permissions = fs::metadata(&target).ok().map(|m| m.permissions());
+        let result = Self::write_synced(&temp, content, permissions)
+            .and_then(|_| fs::rename(&temp, &target))
+            .map_err(|e| format!("write failed: {}", e));
+
+        if result.is_err() {
+            fs::remove_file(&temp).ok();
+            return result;
+        }
 
-            if !host.hostname.is_empty() {
-                output.push_str(&format!("    HostName {}\n", host.hostname));
-            }
-            if !host.user.is_empty() {
-                output.push_str(&format!("    User {}\n", host.user));
-            }
-            if host.has_custom_port() {
-                output.push_str(&format!("    Port {}\n", host.port));
-            }
-            if host.has_identity_file() {
-                output.push_str(&format!("    IdentityFile {}\n", host.identity_file));
-            }
-            for (key, value) in &host.extra_options {
-                output.push_str(&format!("    {} {}\n", key, value));
-            }
-            output.push('\n');
+        #[cfg(unix)]
+        if let Ok(dir) = File::open(dir) {
+            dir.sync_all().ok();
         }
 
-        output
+        Ok(())
     }
 
-    fn set_permissions(&self) {
+    fn write_synced(path: &Path, content: &str, permissions: Option<fs::Permissions>) -> std::io::Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
         #[cfg(unix)]
         {
-            use std::os::unix::fs::PermissionsExt;
-            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600)).ok();
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path)?;
+        file.write_all(content.as_bytes())?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
         }
+        file.sync_all()
     }
 }
 
 impl SshRepository for FileSshRepository {
-    fn load_all(&self) -> (String, Vec<SshHost>) {
-        let content = self.read_file();
-        SshConfigParser::parse(&content)
+    fn load_all(&self) -> ConfigSet {
+        let mut set = ConfigSet::default();
+        self.load_file(&self.path, &mut set, 0);
+        set
     }
 
-    fn save_all(&self, preamble: &str, hosts: &[SshHost]) -> Result<PathBuf, String> {
-        let backup_path = self.create_backup()?;
+    fn save_file(&self, file: &ConfigFile) -> Result<PathBuf, String> {
+        if !FileStamp::unchanged(file.stamp, self.disk_stamp(&file.path)) {
+            return Err(format!("{} changed on disk since it was loaded", file.path.display()));
+        }
 
-        let ssh_dir = self.path.parent().unwrap();
-        fs::create_dir_all(ssh_dir)
-            .map_err(|e| format!("cannot create .ssh directory: {}", e))?;
+        let backup_path = self.create_backup(&file.path)?;
 
-        let content = Self::serialize(preamble, hosts);
-        fs::write(&self.path, content)
-            .map_err(|e| format!("write failed: {}", e))?;
+        if let Some(dir) = file.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+        }
 
-        self.set_permissions();
+        Self::write_atomically(&file.path, &file.document.render())?;
         Ok(backup_path)
     }
 
+    fn disk_stamp(&self, path: &Path) -> Option<FileStamp> {
+        let content = fs::read_to_string(path).ok()?;
+        Some(Self::stamp(&content))
+    }
+
     fn config_path(&self) -> PathBuf {
         self.path.clone()
     }
+
+    fn list_backups(&self) -> Vec<BackupEntry> {
+        let Ok(entries) = fs::read_dir(self.ssh_dir()) else {
+            return Vec::new();
+        };
+
+        let mut backups: Vec<BackupEntry> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let (original_name, created) = BackupEntry::parse_name(&name)?;
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                Some(BackupEntry {
+                    path: entry.path(),
+                    source: self.ssh_dir().join(&original_name),
+                    original_name,
+                    created,
+                    size,
+                })
+            })
+            .collect();
+
+        backups.sort_by_key(|b| std::cmp::Reverse(b.created));
+        backups
+    }
+
+    fn read_text(&self, path: &Path) -> Result<String, String> {
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))
+    }
+
+    fn restore_backup(&self, backup: &BackupEntry, target: &Path) -> Result<PathBuf, String> {
+        let content = self.read_text(&backup.path)?;
+        let safety_backup = self.create_backup(target)?;
+        Self::write_atomically(target, &content)?;
+        Ok(safety_backup)
+    }
+
+    fn delete_backup(&self, backup: &BackupEntry) -> Result<(), String> {
+        fs::remove_file(&backup.path)
+            .map_err(|e| format!("cannot delete {}: {}", backup.file_name(), e))
+    }
 }
 
 struct SshConfigParser;
 
 impl SshConfigParser {
-    fn parse(content: &str) -> (String, Vec<SshHost>) {
-        let mut preamble = String::new();
-        let mut hosts: Vec<SshHost> = Vec::new();
-        let mut current: Option<SshHost> = None;
-        let mut in_preamble = true;
-
-        for line in content.lines() {
-            let trimmed = line.trim();
-            let lower = trimmed.to_lowercase();
-
-            if lower.starts_with("host ") && !lower.starts_with("hostname") {
-                if let Some(host) = current.take() {
-                    Self::push_if_named(&mut hosts, host);
-                }
-
-                in_preamble = false;
-                let alias = Self::strip_inline_comment(&trimmed[5..]).trim().to_string();
-
-                current = Some(SshHost {
-                    alias,
-                    ..SshHost::empty()
-                });
-                continue;
-            }
+    fn parse(content: &str) -> SshConfig {
+        let mut config = SshConfig {
+            trailing_newline: content.ends_with('\n'),
+            crlf: content.split_once('\n').is_some_and(|(first, _)| first.ends_with('\r')),
+            ..SshConfig::default()
+        };
+
+        let body = content.strip_suffix('\n').unwrap_or(content);
+        if content.is_empty() {
+            return config;
+        }
 
-            if in_preamble {
-                preamble.push_str(line);
-                preamble.push('\n');
-                continue;
+        for raw in body.split('\n') {
+            let line = Self::parse_line(raw);
+
+            if let ConfigLine::Directive(directive) = &line {
+                if directive.is("host") || directive.is("match") {
+                    let leading = match config.blocks.last_mut() {
+                        Some(block) => Self::take_leading_comments(&mut block.body),
+                        None => Self::take_leading_comments(&mut config.preamble),
+                    };
+                    config.blocks.push(ConfigBlock {
+                        leading,
+                        header: directive.clone(),
+                        body: Vec::new(),
+                    });
+                    continue;
+                }
             }
 
-            if let Some(ref mut host) = current {
-                Self::parse_option(host, trimmed);
+            match config.blocks.last_mut() {
+                Some(block) => block.body.push(line),
+                None => config.preamble.push(line),
             }
         }
 
-        if let Some(host) = current {
-            Self::push_if_named(&mut hosts, host);
-        }
-
-        (preamble, hosts)
+        config
     }
 
-    fn push_if_named(hosts: &mut Vec<SshHost>, host: SshHost) {
-        if host.alias != "*" && !host.alias.is_empty() {
-            hosts.push(host);
-        }
+    /// Comments written directly above a `Host` line (no blank line in
+    /// between) describe that host, so they move into its block.
+    fn take_leading_comments(lines: &mut Vec<ConfigLine>) -> Vec<ConfigLine> {
+        let start = lines
+            .iter()
+            .rposition(|line| !matches!(line, ConfigLine::Comment(_)))
+            .map_or(0, |i| i + 1);
+        lines.split_off(start)
     }
 
-    fn parse_option(host: &mut SshHost, line: &str) {
-        if line.is_empty() || line.starts_with('#') {
-            return;
+    fn parse_line(raw: &str) -> ConfigLine {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            return ConfigLine::Blank(raw.to_string());
         }
-
-        let (key, raw_value) = Self::split_key_value(line);
-        if key.is_empty() {
-            return;
+        if trimmed.starts_with('#') {
+            return ConfigLine::Comment(raw.to_string());
         }
 
-        let value = Self::strip_inline_comment(&raw_value).to_string();
+        let rest = raw.trim_start();
+        let indent = &raw[..raw.len() - rest.len()];
 
-        match key.to_lowercase().as_str() {
-            "hostname" => host.hostname = value,
-            "port" => host.port = value.parse().unwrap_or(22),
-            "user" => host.user = value,
-            "identityfile" => host.identity_file = value,
-            _ => host.extra_options.push((key, value)),
-        }
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let (keyword, after_key) = rest.split_at(key_end);
+
+        let separator_len = Self::separator_len(after_key);
+        let (separator, tail) = after_key.split_at(separator_len);
+
+        let value = Self::strip_inline_comment(tail).trim_end();
+        let trailing = &tail[value.len()..];
+
+        ConfigLine::Directive(Directive {
+            indent: indent.to_string(),
+            keyword: keyword.to_string(),
+            separator: separator.to_string(),
+            value: value.to_string(),
+            trailing: trailing.to_string(),
+        })
     }
 
-    fn split_key_value(line: &str) -> (String, String) {
-        if let Some(pos) = line.find('=') {
-            let k = line[..pos].trim().to_string();
-            let v = line[pos + 1..].trim().to_string();
-            return (k, v);
-        }
+    /// Length of the `Keyword value` separator: whitespace, an optional `=`,
+    /// then more whitespace.
+    fn separator_len(s: &str) -> usize {
+        let after_space = s.trim_start();
+        let mut len = s.len() - after_space.len();
 
-        if let Some(pos) = line.find(char::is_whitespace) {
-            let k = line[..pos].trim().to_string();
-            let v = line[pos..].trim().to_string();
-            return (k, v);
+        if let Some(after_eq) = after_space.strip_prefix('=') {
+            len += 1 + after_eq.len() - after_eq.trim_start().len();
         }
 
-        (String::new(), String::new())
+        len
     }
 
     fn strip_inline_comment(s: &str) -> &str {
@@ -223,3 +423,432 @@ impl SshConfigParser {
         s
     }
 }
+
+/// A throwaway home directory under the system temp dir, with `files`
+/// written at their paths relative to it (e.g. `.ssh/config`).
+#[cfg(test)]
+pub(crate) fn fixture_home(name: &str, files: &[(&str, &str)]) -> FileSshRepository {
+    let home = std::env::temp_dir().join(format!("lazyssh-home-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&home);
+    fs::create_dir_all(home.join(".ssh")).unwrap();
+    for (path, content) in files {
+        let path = home.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    FileSshRepository::with_home(home)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SshHost;
+
+    fn host(alias: &str, hostname: &str, user: &str) -> SshHost {
+        SshHost {
+            patterns: vec![alias.to_string()],
+            hostname: hostname.to_string(),
+            user: user.to_string(),
+            ..SshHost::empty()
+        }
+    }
+
+    fn temp_ssh_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lazyssh-config-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("config.d")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn backups_of_includes_restore_to_their_own_file() {
+        let dir = temp_ssh_dir("backups");
+        fs::write(dir.join("config"), "Include config.d/*\n").unwrap();
+        fs::write(dir.join("config.d/config"), "Host web\n").unwrap();
+        let repo = FileSshRepository {
+            path: dir.join("config"),
+            home: dir.clone(),
+        };
+
+        repo.create_backup(&dir.join("config")).unwrap();
+        repo.create_backup(&dir.join("config.d/config")).unwrap();
+
+        let mut sources: Vec<(String, PathBuf)> = repo
+            .list_backups()
+            .into_iter()
+            .map(|b| (b.original_name, b.source))
+            .collect();
+        sources.sort();
+        assert_eq!(
+            sources,
+            vec![
+                ("config".to_string(), dir.join("config")),
+                ("config.d/config".to_string(), dir.join("config.d/config")),
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn save_refuses_external_edit_that_kept_the_mtime() {
+        let dir = temp_ssh_dir("stamp");
+        let path = dir.join("config");
+        fs::write(&path, "Host web\n    User a\n").unwrap();
+        let repo = FileSshRepository {
+            path: path.clone(),
+            home: dir.clone(),
+        };
+
+        let mut set = repo.load_all();
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, "Host web\n    User b\n").unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+
+        set.files[0].document.add_host(&host("db", "db.example", ""));
+        let err = repo.save_file(&set.files[0]).unwrap_err();
+        assert!(err.contains("changed on disk"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Host web\n    User b\n");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_keep_the_file_mode_and_create_new_files_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let dir = temp_ssh_dir("mode");
+        let path = dir.join("config");
+        fs::write(&path, "Host web\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let repo = FileSshRepository {
+            path: path.clone(),
+            home: dir.clone(),
+        };
+
+        let mut set = repo.load_all();
+        set.files[0].document.add_host(&host("db", "db.example", ""));
+        repo.save_file(&set.files[0]).unwrap();
+        assert_eq!(mode(&path), 0o644);
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        FileSshRepository::write_atomically(&path, "Host web\n").unwrap();
+        assert_eq!(mode(&path), 0o640);
+
+        let new = dir.join("config.d/new");
+        FileSshRepository::write_atomically(&new, "Host db\n").unwrap();
+        assert_eq!(mode(&new), 0o600);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn new_host_goes_above_wildcard_defaults() {
+        let mut config = SshConfigParser::parse("Host web\n    HostName web.example\n\n# defaults\nHost *\n    User root\n");
+        config.add_host(&host("newhost", "n.example", "alice"));
+
+        assert_eq!(
+            config.render(),
+            "Host web\n    HostName web.example\n\n\
+             Host newhost\n    HostName n.example\n    User alice\n\n\
+             # defaults\nHost *\n    User root\n"
+        );
+    }
+
+    #[test]
+    fn new_host_goes_above_match_blocks() {
+        let mut config = SshConfigParser::parse("Match all\n    User root\n");
+        config.add_host(&host("newhost", "n.example", "alice"));
+
+        assert_eq!(
+            config.render(),
+            "Host newhost\n    HostName n.example\n    User alice\n\nMatch all\n    User root\n"
+        );
+    }
+
+    #[test]
+    fn new_host_is_appended_without_pattern_blocks() {
+        let mut config = SshConfigParser::parse("Host web\n    HostName web.example");
+        config.add_host(&host("newhost", "n.example", ""));
+
+        assert_eq!(
+            config.render(),
+            "Host web\n    HostName web.example\n\nHost newhost\n    HostName n.example\n"
+        );
+    }
+
+    #[test]
+    fn new_pattern_block_is_appended_after_existing_patterns() {
+        let mut config = SshConfigParser::parse("Host *.corp\n    User corp\n");
+        config.add_host(&host("*", "", "root"));
+
+        assert_eq!(config.render(), "Host *.corp\n    User corp\n\nHost *\n    User root\n");
+    }
+
+    /// A file with everything the parser has to carry through untouched.
+    const MESSY: &str = "# global\n\
+                         Include config.d/*\n\
+                         \n\
+                         # the web box\n\
+                         Host web web.example # short and long\n\
+                         \tHostName=web.example.com\n\
+                         \tUser = deploy\n\
+                         \t# keys\n\
+                         \tIdentityFile \"~/.ssh/id web\"   # quoted\n\
+                         \n\
+                         Host *\n\
+                         \x20 ServerAliveInterval\t60";
+
+    #[test]
+    fn parse_and_render_round_trip() {
+        let crlf = MESSY.replace('\n', "\r\n") + "\r\n";
+        for text in [MESSY.to_string(), format!("{}\n", MESSY), crlf, String::new(), "\n\n".to_string()] {
+            assert_eq!(SshConfigParser::parse(&text).render(), text);
+        }
+    }
+
+    #[test]
+    fn lines_added_to_a_crlf_file_end_in_crlf() {
+        let crlf = MESSY.replace('\n', "\r\n");
+        for text in [crlf.clone() + "\r\n", crlf] {
+            let mut config = SshConfigParser::parse(&text);
+            let mut web = config.hosts()[0].clone();
+            web.identity_files.push("~/.ssh/backup".to_string());
+            web.port = 2222;
+            config.update_host(0, &web);
+            config.add_host(&SshHost {
+                patterns: vec!["db".to_string()],
+                hostname: "db.example".to_string(),
+                ..SshHost::empty()
+            });
+
+            let rendered = config.render();
+            assert!(rendered.contains("\tIdentityFile=~/.ssh/backup\r\n\tPort=2222\r\n"), "{:?}", rendered);
+            assert!(rendered.contains("\r\nHost db\r\n    HostName db.example\r\n\r\nHost *\r\n"), "{:?}", rendered);
+            assert_eq!(rendered.matches('\n').count(), rendered.matches("\r\n").count(), "{:?}", rendered);
+            assert!(rendered.ends_with("\r\n"));
+        }
+
+        let mut config = SshConfigParser::parse("Host web\r\n    User deploy");
+        config.add_host(&SshHost { patterns: vec!["*".to_string()], user: "root".to_string(), ..SshHost::empty() });
+        assert_eq!(config.render(), "Host web\r\n    User deploy\r\n\r\nHost *\r\n    User root\r\n");
+
+        let mut config = SshConfigParser::parse("Host web\n    User deploy\n");
+        config.add_host(&SshHost { patterns: vec!["db".to_string()], ..SshHost::empty() });
+        assert!(!config.render().contains('\r'));
+    }
+
+    #[test]
+    fn parses_values_without_separators_or_comments() {
+        let hosts = SshConfigParser::parse(MESSY).hosts();
+        assert_eq!(hosts[0].patterns, vec!["web", "web.example"]);
+        assert_eq!(hosts[0].hostname, "web.example.com");
+        assert_eq!(hosts[0].user, "deploy");
+        assert_eq!(hosts[0].identity_files, vec!["\"~/.ssh/id web\""]);
+        assert_eq!(hosts[1].extra_options, vec![("ServerAliveInterval".to_string(), "60".to_string())]);
+
+        let crlf = SshConfigParser::parse(&MESSY.replace('\n', "\r\n")).hosts();
+        assert_eq!(crlf[0].user, "deploy");
+    }
+
+    #[test]
+    fn editing_a_host_rewrites_only_that_line() {
+        let crlf = MESSY.replace('\n', "\r\n") + "\r\n";
+        for text in [MESSY.to_string(), crlf] {
+            let mut config = SshConfigParser::parse(&text);
+            let mut web = config.hosts()[0].clone();
+            web.user = "admin".to_string();
+            config.update_host(0, &web);
+
+            assert_eq!(config.render(), text.replace("User = deploy", "User = admin"));
+        }
+    }
+
+    #[test]
+    fn editing_keeps_inline_comments_and_quotes() {
+        let mut config = SshConfigParser::parse(MESSY);
+        let mut web = config.hosts()[0].clone();
+        web.patterns = vec!["www".to_string()];
+        web.identity_files = vec!["\"~/.ssh/id www\"".to_string()];
+        config.update_host(0, &web);
+
+        assert_eq!(
+            config.render(),
+            MESSY
+                .replace("Host web web.example #", "Host www #")
+                .replace("\"~/.ssh/id web\"   # quoted", "\"~/.ssh/id www\"   # quoted")
+        );
+    }
+
+    #[test]
+    fn removing_a_host_keeps_its_neighbours() {
+        let mut config = SshConfigParser::parse(MESSY);
+        config.remove_host(1);
+        assert_eq!(config.render(), MESSY[..MESSY.find("\nHost *").unwrap()]);
+    }
+
+    const KEYS: &str = "Host web\n\
+                        \x20   IdentityFile ~/.ssh/a # first\n\
+                        \x20   # the deploy key\n\
+                        \x20   IdentityFile ~/.ssh/b\n\
+                        \x20   IdentityFile ~/.ssh/c # third\n\
+                        \x20   ForwardAgent yes # agent\n\
+                        \x20   Compression yes\n\
+                        \x20   ServerAliveInterval 60 # keepalive\n";
+
+    fn edit_web(edit: impl FnOnce(&mut SshHost)) -> String {
+        let mut config = SshConfigParser::parse(KEYS);
+        let mut web = config.hosts()[0].clone();
+        edit(&mut web);
+        config.update_host(0, &web);
+        config.render()
+    }
+
+    #[test]
+    fn deleting_a_middle_list_entry_removes_only_its_line() {
+        let out = edit_web(|web| {
+            web.identity_files.remove(1);
+        });
+        assert_eq!(out, KEYS.replace("    IdentityFile ~/.ssh/b\n", ""));
+    }
+
+    #[test]
+    fn deleting_a_middle_option_removes_only_its_line() {
+        let out = edit_web(|web| {
+            web.extra_options.remove(1);
+        });
+        assert_eq!(out, KEYS.replace("    Compression yes\n", ""));
+    }
+
+    #[test]
+    fn changing_a_list_entry_rewrites_only_its_line() {
+        let out = edit_web(|web| web.identity_files[1] = "~/.ssh/b2".to_string());
+        assert_eq!(out, KEYS.replace("~/.ssh/b\n", "~/.ssh/b2\n"));
+
+        let out = edit_web(|web| web.extra_options[0].1 = "no".to_string());
+        assert_eq!(out, KEYS.replace("ForwardAgent yes", "ForwardAgent no"));
+    }
+
+    #[test]
+    fn new_list_entries_go_next_to_their_neighbours() {
+        let out = edit_web(|web| web.identity_files.insert(1, "~/.ssh/new".to_string()));
+        assert_eq!(
+            out,
+            KEYS.replace("# first\n", "# first\n    IdentityFile ~/.ssh/new\n")
+        );
+
+        let out = edit_web(|web| {
+            web.identity_files.remove(0);
+            web.identity_files.push("~/.ssh/d".to_string());
+        });
+        assert_eq!(
+            out,
+            KEYS.replace("    IdentityFile ~/.ssh/a # first\n", "")
+                .replace("# third\n", "# third\n    IdentityFile ~/.ssh/d\n")
+        );
+    }
+
+    fn loaded_paths(repo: &FileSshRepository, set: &ConfigSet) -> Vec<String> {
+        set.files
+            .iter()
+            .map(|f| f.path.strip_prefix(&repo.home).unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn include_resolves_home_absolute_and_relative_paths() {
+        let repo = fixture_home(
+            "include-paths",
+            &[
+                ("extra/home.conf", "Host h\n"),
+                ("abs/abs.conf", "Host a\n"),
+                (".ssh/rel.conf", "Host r\n"),
+            ],
+        );
+        let absolute = repo.home.join("abs/abs.conf");
+        fs::write(
+            &repo.path,
+            format!("Include ~/extra/home.conf\nInclude {}\nInclude rel.conf\n", absolute.display()),
+        )
+        .unwrap();
+
+        let set = repo.load_all();
+        assert_eq!(
+            loaded_paths(&repo, &set),
+            vec![".ssh/config", "extra/home.conf", "abs/abs.conf", ".ssh/rel.conf"]
+        );
+        assert_eq!(set.files[0].includes, vec![(1, vec![1]), (2, vec![2]), (3, vec![3])]);
+        assert!(set.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn include_globs_load_in_sorted_order() {
+        let repo = fixture_home(
+            "include-glob",
+            &[
+                (".ssh/config", "Include config.d/*.conf\n"),
+                (".ssh/config.d/b.conf", "Host b\n"),
+                (".ssh/config.d/a.conf", "Host a\n"),
+                (".ssh/config.d/10.conf", "Host ten\n"),
+                (".ssh/config.d/.hidden.conf", "Host hidden\n"),
+                (".ssh/config.d/notes.txt", "Host notes\n"),
+            ],
+        );
+
+        let set = repo.load_all();
+        assert_eq!(
+            loaded_paths(&repo, &set),
+            vec![".ssh/config", ".ssh/config.d/10.conf", ".ssh/config.d/a.conf", ".ssh/config.d/b.conf"]
+        );
+        let aliases: Vec<String> = set.hosts().iter().map(|h| h.alias().to_string()).collect();
+        assert_eq!(aliases, vec!["ten", "a", "b"]);
+    }
+
+    #[test]
+    fn included_files_follow_the_file_that_includes_them() {
+        let repo = fixture_home(
+            "include-order",
+            &[
+                (".ssh/config", "Host top\nInclude first.conf\nInclude second.conf\nHost bottom\n"),
+                (".ssh/first.conf", "Host first\nInclude nested.conf\n"),
+                (".ssh/nested.conf", "Host nested\n"),
+                (".ssh/second.conf", "Host second\n"),
+            ],
+        );
+
+        let set = repo.load_all();
+        assert_eq!(
+            loaded_paths(&repo, &set),
+            vec![".ssh/config", ".ssh/first.conf", ".ssh/nested.conf", ".ssh/second.conf"]
+        );
+        assert_eq!(set.files[0].includes, vec![(2, vec![1]), (3, vec![3])]);
+        assert_eq!(set.files[1].includes, vec![(2, vec![2])]);
+    }
+
+    #[test]
+    fn include_cycles_and_deep_chains_stop() {
+        let repo = fixture_home(
+            "include-cycle",
+            &[
+                (".ssh/config", "Include loop.conf\n"),
+                (".ssh/loop.conf", "Include config\nInclude loop.conf\n"),
+            ],
+        );
+        let set = repo.load_all();
+        assert_eq!(loaded_paths(&repo, &set), vec![".ssh/config", ".ssh/loop.conf"]);
+
+        let chain: Vec<(String, String)> = (0..=MAX_INCLUDE_DEPTH + 2)
+            .map(|i| {
+                let name = if i == 0 { "config".to_string() } else { format!("d{}.conf", i) };
+                (format!(".ssh/{}", name), format!("Include d{}.conf\n", i + 1))
+            })
+            .collect();
+        let files: Vec<(&str, &str)> = chain.iter().map(|(p, c)| (p.as_str(), c.as_str())).collect();
+        let repo = fixture_home("include-depth", &files);
+
+        let set = repo.load_all();
+        assert_eq!(set.files.len(), MAX_INCLUDE_DEPTH + 1);
+        let deepest = set.files.last().unwrap();
+        assert_eq!(deepest.path, repo.home.join(format!(".ssh/d{}.conf", MAX_INCLUDE_DEPTH)));
+        assert!(deepest.load_diagnostics[0].message.contains("nested deeper than 16"));
+    }
+}
@@ -42,10 +42,10 @@ impl ThemeRepository for FileThemeRepository {
     }
 }
 
-struct ThemeCatalog;
+pub(crate) struct ThemeCatalog;
 
 impl ThemeCatalog {
-    fn all() -> Vec<Theme> {
+    pub(crate) fn all() -> Vec<Theme> {
         vec![
             Self::transparent(),
             Self::tokyo_night(),
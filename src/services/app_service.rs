@@ -1,17 +1,79 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
 use std::path::PathBuf;
 
-use crate::models::{Action, FormField, Mode, SshHost, Theme, ThemePreference};
-use crate::repositories::{SshRepository, ThemeRepository};
+use chrono::Local;
+
+use crate::models::inventory::{DiagnosticRecord, HostRecord, Inventory, OptionRecord, SourceRecord, SCHEMA_VERSION};
+use crate::models::{
+    Action, AuditFinding, BackupEntry, BannerRecord, ConfigFile, ConfigSet, Diagnostic, Directive, EffectiveConfig,
+    FileStamp, FormField, History, HistoryEntry, HostHistory, HostTrust, KeyGenField, KeyGenRequest, KnownHostsEdit, KeyUsage, KnownHostEntry, KnownHosts, KnownHostsIssue,
+    LaunchCommand, MatchRule, Mode, ProbeResult,
+    ProbeSettings, ProbeStatus, ProbeTarget, Remedy, SessionDiagnosis, Settings, SshFailure, Severity, SortMode, SshConfig, SshHost, Theme, ThemePreference,
+};
+use crate::repositories::{
+    BannerRepository, HistoryRepository, KeyRepository, KnownHostsRepository, SettingsRepository, SshRepository,
+    ThemeRepository,
+};
+use crate::services::{diff_lines, Auditor, ConfigResolver, DiffLine, Linter, Prober};
 
 pub struct AppService {
-    preamble: String,
+    config: ConfigSet,
     hosts: Vec<SshHost>,
+    match_rules: Vec<MatchRule>,
+    diagnostics: Vec<Diagnostic>,
     ssh_config_path: PathBuf,
+    /// An edit that was not written because the file changed on disk.
+    pending_save: Option<(usize, ConfigFile)>,
+    settings: Settings,
+    history: History,
+    /// Latest reachability result per host alias.
+    probes: HashMap<String, ProbeStatus>,
+    banners: HashMap<String, BannerRecord>,
+    /// Banner updates not yet written to disk.
+    banners_dirty: bool,
+    known_hosts: KnownHosts,
+    /// known_hosts verdict per concrete host alias.
+    trust: HashMap<String, HostTrust>,
+    known_hosts_issues: Vec<KnownHostsIssue>,
 
     pub mode: Mode,
     pub cursor: usize,
     pub form_draft: SshHost,
     pub form_field: FormField,
+    pub match_cursor: usize,
+    pub diagnostic_cursor: usize,
+    pub audit_findings: Vec<AuditFinding>,
+    pub audit_cursor: usize,
+
+    pub backups: Vec<BackupEntry>,
+    pub backup_cursor: usize,
+    /// Diff of the selected backup against the current file.
+    pub backup_diff: Vec<DiffLine>,
+
+    /// Entries of the known_hosts manager that pass the filter, as indices
+    /// into the loaded entries.
+    pub known_hosts_visible: Vec<usize>,
+    pub known_hosts_cursor: usize,
+    pub known_hosts_filter: String,
+    pub known_hosts_filtering: bool,
+
+    /// Keys on disk and in `IdentityFile` lines, for the keys view.
+    pub keys: Vec<KeyUsage>,
+    pub key_cursor: usize,
+
+    /// The key generation dialog, opened from the host form.
+    pub keygen: KeyGenRequest,
+    pub keygen_confirm: String,
+    pub keygen_field: KeyGenField,
+    /// The form to go back to, with the draft still in `form_draft`.
+    pub keygen_return: Mode,
+    /// Set once the key exists, while asking whether to use it.
+    pub keygen_created: Option<PathBuf>,
+
+    /// Why the last session failed, shown when control returns to the TUI.
+    pub session_diagnosis: Option<SessionDiagnosis>,
 
     pub theme: Theme,
     pub theme_preference: ThemePreference,
@@ -20,7 +82,13 @@ pub struct AppService {
 
     pub search_query: String,
     pub visible_indices: Vec<usize>,
+    pub sort_mode: SortMode,
+    /// `--pick` lists only connectable hosts and returns them on Enter.
+    pub picking: bool,
+    /// Hosts marked with Tab in the picker, in the order they were marked.
+    pub pick_marks: Vec<usize>,
     pub show_command: bool,
+    pub show_effective: bool,
     pub notification: Option<(String, bool)>,
     pub pending_action: Action,
 }
@@ -29,8 +97,12 @@ impl AppService {
     pub fn initialize(
         ssh_repo: &dyn SshRepository,
         theme_repo: &dyn ThemeRepository,
+        settings_repo: &dyn SettingsRepository,
     ) -> Self {
-        let (preamble, hosts) = ssh_repo.load_all();
+        let config = ssh_repo.load_all();
+        let hosts = config.hosts();
+        let match_rules = config.match_rules();
+        let diagnostics = Linter::diagnose(&config);
         let ssh_config_path = ssh_repo.config_path();
 
         let preference = theme_repo.load_preference();
@@ -40,17 +112,50 @@ impl AppService {
         let mut theme = available_themes[theme_index].clone();
         theme.transparent = preference.transparent;
 
-        let host_count = hosts.len();
-
-        Self {
-            preamble,
+        let mut app = Self {
+            config,
             hosts,
+            match_rules,
+            diagnostics,
             ssh_config_path,
+            pending_save: None,
+            settings: settings_repo.load(),
+            history: History::default(),
+            probes: HashMap::new(),
+            banners: HashMap::new(),
+            banners_dirty: false,
+            known_hosts: KnownHosts::default(),
+            trust: HashMap::new(),
+            known_hosts_issues: Vec::new(),
 
             mode: Mode::Normal,
             cursor: 0,
             form_draft: SshHost::empty(),
             form_field: FormField::Alias,
+            match_cursor: 0,
+            diagnostic_cursor: 0,
+            audit_findings: Vec::new(),
+            audit_cursor: 0,
+
+            backups: Vec::new(),
+            backup_cursor: 0,
+            backup_diff: Vec::new(),
+
+            known_hosts_visible: Vec::new(),
+            known_hosts_cursor: 0,
+            known_hosts_filter: String::new(),
+            known_hosts_filtering: false,
+
+            keys: Vec::new(),
+            key_cursor: 0,
+
+            keygen: KeyGenRequest::for_host(""),
+            keygen_confirm: String::new(),
+            keygen_field: KeyGenField::Algorithm,
+            keygen_return: Mode::Normal,
+            keygen_created: None,
+
+            session_diagnosis: None,
 
             theme,
             theme_preference: preference,
@@ -58,19 +163,48 @@ impl AppService {
             theme_cursor: theme_index,
 
             search_query: String::new(),
-            visible_indices: (0..host_count).collect(),
+            visible_indices: Vec::new(),
+            sort_mode: SortMode::Config,
+            picking: false,
+            pick_marks: Vec::new(),
             show_command: false,
+            show_effective: false,
             notification: None,
             pending_action: Action::Continue,
-        }
+        };
+
+        app.sort_mode = app.settings.sort;
+        app.rebuild_filter();
+        app
     }
 
     pub fn config_path_display(&self) -> String {
         self.ssh_config_path.to_string_lossy().to_string()
     }
 
+    pub fn config_file_count(&self) -> usize {
+        self.config.files.len()
+    }
+
+    /// A path with the home directory shortened to `~`.
+    pub fn path_display(&self, path: &std::path::Path) -> String {
+        match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(PathBuf::from)) {
+            Some(relative) => format!("~/{}", relative.to_string_lossy()),
+            None => path.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Path of a config file with the home directory shortened to `~`.
+    pub fn source_display(&self, file_index: usize) -> String {
+        self.config.path_of(file_index).map_or("?".into(), |path| self.path_display(path))
+    }
+
     pub fn host_count(&self) -> usize {
-        self.hosts.len()
+        self.hosts.iter().filter(|h| !h.is_pattern()).count()
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.hosts.iter().filter(|h| h.is_pattern()).count()
     }
 
     pub fn visible_hosts(&self) -> Vec<(usize, &SshHost)> {
@@ -92,6 +226,23 @@ impl AppService {
         self.hosts.get(index)
     }
 
+    pub fn hosts(&self) -> &[SshHost] {
+        &self.hosts
+    }
+
+    /// Index of the host declaring `name` on its `Host` line, preferring
+    /// a concrete alias over a pattern written the same way.
+    pub fn find_host(&self, name: &str) -> Option<usize> {
+        self.hosts
+            .iter()
+            .position(|h| h.connect_alias().is_some_and(|a| a.eq_ignore_ascii_case(name)))
+            .or_else(|| {
+                self.hosts
+                    .iter()
+                    .position(|h| h.patterns.iter().any(|p| p.eq_ignore_ascii_case(name)))
+            })
+    }
+
     // INFO: Navigation
 
     pub fn move_cursor_up(&mut self) {
@@ -145,282 +296,1838 @@ impl AppService {
         self.mode = Mode::Normal;
     }
 
+    /// Concrete hosts are listed first, in config or frecency order,
+    /// followed by the pattern blocks (`Host *`, `Host *.corp`, ...) in
+    /// their own section.
     fn rebuild_filter(&mut self) {
-        if self.search_query.is_empty() {
-            self.visible_indices = (0..self.hosts.len()).collect();
-        } else {
-            let query = self.search_query.to_lowercase();
-            self.visible_indices = self
-                .hosts
-                .iter()
-                .enumerate()
-                .filter(|(_, h)| {
-                    h.alias.to_lowercase().contains(&query)
-                        || h.hostname.to_lowercase().contains(&query)
-                        || h.user.to_lowercase().contains(&query)
-                })
-                .map(|(i, _)| i)
-                .collect();
+        let query = self.search_query.to_lowercase();
+        let (patterns, mut concrete): (Vec<usize>, Vec<usize>) = self
+            .hosts
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| !(self.picking && h.is_pattern()))
+            .filter(|(_, h)| {
+                query.is_empty()
+                    || h.patterns.iter().any(|p| p.to_lowercase().contains(&query))
+                    || h.hostname.to_lowercase().contains(&query)
+                    || h.user.to_lowercase().contains(&query)
+            })
+            .map(|(i, _)| i)
+            .partition(|&i| self.hosts[i].is_pattern());
+
+        if self.sort_mode == SortMode::Frecency {
+            let scores = self.history.frecency_scores(Local::now().naive_local());
+            concrete.sort_by_key(|&i| {
+                std::cmp::Reverse(scores.get(&self.hosts[i].alias().to_lowercase()).copied().unwrap_or(0))
+            });
         }
 
+        self.visible_indices = concrete;
+        self.visible_indices.extend(patterns);
+
         let count = self.visible_indices.len();
         if self.cursor >= count {
             self.cursor = count.saturating_sub(1);
         }
     }
 
-    // ─── CRUD via Repository ─────────────────────────────────────────────
+    // ─── History ─────────────────────────────────────────────────────────
 
-    pub fn begin_add(&mut self) {
-        self.form_draft = SshHost::empty();
-        self.form_field = FormField::Alias;
-        self.mode = Mode::AddHost;
+    pub fn load_history(&mut self, history_repo: &dyn HistoryRepository) {
+        self.history = history_repo.load();
+        self.rebuild_filter();
     }
 
-    pub fn begin_edit(&mut self) {
-        if let Some(index) = self.selected_real_index() {
-            self.form_draft = self.hosts[index].clone();
-            self.form_field = FormField::Alias;
-            self.mode = Mode::EditHost(index);
+    pub fn host_history(&self, host: &SshHost) -> Option<HostHistory<'_>> {
+        self.history.for_host(host.alias())
+    }
+
+    /// Stores a finished session; the list is re-sorted when ordered by use.
+    pub fn record_session(&mut self, entry: HistoryEntry, history_repo: &dyn HistoryRepository) {
+        if let Err(e) = history_repo.append(&entry) {
+            self.notification = Some((e, true));
         }
+        self.history.entries.push(entry);
+        self.rebuild_filter();
     }
 
-    pub fn begin_delete(&mut self) {
-        if let Some(index) = self.selected_real_index() {
-            self.mode = Mode::ConfirmDelete(index);
+    /// Switches between config order and frecency, keeping the selection.
+    pub fn toggle_sort(&mut self) {
+        let selected = self.selected_real_index();
+        self.sort_mode = self.sort_mode.toggled();
+        self.rebuild_filter();
+        if let Some(position) = selected.and_then(|s| self.visible_indices.iter().position(|&i| i == s)) {
+            self.cursor = position;
         }
+        self.notification = Some((format!("Sorted by {}", self.sort_mode.label()), false));
     }
 
-    pub fn commit_add(&mut self, ssh_repo: &dyn SshRepository) {
-        if !self.form_draft.is_valid() {
-            self.notification = Some(("Alias and HostName are required".into(), true));
-            return;
+    // ─── Reachability ────────────────────────────────────────────────────
+
+    pub fn probe_settings(&self) -> &ProbeSettings {
+        &self.settings.probe
+    }
+
+    pub fn probe_status(&self, host: &SshHost) -> Option<&ProbeStatus> {
+        self.probes.get(host.alias())
+    }
+
+    /// Probes every concrete host at startup when `probe.on_start` is set.
+    pub fn start_probes(&mut self, prober: &Prober) {
+        if self.settings.probe.on_start {
+            self.probe_all(prober);
         }
+    }
 
-        let alias_exists = self.hosts.iter().any(|h| {
-            h.alias.to_lowercase() == self.form_draft.alias.to_lowercase()
-        });
-        if alias_exists {
-            self.notification = Some((
-                format!("'{}' already exists", self.form_draft.alias),
-                true,
-            ));
-            return;
+    /// Queues a TCP check of every concrete host. Hosts reached through
+    /// `ProxyJump` or `ProxyCommand` are marked instead of probed, since a
+    /// direct connect says nothing about them.
+    pub fn probe_all(&mut self, prober: &Prober) {
+        let mut targets = Vec::new();
+        for host in self.hosts.iter().filter(|h| !h.is_pattern()) {
+            let alias = host.alias().to_string();
+            let effective = ConfigResolver::resolve(&self.config, &alias);
+
+            let proxy = ["ProxyJump", "ProxyCommand"]
+                .into_iter()
+                .find(|k| effective.value_of(k).is_some_and(|v| !v.eq_ignore_ascii_case("none")));
+            if let Some(keyword) = proxy {
+                self.probes.insert(alias, ProbeStatus::Skipped(keyword.into()));
+                continue;
+            }
+
+            let hostname = effective.value_of("HostName").unwrap_or(&alias).replace("%h", &alias);
+            let port = effective.value_of("Port").and_then(|p| p.parse().ok()).unwrap_or(22);
+            self.probes.insert(alias.clone(), ProbeStatus::Pending);
+            targets.push(ProbeTarget {
+                alias,
+                host: hostname,
+                port,
+            });
         }
 
-        let name = self.form_draft.alias.clone();
-        self.hosts.push(self.form_draft.clone());
+        self.notification = Some((format!("Checking {} hosts…", targets.len()), false));
+        prober.submit(targets);
+    }
 
-        match ssh_repo.save_all(&self.preamble, &self.hosts) {
-            Ok(_) => {
-                self.notification = Some((format!("Added '{}'", name), false));
-            }
-            Err(e) => {
-                self.hosts.pop();
-                self.notification = Some((e, true));
+    /// Stores a probe result. Banners are written later by `flush_banners`
+    /// so a full round of probes costs one write, not one per host.
+    pub fn apply_probe(&mut self, result: ProbeResult) {
+        if let Some(banner) = &result.banner {
+            let now = Local::now().naive_local();
+            match self.banners.get_mut(&result.alias) {
+                Some(record) => record.observe(banner, now),
+                None => {
+                    self.banners.insert(result.alias.clone(), BannerRecord::new(banner, now));
+                }
             }
+            self.banners_dirty = true;
         }
+        self.probes.insert(result.alias, result.status);
+    }
 
-        self.rebuild_filter();
-        self.mode = Mode::Normal;
+    pub fn load_banners(&mut self, banner_repo: &dyn BannerRepository) {
+        self.banners = banner_repo.load();
     }
 
-    pub fn commit_edit(&mut self, index: usize, ssh_repo: &dyn SshRepository) {
-        if !self.form_draft.is_valid() {
-            self.notification = Some(("Alias and HostName are required".into(), true));
+    pub fn flush_banners(&mut self, banner_repo: &dyn BannerRepository) {
+        if !self.banners_dirty {
             return;
         }
-
-        let duplicate = self.hosts.iter().enumerate().any(|(i, h)| {
-            i != index && h.alias.to_lowercase() == self.form_draft.alias.to_lowercase()
-        });
-        if duplicate {
-            self.notification = Some((
-                format!("'{}' already exists", self.form_draft.alias),
-                true,
-            ));
-            return;
+        self.banners_dirty = false;
+        if let Err(e) = banner_repo.save(&self.banners) {
+            self.notification = Some((e, true));
         }
+    }
 
-        let name = self.form_draft.alias.clone();
-        let backup = self.hosts[index].clone();
-        self.hosts[index] = self.form_draft.clone();
+    pub fn host_banner(&self, host: &SshHost) -> Option<&BannerRecord> {
+        self.banners.get(host.alias())
+    }
 
-        match ssh_repo.save_all(&self.preamble, &self.hosts) {
-            Ok(_) => {
-                self.notification = Some((format!("Updated '{}'", name), false));
+    /// Clears the changed-banner flag of the selected host.
+    pub fn accept_banner(&mut self, banner_repo: &dyn BannerRepository) {
+        let Some(alias) = self.selected_host().map(|h| h.alias().to_string()) else {
+            return;
+        };
+        match self.banners.get_mut(&alias) {
+            Some(record) if record.is_changed() => {
+                record.accept();
+                self.banners_dirty = true;
+                self.flush_banners(banner_repo);
+                self.notification = Some((format!("Accepted the new banner of '{}'", alias), false));
             }
-            Err(e) => {
-                self.hosts[index] = backup;
-                self.notification = Some((e, true));
+            _ => {
+                self.notification = Some((format!("The banner of '{}' has not changed", alias), false));
             }
         }
+    }
 
-        self.rebuild_filter();
-        self.mode = Mode::Normal;
+    // ─── Known hosts ─────────────────────────────────────────────────────
+
+    pub fn load_known_hosts(&mut self, known_hosts_repo: &dyn KnownHostsRepository) {
+        self.known_hosts = known_hosts_repo.load();
+        self.compute_trust();
+        self.refresh_known_hosts_view();
     }
 
-    pub fn commit_delete(&mut self, index: usize, ssh_repo: &dyn SshRepository) {
-        let removed = self.hosts.remove(index);
+    /// Looks every concrete host up the way ssh does: by `HostKeyAlias` if
+    /// set, otherwise by `HostName`, with a non-default `Port` as `[name]:port`.
+    fn compute_trust(&mut self) {
+        self.trust = self
+            .hosts
+            .iter()
+            .filter(|h| !h.is_pattern())
+            .map(|host| {
+                let alias = host.alias();
+                let effective = ConfigResolver::resolve(&self.config, alias);
+                let name = effective
+                    .value_of("HostKeyAlias")
+                    .or(effective.value_of("HostName"))
+                    .unwrap_or(alias)
+                    .replace("%h", alias);
+                let port = effective.value_of("Port").and_then(|p| p.parse().ok()).unwrap_or(22);
+                (alias.to_string(), self.known_hosts.trust_for(&name, port))
+            })
+            .collect();
+    }
 
-        match ssh_repo.save_all(&self.preamble, &self.hosts) {
-            Ok(_) => {
-                self.notification = Some((
-                    format!("Deleted '{}'", removed.alias),
-                    false,
-                ));
-            }
-            Err(e) => {
-                self.hosts.insert(index, removed);
-                self.notification = Some((e, true));
-            }
+    pub fn host_trust(&self, host: &SshHost) -> Option<&HostTrust> {
+        self.trust.get(host.alias())
+    }
+
+    /// `file:line` of a known_hosts entry, home shortened to `~`.
+    pub fn known_hosts_source(&self, file_index: usize, line: usize) -> String {
+        match self.known_hosts.files.get(file_index) {
+            Some(file) => format!("{}:{}", self.path_display(&file.path), line),
+            None => "?".into(),
         }
+    }
 
-        self.rebuild_filter();
-        let count = self.visible_hosts().len();
-        if self.cursor >= count && count > 0 {
-            self.cursor = count - 1;
+    pub fn open_known_hosts(&mut self) {
+        self.known_hosts_cursor = 0;
+        self.known_hosts_filter.clear();
+        self.known_hosts_filtering = false;
+        self.refresh_known_hosts_view();
+        self.mode = Mode::KnownHosts;
+    }
+
+    pub fn known_host_entry(&self, index: usize) -> Option<&KnownHostEntry> {
+        self.known_hosts.entries.get(index)
+    }
+
+    /// Lookup names of the configured hosts, used to put a name on hashed
+    /// entries.
+    fn known_hosts_candidates(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.trust.values().map(|t| t.lookup_name.clone()).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// What the manager shows in the host column: the patterns as written,
+    /// or for hashed lines the configured host they belong to, if any.
+    pub fn known_host_names(&self, entry: &KnownHostEntry) -> String {
+        let names = entry.names(&self.known_hosts_candidates());
+        match (entry.is_hashed(), names.is_empty()) {
+            (true, true) => "(hashed)".into(),
+            (true, false) => format!("(hashed) {}", names.join(",")),
+            (false, _) => entry.hosts_display(),
         }
-        self.mode = Mode::Normal;
     }
 
-    pub fn cancel_mode(&mut self) {
-        self.mode = Mode::Normal;
+    pub fn known_hosts_issues(&self) -> &[KnownHostsIssue] {
+        &self.known_hosts_issues
     }
 
-    // ─── SSH Execution ───────────────────────────────────────────────────
+    pub fn known_hosts_issue_of(&self, index: usize) -> Option<&KnownHostsIssue> {
+        self.known_hosts_issues.iter().find(|i| i.entries.contains(&index))
+    }
 
-    pub fn launch_ssh(&mut self) {
-        if let Some(host) = self.selected_host() {
-            self.pending_action = Action::LaunchSsh(host.as_ssh_args());
+    pub fn known_hosts_issue_display(&self, issue: &KnownHostsIssue) -> String {
+        issue.describe(&self.known_hosts)
+    }
+
+    /// The host selected in the main list and the known_hosts lines `R`
+    /// would remove for it: unmarked entries in files lazyssh may write.
+    pub fn known_hosts_of_selected_host(&self) -> Option<(&str, &HostTrust, Vec<usize>)> {
+        self.known_hosts_of(self.selected_host()?)
+    }
+
+    fn known_hosts_of<'a>(&'a self, host: &'a SshHost) -> Option<(&'a str, &'a HostTrust, Vec<usize>)> {
+        let trust = self.trust.get(host.alias())?;
+        let entries = self
+            .known_hosts
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| {
+                e.marker.is_none()
+                    && e.matches_name(&trust.lookup_name)
+                    && self.known_hosts.files.get(e.file_index).is_some_and(|f| !f.system)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        Some((host.alias(), trust, entries))
+    }
+
+    fn refresh_known_hosts_view(&mut self) {
+        let candidates = self.known_hosts_candidates();
+        self.known_hosts_issues = self.known_hosts.issues(&candidates);
+
+        let filter = self.known_hosts_filter.to_lowercase();
+        self.known_hosts_visible = (0..self.known_hosts.entries.len())
+            .filter(|&i| {
+                let entry = &self.known_hosts.entries[i];
+                filter.is_empty()
+                    || entry.matches_name(&filter)
+                    || self.known_host_names(entry).to_lowercase().contains(&filter)
+                    || entry.key.key_type.to_lowercase().contains(&filter)
+                    || entry.key.fingerprint().to_lowercase().contains(&filter)
+            })
+            .collect();
+        self.known_hosts_cursor = self
+            .known_hosts_cursor
+            .min(self.known_hosts_visible.len().saturating_sub(1));
+    }
+
+    pub fn known_hosts_cursor_up(&mut self) {
+        self.known_hosts_cursor = self.known_hosts_cursor.saturating_sub(1);
+    }
+
+    pub fn known_hosts_cursor_down(&mut self) {
+        if self.known_hosts_cursor + 1 < self.known_hosts_visible.len() {
+            self.known_hosts_cursor += 1;
         }
     }
 
-    // ─── Form Editing ────────────────────────────────────────────────────
+    pub fn known_hosts_filter_type(&mut self, c: char) {
+        self.known_hosts_filter.push(c);
+        self.known_hosts_cursor = 0;
+        self.refresh_known_hosts_view();
+    }
 
-    pub fn form_next_field(&mut self) {
-        self.form_field = self.form_field.next();
+    pub fn known_hosts_filter_backspace(&mut self) {
+        self.known_hosts_filter.pop();
+        self.refresh_known_hosts_view();
     }
 
-    pub fn form_previous_field(&mut self) {
-        self.form_field = self.form_field.previous();
+    pub fn cancel_known_hosts_filter(&mut self) {
+        self.known_hosts_filter.clear();
+        self.known_hosts_filtering = false;
+        self.refresh_known_hosts_view();
     }
 
-    pub fn form_type_char(&mut self, c: char) {
-        if !self.form_field.accepts_char(c) {
+    pub fn delete_selected_known_host(&mut self, known_hosts_repo: &dyn KnownHostsRepository) {
+        let Some(entry) = self
+            .known_hosts_visible
+            .get(self.known_hosts_cursor)
+            .and_then(|&i| self.known_hosts.entries.get(i))
+        else {
             return;
+        };
+        let (file_index, line) = (entry.file_index, entry.line);
+        let what = format!("Removed {}", self.known_hosts_source(file_index, line));
+        self.remove_known_hosts_lines(&[(file_index, line)], &what, known_hosts_repo);
+    }
+
+    /// Removes every key on file for the selected host, like
+    /// `ssh-keygen -R`, so the next connect asks about the new key.
+    pub fn remove_known_hosts_of_selected_host(&mut self, known_hosts_repo: &dyn KnownHostsRepository) {
+        if let Some(index) = self.selected_real_index() {
+            self.remove_known_hosts_of_host(index, known_hosts_repo);
         }
-        let mut value = self.read_form_field();
-        value.push(c);
-        self.write_form_field(value);
     }
 
-    pub fn form_delete_char(&mut self) {
-        let mut value = self.read_form_field();
-        value.pop();
-        self.write_form_field(value);
+    fn remove_known_hosts_of_host(&mut self, index: usize, known_hosts_repo: &dyn KnownHostsRepository) {
+        let Some((alias, trust, entries)) = self.hosts.get(index).and_then(|h| self.known_hosts_of(h)) else {
+            return;
+        };
+        if entries.is_empty() {
+            self.notification = Some((
+                format!("No known_hosts entries for '{}' ({})", alias, trust.lookup_name),
+                false,
+            ));
+            return;
+        }
+
+        let what = format!("Removed {} entries for '{}'", entries.len(), alias);
+        let lines: Vec<(usize, usize)> = entries
+            .iter()
+            .map(|&i| (self.known_hosts.entries[i].file_index, self.known_hosts.entries[i].line))
+            .collect();
+        self.remove_known_hosts_lines(&lines, &what, known_hosts_repo);
     }
 
-    fn read_form_field(&self) -> String {
-        match self.form_field {
-            FormField::Alias => self.form_draft.alias.clone(),
-            FormField::HostName => self.form_draft.hostname.clone(),
-            FormField::Port => self.form_draft.port.to_string(),
-            FormField::User => self.form_draft.user.clone(),
-            FormField::IdentityFile => self.form_draft.identity_file.clone(),
+    fn remove_known_hosts_lines(
+        &mut self,
+        lines: &[(usize, usize)],
+        what: &str,
+        known_hosts_repo: &dyn KnownHostsRepository,
+    ) {
+        let mut files: Vec<usize> = lines.iter().map(|(f, _)| *f).collect();
+        files.sort_unstable();
+        files.dedup();
+
+        let mut backups = Vec::new();
+        for file_index in files {
+            let numbers: Vec<usize> = lines.iter().filter(|(f, _)| *f == file_index).map(|(_, l)| *l).collect();
+            let content = self.known_hosts.text_without(file_index, &numbers);
+            match self.save_known_hosts_file(file_index, &content, known_hosts_repo) {
+                Ok(backup) => backups.push(backup),
+                Err(e) => {
+                    self.notification = Some((e, true));
+                    self.load_known_hosts(known_hosts_repo);
+                    return;
+                }
+            }
         }
+
+        self.load_known_hosts(known_hosts_repo);
+        self.notification = Some((format!("{}; backup: {}", what, backups.join(", ")), false));
     }
 
-    fn write_form_field(&mut self, value: String) {
-        match self.form_field {
-            FormField::Alias => self.form_draft.alias = value,
-            FormField::HostName => self.form_draft.hostname = value,
-            FormField::Port => self.form_draft.port = value.parse().unwrap_or(22),
-            FormField::User => self.form_draft.user = value,
-            FormField::IdentityFile => self.form_draft.identity_file = value,
+    /// Hashes the host names of every user known_hosts file, like
+    /// `ssh-keygen -H`.
+    pub fn hash_known_hosts(&mut self, known_hosts_repo: &dyn KnownHostsRepository) {
+        let mut total = 0;
+        for file_index in self.user_known_hosts_files() {
+            let salts = match self.known_hosts_salts(file_index) {
+                Ok(salts) => salts,
+                Err(e) => {
+                    self.notification = Some((format!("Cannot read random salt: {}", e), true));
+                    self.load_known_hosts(known_hosts_repo);
+                    return;
+                }
+            };
+            let mut salts = salts.into_iter();
+            let (content, hashed) = self
+                .known_hosts
+                .hashed_text(file_index, || salts.next().unwrap_or_default());
+            if hashed == 0 {
+                continue;
+            }
+            if let Err(e) = self.save_known_hosts_file(file_index, &content, known_hosts_repo) {
+                self.notification = Some((e, true));
+                self.load_known_hosts(known_hosts_repo);
+                return;
+            }
+            total += hashed;
         }
+
+        self.load_known_hosts(known_hosts_repo);
+        let message = match total {
+            0 => "Nothing to hash: every entry is hashed, marked or a wildcard".to_string(),
+            n => format!("Hashed {} known_hosts lines (backups kept as *.old_*)", n),
+        };
+        self.notification = Some((message, false));
     }
 
-    // ─── Theme ───────────────────────────────────────────────────────────
+    fn user_known_hosts_files(&self) -> Vec<usize> {
+        (0..self.known_hosts.files.len())
+            .filter(|&i| !self.known_hosts.files[i].system)
+            .collect()
+    }
 
-    pub fn open_theme_selector(&mut self) {
-        self.theme_cursor = self.theme_preference.theme_index;
-        self.mode = Mode::SelectTheme;
+    /// Lines `H` would hash across the user files.
+    fn hashable_known_hosts_lines(&self) -> usize {
+        self.user_known_hosts_files()
+            .into_iter()
+            .map(|i| self.known_hosts.hashed_text(i, Vec::new).1)
+            .sum()
     }
 
-    pub fn theme_cursor_up(&mut self) {
-        if self.theme_cursor > 0 {
-            self.theme_cursor -= 1;
+    /// One fresh 20-byte salt per name `hashed_text` will hash, read up
+    /// front so a failing read never falls back to a predictable salt.
+    fn known_hosts_salts(&self, file_index: usize) -> std::io::Result<Vec<Vec<u8>>> {
+        let mut needed = 0;
+        self.known_hosts.hashed_text(file_index, || {
+            needed += 1;
+            Vec::new()
+        });
+        if needed == 0 {
+            return Ok(Vec::new());
         }
+
+        let mut bytes = vec![0; needed * 20];
+        File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+        Ok(bytes.chunks(20).map(<[u8]>::to_vec).collect())
     }
 
-    pub fn theme_cursor_down(&mut self) {
-        if self.theme_cursor < self.available_themes.len() - 1 {
-            self.theme_cursor += 1;
+    // Each change asks first, like deleting a host does.
+
+    /// Opens the confirmation for `edit`, or explains why there is
+    /// nothing to do.
+    pub fn begin_known_hosts_edit(&mut self, edit: KnownHostsEdit) {
+        let nothing = match edit {
+            KnownHostsEdit::DeleteEntry => self
+                .known_hosts_visible
+                .get(self.known_hosts_cursor)
+                .is_none()
+                .then(|| "No entry selected".to_string()),
+            KnownHostsEdit::RemoveHost => match self.selected_host().and_then(|h| self.known_hosts_of(h)) {
+                None => Some("Select a host in the host list first".to_string()),
+                Some((alias, trust, entries)) if entries.is_empty() => Some(format!(
+                    "No known_hosts entries for '{}' ({})",
+                    alias, trust.lookup_name
+                )),
+                Some(_) => None,
+            },
+            KnownHostsEdit::HashNames => (self.hashable_known_hosts_lines() == 0)
+                .then(|| "Nothing to hash: every entry is hashed, marked or a wildcard".to_string()),
+        };
+
+        match nothing {
+            Some(message) => self.notification = Some((message, false)),
+            None => self.mode = Mode::ConfirmKnownHostsEdit(edit),
         }
     }
 
-    pub fn apply_selected_theme(&mut self, theme_repo: &dyn ThemeRepository) {
-        let index = self.theme_cursor;
-        if let Some(new_theme) = self.available_themes.get(index) {
-            let mut theme = new_theme.clone();
-            if index == 0 {
-                theme.transparent = true;
-            } else {
-                theme.transparent = self.theme_preference.transparent;
+    /// The question shown before `edit` is applied.
+    pub fn known_hosts_edit_prompt(&self, edit: KnownHostsEdit) -> String {
+        match edit {
+            KnownHostsEdit::DeleteEntry => {
+                let entry = self
+                    .known_hosts_visible
+                    .get(self.known_hosts_cursor)
+                    .and_then(|&i| self.known_hosts.entries.get(i));
+                match entry {
+                    Some(e) => format!(
+                        "Remove {} ({}) from {}?",
+                        self.known_host_names(e),
+                        e.key.key_type,
+                        self.known_hosts_source(e.file_index, e.line)
+                    ),
+                    None => "Remove the selected entry?".to_string(),
+                }
             }
+            KnownHostsEdit::RemoveHost => match self.selected_host().and_then(|h| self.known_hosts_of(h)) {
+                Some((alias, _, entries)) => {
+                    format!("Remove the {} known_hosts entries of '{}'?", entries.len(), alias)
+                }
+                None => "Remove the entries of the selected host?".to_string(),
+            },
+            KnownHostsEdit::HashNames => format!(
+                "Hash the host names on {} known_hosts lines? They can no longer be read back.",
+                self.hashable_known_hosts_lines()
+            ),
+        }
+    }
 
-            self.theme = theme;
-            self.theme_preference.theme_index = index;
-            self.theme_preference.transparent = self.theme.transparent;
-            theme_repo.save_preference(&self.theme_preference);
+    pub fn apply_known_hosts_edit(&mut self, edit: KnownHostsEdit, known_hosts_repo: &dyn KnownHostsRepository) {
+        self.mode = Mode::KnownHosts;
+        match edit {
+            KnownHostsEdit::DeleteEntry => self.delete_selected_known_host(known_hosts_repo),
+            KnownHostsEdit::RemoveHost => self.remove_known_hosts_of_selected_host(known_hosts_repo),
+            KnownHostsEdit::HashNames => self.hash_known_hosts(known_hosts_repo),
+        }
+    }
 
-            self.notification = Some((format!("Theme: {}", new_theme.name), false));
+    pub fn cancel_known_hosts_edit(&mut self) {
+        self.mode = Mode::KnownHosts;
+    }
+
+    /// Writes one file, refusing when it changed since it was loaded so a
+    /// key ssh just added is never dropped.
+    fn save_known_hosts_file(
+        &self,
+        file_index: usize,
+        content: &str,
+        known_hosts_repo: &dyn KnownHostsRepository,
+    ) -> Result<String, String> {
+        let file = &self.known_hosts.files[file_index];
+        if file.system {
+            return Err(format!("{} is system-wide; edit it as root", file.path.display()));
         }
-        self.mode = Mode::Normal;
+
+        let on_disk = known_hosts_repo.load();
+        let unchanged = on_disk
+            .files
+            .iter()
+            .find(|f| f.path == file.path)
+            .is_some_and(|f| f.lines == file.lines);
+        if !unchanged {
+            return Err(format!("{} changed on disk; reloaded, try again", file.path.display()));
+        }
+
+        let backup = known_hosts_repo.save(&file.path, content)?;
+        Ok(backup.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default())
     }
 
-    pub fn toggle_transparency(&mut self, theme_repo: &dyn ThemeRepository) {
-        self.theme.transparent = !self.theme.transparent;
-        self.theme_preference.transparent = self.theme.transparent;
-        theme_repo.save_preference(&self.theme_preference);
+    // ─── Keys ────────────────────────────────────────────────────────────
+
+    /// Collects the keys in `~/.ssh` and every `IdentityFile` of every
+    /// block, so keys outside `~/.ssh` and missing ones are listed too.
+    pub fn open_keys(&mut self, key_repo: &dyn KeyRepository) {
+        let mut keys: Vec<KeyUsage> = key_repo
+            .scan()
+            .into_iter()
+            .map(|key| KeyUsage { key, hosts: Vec::new() })
+            .collect();
+
+        for host in &self.hosts {
+            for identity in &host.identity_files {
+                let key = key_repo.inspect(&host.expand_host_tokens(identity));
+                let alias = host.alias().to_string();
+                match keys.iter_mut().find(|k| same_file(&k.key.path, &key.path)) {
+                    Some(usage) if usage.hosts.contains(&alias) => {}
+                    Some(usage) => usage.hosts.push(alias),
+                    None => keys.push(KeyUsage { key, hosts: vec![alias] }),
+                }
+            }
+        }
 
-        let label = if self.theme.transparent { "ON" } else { "OFF" };
-        self.notification = Some((format!("Transparency: {}", label), false));
+        self.keys = keys;
+        self.key_cursor = 0;
+        self.mode = Mode::Keys;
     }
 
-    // ─── Misc ────────────────────────────────────────────────────────────
+    pub fn key_cursor_up(&mut self) {
+        self.key_cursor = self.key_cursor.saturating_sub(1);
+    }
 
-    pub fn toggle_command_preview(&mut self) {
-        self.show_command = !self.show_command;
+    pub fn key_cursor_down(&mut self) {
+        if self.key_cursor + 1 < self.keys.len() {
+            self.key_cursor += 1;
+        }
     }
 
-    pub fn reload_from_disk(&mut self, ssh_repo: &dyn SshRepository) {
-        let (preamble, hosts) = ssh_repo.load_all();
-        self.preamble = preamble;
-        self.hosts = hosts;
-        self.rebuild_filter();
-        self.notification = Some((
-            format!("Reloaded ({} hosts)", self.hosts.len()),
-            false,
-        ));
+    // ─── Key generation ──────────────────────────────────────────────────
+
+    /// Opens the key dialog on top of the host form, with a file name and
+    /// comment taken from the alias being edited.
+    pub fn begin_keygen(&mut self) {
+        if !matches!(self.mode, Mode::AddHost | Mode::EditHost(_)) {
+            return;
+        }
+        self.keygen = KeyGenRequest::for_host(self.form_draft.alias());
+        self.keygen_confirm.clear();
+        self.keygen_field = KeyGenField::Algorithm;
+        self.keygen_created = None;
+        self.keygen_return = self.mode.clone();
+        self.mode = Mode::GenerateKey;
     }
 
-    pub fn open_help(&mut self) {
-        self.mode = Mode::Help;
+    pub fn keygen_next_field(&mut self) {
+        self.keygen_field = self.keygen_field.next();
     }
 
-    pub fn request_quit(&mut self) {
-        self.pending_action = Action::Quit;
+    pub fn keygen_previous_field(&mut self) {
+        self.keygen_field = self.keygen_field.previous();
     }
 
-    pub fn clear_notification(&mut self) {
-        self.notification = None;
+    pub fn keygen_cycle_algorithm(&mut self, forward: bool) {
+        if self.keygen_field != KeyGenField::Algorithm {
+            return;
+        }
+        self.update_keygen(|request| {
+            request.algorithm = if forward {
+                request.algorithm.next()
+            } else {
+                request.algorithm.previous()
+            };
+        });
     }
 
-    pub fn take_action(&mut self) -> Action {
-        let action = self.pending_action.clone();
-        self.pending_action = Action::Continue;
-        action
+    pub fn keygen_type_char(&mut self, c: char) {
+        self.edit_keygen_field(|value| value.push(c));
+    }
+
+    pub fn keygen_delete_char(&mut self) {
+        self.edit_keygen_field(|value| {
+            value.pop();
+        });
+    }
+
+    fn edit_keygen_field(&mut self, edit: impl FnOnce(&mut String)) {
+        match self.keygen_field {
+            KeyGenField::Algorithm => {}
+            KeyGenField::FileName => edit(&mut self.keygen.file_name),
+            KeyGenField::Comment => self.update_keygen(|request| edit(&mut request.comment)),
+            KeyGenField::Passphrase => edit(&mut self.keygen.passphrase),
+            KeyGenField::ConfirmPassphrase => edit(&mut self.keygen_confirm),
+        }
+    }
+
+    /// Applies a change and keeps the file name in step with it, unless
+    /// the user has typed a name of their own.
+    fn update_keygen(&mut self, change: impl FnOnce(&mut KeyGenRequest)) {
+        let suggested = self.keygen.file_name == self.keygen.suggested_file_name();
+        change(&mut self.keygen);
+        if suggested {
+            self.keygen.file_name = self.keygen.suggested_file_name();
+        }
+    }
+
+    pub fn commit_keygen(&mut self, key_repo: &dyn KeyRepository) {
+        if self.keygen.passphrase != self.keygen_confirm {
+            self.keygen_field = KeyGenField::ConfirmPassphrase;
+            self.notification = Some(("Passphrases do not match".into(), true));
+            return;
+        }
+
+        match key_repo.generate(&self.keygen) {
+            Ok(path) => {
+                self.keygen.passphrase.clear();
+                self.keygen_confirm.clear();
+                self.keygen_created = Some(path);
+                self.notification = None;
+            }
+            Err(e) => self.notification = Some((e, true)),
+        }
+    }
+
+    /// Returns to the host form, adding the new key to its `IdentityFile`
+    /// list when `attach` is set. The host is saved with the form as usual.
+    pub fn finish_keygen(&mut self, attach: bool) {
+        let Some(path) = self.keygen_created.take() else {
+            return;
+        };
+        let identity = self.path_display(&path);
+
+        if attach {
+            if !self.form_draft.identity_files.contains(&identity) {
+                self.form_draft.identity_files.push(identity.clone());
+            }
+            self.form_field = FormField::IdentityFile;
+            self.notification = Some((format!("Created {}; save the host to use it", identity), false));
+        } else {
+            self.notification = Some((format!("Created {}", identity), false));
+        }
+        self.mode = self.keygen_return.clone();
+    }
+
+    pub fn cancel_keygen(&mut self) {
+        self.keygen.passphrase.clear();
+        self.keygen_confirm.clear();
+        self.mode = self.keygen_return.clone();
+    }
+
+    // ─── Session failures ────────────────────────────────────────────────
+
+    /// Opens the diagnosis popup when ssh itself failed in a recognisable way.
+    pub fn diagnose_session(&mut self, alias: &str, exit_code: Option<i32>, stderr: &str) {
+        if let Some(diagnosis) = SessionDiagnosis::from_session(alias, exit_code, stderr) {
+            self.session_diagnosis = Some(diagnosis);
+            self.mode = Mode::SessionFailure;
+        }
+    }
+
+    pub fn apply_remedy(
+        &mut self,
+        key: char,
+        ssh_repo: &dyn SshRepository,
+        known_hosts_repo: &dyn KnownHostsRepository,
+        prober: &Prober,
+    ) {
+        let Some(diagnosis) = self.session_diagnosis.take() else {
+            return;
+        };
+        let Some(&remedy) = diagnosis.failure.remedies().iter().find(|r| r.key() == key) else {
+            self.session_diagnosis = Some(diagnosis);
+            return;
+        };
+        let Some(index) = self.find_host(&diagnosis.alias) else {
+            self.mode = Mode::Normal;
+            self.notification = Some((format!("'{}' is no longer in the config", diagnosis.alias), true));
+            return;
+        };
+
+        self.mode = Mode::Normal;
+        self.select_block(self.hosts[index].file_index, self.hosts[index].block_index);
+        match remedy {
+            Remedy::RemoveKnownHost => self.remove_offending_keys(&diagnosis, index, known_hosts_repo),
+            Remedy::IdentitiesOnly => self.set_identities_only(index, ssh_repo),
+            Remedy::EditHost => self.begin_edit(),
+            Remedy::Probe => self.probe_all(prober),
+        }
+    }
+
+    /// Removes the lines ssh named as offending, or every key on file for
+    /// the host when it named none.
+    fn remove_offending_keys(
+        &mut self,
+        diagnosis: &SessionDiagnosis,
+        index: usize,
+        known_hosts_repo: &dyn KnownHostsRepository,
+    ) {
+        self.load_known_hosts(known_hosts_repo);
+        let SshFailure::HostKeyChanged { offending } = &diagnosis.failure else {
+            return;
+        };
+
+        let lines: Vec<(usize, usize)> = offending
+            .iter()
+            .filter_map(|(path, line)| {
+                let file_index = self.known_hosts.files.iter().position(|f| {
+                    f.path == *path || std::fs::canonicalize(&f.path).is_ok_and(|p| p == *path)
+                })?;
+                Some((file_index, *line))
+            })
+            .collect();
+        if lines.is_empty() {
+            self.remove_known_hosts_of_host(index, known_hosts_repo);
+            return;
+        }
+
+        let what = format!("Removed the stale key of '{}'", diagnosis.alias);
+        self.remove_known_hosts_lines(&lines, &what, known_hosts_repo);
+    }
+
+    fn set_identities_only(&mut self, index: usize, ssh_repo: &dyn SshRepository) {
+        let mut draft = self.hosts[index].clone();
+        draft.extra_options.retain(|(k, _)| !k.eq_ignore_ascii_case("IdentitiesOnly"));
+        draft.extra_options.push(("IdentitiesOnly".into(), "yes".into()));
+        let needs_identity = !draft.has_identity_file();
+
+        match self.update_host(index, draft, ssh_repo) {
+            Ok(_) if needs_identity => {
+                self.begin_edit();
+                self.form_field = FormField::IdentityFile;
+                self.notification = Some(("Added IdentitiesOnly yes; now set the IdentityFile to offer".into(), false));
+            }
+            Ok(message) => self.notification = Some((format!("{}: IdentitiesOnly yes", message), false)),
+            Err(e) => self.notification = Some((e, true)),
+        }
+    }
+
+    // ─── Picker ──────────────────────────────────────────────────────────
+
+    pub fn begin_pick(&mut self) {
+        self.picking = true;
+        self.pick_marks.clear();
+        self.search_query.clear();
+        self.cursor = 0;
+        self.rebuild_filter();
+    }
+
+    pub fn is_marked(&self, index: usize) -> bool {
+        self.pick_marks.contains(&index)
+    }
+
+    /// Marks or unmarks the highlighted host and moves to the next one.
+    pub fn toggle_pick_mark(&mut self) {
+        let Some(index) = self.selected_real_index() else {
+            return;
+        };
+        match self.pick_marks.iter().position(|&i| i == index) {
+            Some(position) => {
+                self.pick_marks.remove(position);
+            }
+            None => self.pick_marks.push(index),
+        }
+        self.move_cursor_down();
+    }
+
+    /// The marked hosts, or the highlighted one when nothing is marked.
+    pub fn confirm_pick(&mut self) {
+        let indices = if self.pick_marks.is_empty() {
+            self.selected_real_index().into_iter().collect()
+        } else {
+            self.pick_marks.clone()
+        };
+        if indices.is_empty() {
+            return;
+        }
+
+        let aliases = indices
+            .iter()
+            .filter_map(|&i| self.hosts.get(i))
+            .map(|h| h.alias().to_string())
+            .collect();
+        self.pending_action = Action::Pick(aliases);
+    }
+
+    // ─── CRUD via Repository ─────────────────────────────────────────────
+
+    pub fn begin_add(&mut self) {
+        self.form_draft = SshHost::empty();
+        self.form_field = FormField::Alias;
+        self.mode = Mode::AddHost;
+    }
+
+    pub fn begin_edit(&mut self) {
+        if let Some(index) = self.selected_real_index() {
+            self.form_draft = self.hosts[index].clone();
+            self.form_field = FormField::Alias;
+            self.mode = Mode::EditHost(index);
+        }
+    }
+
+    pub fn begin_delete(&mut self) {
+        if let Some(index) = self.selected_real_index() {
+            self.mode = Mode::ConfirmDelete(index);
+        }
+    }
+
+    pub fn commit_add(&mut self, ssh_repo: &dyn SshRepository) {
+        let mut draft = self.form_draft.clone();
+        let checked = self.check_draft(&mut draft, None);
+        self.form_draft = draft.clone();
+        if let Err(e) = checked {
+            self.notification = Some((e, true));
+            return;
+        }
+
+        let result = self.write_new_host(draft, ssh_repo);
+        self.notify(result);
+        self.mode = self.mode_after_save();
+    }
+
+    pub fn commit_edit(&mut self, index: usize, ssh_repo: &dyn SshRepository) {
+        let mut draft = self.form_draft.clone();
+        let checked = self.check_draft(&mut draft, Some(index));
+        self.form_draft = draft.clone();
+        if let Err(e) = checked {
+            self.notification = Some((e, true));
+            return;
+        }
+
+        let result = self.write_host_update(index, draft, ssh_repo);
+        self.notify(result);
+        self.mode = self.mode_after_save();
+    }
+
+    pub fn commit_delete(&mut self, index: usize, ssh_repo: &dyn SshRepository) {
+        let result = self.delete_host(index, ssh_repo);
+        self.notify(result);
+
+        let count = self.visible_hosts().len();
+        if self.cursor >= count && count > 0 {
+            self.cursor = count - 1;
+        }
+        self.mode = self.mode_after_save();
+    }
+
+    fn notify(&mut self, result: Result<String, String>) {
+        self.notification = Some(match result {
+            Ok(message) => (message, false),
+            Err(e) => (e, true),
+        });
+    }
+
+    /// Validates and writes a new host; the path shared by the add form
+    /// and `lazyssh add`.
+    pub fn add_host(&mut self, mut draft: SshHost, ssh_repo: &dyn SshRepository) -> Result<String, String> {
+        self.check_draft(&mut draft, None)?;
+        self.write_new_host(draft, ssh_repo)
+    }
+
+    /// Validates and writes changes to the host at `index`; shared by the
+    /// edit form and `lazyssh edit`.
+    pub fn update_host(
+        &mut self,
+        index: usize,
+        mut draft: SshHost,
+        ssh_repo: &dyn SshRepository,
+    ) -> Result<String, String> {
+        self.check_draft(&mut draft, Some(index))?;
+        self.write_host_update(index, draft, ssh_repo)
+    }
+
+    pub fn delete_host(&mut self, index: usize, ssh_repo: &dyn SshRepository) -> Result<String, String> {
+        let removed = self
+            .hosts
+            .get(index)
+            .cloned()
+            .ok_or_else(|| "host is no longer loaded".to_string())?;
+
+        let result = self.persist(removed.file_index, ssh_repo, |doc| {
+            if let Some(block) = removed.block_index {
+                doc.remove_host(block);
+            }
+        });
+        self.rebuild_filter();
+
+        result.map(|()| format!("Deleted '{}'", removed.alias()))
+    }
+
+    /// Normalizes a draft and rejects it when required fields are missing
+    /// or one of its patterns already belongs to another host.
+    fn check_draft(&self, draft: &mut SshHost, editing: Option<usize>) -> Result<(), String> {
+        draft.normalize_patterns();
+        draft.normalize_lists();
+        if !draft.is_valid() {
+            return Err("Alias and HostName are required".into());
+        }
+
+        if let Some(pattern) = self.duplicate_pattern(draft, editing) {
+            return Err(format!("'{}' already exists", pattern));
+        }
+        Ok(())
+    }
+
+    fn write_new_host(&mut self, draft: SshHost, ssh_repo: &dyn SshRepository) -> Result<String, String> {
+        let name = draft.alias().to_string();
+        let file_label = self.source_display(draft.file_index);
+
+        let result = self.persist(draft.file_index, ssh_repo, |doc| doc.add_host(&draft));
+        self.rebuild_filter();
+
+        result.map(|()| format!("Added '{}' to {}", name, file_label))
+    }
+
+    fn write_host_update(
+        &mut self,
+        index: usize,
+        draft: SshHost,
+        ssh_repo: &dyn SshRepository,
+    ) -> Result<String, String> {
+        let name = draft.alias().to_string();
+        let host = self
+            .hosts
+            .get(index)
+            .ok_or_else(|| "host is no longer loaded".to_string())?;
+        let (file_index, block) = (host.file_index, host.block_index);
+
+        let result = self.persist(file_index, ssh_repo, |doc| {
+            if let Some(block) = block {
+                doc.update_host(block, &draft);
+            }
+        });
+        self.rebuild_filter();
+
+        result.map(|()| format!("Updated '{}'", name))
+    }
+
+    /// A pattern of `draft` that another host already declares.
+    fn duplicate_pattern(&self, draft: &SshHost, editing: Option<usize>) -> Option<String> {
+        self.hosts
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != editing)
+            .find_map(|(_, h)| draft.shared_pattern(h))
+            .map(str::to_string)
+    }
+
+    /// Applies `edit` to one file of the config set and writes only that file.
+    /// If the file changed on disk since it was loaded, the edit is parked in
+    /// `pending_save` so the user can reload or overwrite.
+    fn persist(
+        &mut self,
+        file_index: usize,
+        ssh_repo: &dyn SshRepository,
+        edit: impl FnOnce(&mut SshConfig),
+    ) -> Result<(), String> {
+        let mut file = self
+            .config
+            .files
+            .get(file_index)
+            .cloned()
+            .ok_or_else(|| "config file is no longer loaded".to_string())?;
+
+        edit(&mut file.document);
+
+        if !FileStamp::unchanged(file.stamp, ssh_repo.disk_stamp(&file.path)) {
+            let message = format!(
+                "{} changed on disk since it was loaded",
+                self.source_display(file_index)
+            );
+            self.pending_save = Some((file_index, file));
+            return Err(message);
+        }
+
+        self.write_file(file_index, file, ssh_repo)
+    }
+
+    fn write_file(
+        &mut self,
+        file_index: usize,
+        mut file: ConfigFile,
+        ssh_repo: &dyn SshRepository,
+    ) -> Result<(), String> {
+        ssh_repo.save_file(&file)?;
+        file.stamp = ssh_repo.disk_stamp(&file.path);
+
+        self.config.files[file_index] = file;
+        self.refresh_entries();
+        self.prune_backups(ssh_repo);
+        Ok(())
+    }
+
+    fn mode_after_save(&self) -> Mode {
+        if self.pending_save.is_some() {
+            Mode::SaveConflict
+        } else {
+            Mode::Normal
+        }
+    }
+
+    /// Discards the parked edit and picks up the version on disk.
+    pub fn resolve_conflict_reload(&mut self, ssh_repo: &dyn SshRepository) {
+        self.pending_save = None;
+        self.mode = Mode::Normal;
+        self.reload_from_disk(ssh_repo);
+    }
+
+    /// Writes the parked edit over whatever is on disk now. A backup of the
+    /// external version is still taken first.
+    pub fn resolve_conflict_overwrite(&mut self, ssh_repo: &dyn SshRepository) {
+        self.mode = Mode::Normal;
+        let Some((file_index, mut file)) = self.pending_save.take() else {
+            return;
+        };
+
+        file.stamp = ssh_repo.disk_stamp(&file.path);
+        let label = self.source_display(file_index);
+
+        match self.write_file(file_index, file, ssh_repo) {
+            Ok(()) => {
+                self.notification = Some((format!("Overwrote {}", label), false));
+            }
+            Err(e) => {
+                self.notification = Some((e, true));
+            }
+        }
+        self.rebuild_filter();
+    }
+
+    pub fn pending_conflict_file(&self) -> Option<String> {
+        self.pending_save
+            .as_ref()
+            .map(|(file_index, _)| self.source_display(*file_index))
+    }
+
+    fn refresh_entries(&mut self) {
+        self.hosts = self.config.hosts();
+        self.match_rules = self.config.match_rules();
+        self.diagnostics = Linter::diagnose(&self.config);
+        self.compute_trust();
+        self.refresh_known_hosts_view();
+    }
+
+    pub fn cancel_mode(&mut self) {
+        self.pending_save = None;
+        self.mode = Mode::Normal;
+    }
+
+    // ─── SSH Execution ───────────────────────────────────────────────────
+
+    pub fn launch_ssh(&mut self) {
+        let Some(host) = self.selected_host() else {
+            return;
+        };
+
+        if host.is_pattern() {
+            self.notification = Some((
+                format!("'{}' is a pattern, not a connectable host", host.alias()),
+                true,
+            ));
+            return;
+        }
+
+        self.pending_action = Action::LaunchSsh(self.launch_command(host));
+    }
+
+    /// What runs when connecting to `host`, per the launch settings.
+    pub fn launch_command(&self, host: &SshHost) -> LaunchCommand {
+        self.settings.launch.command_for(host)
+    }
+
+    /// The launch command for a host, refusing pattern blocks.
+    pub fn connect_command(&self, index: usize) -> Result<LaunchCommand, String> {
+        let host = self
+            .hosts
+            .get(index)
+            .ok_or_else(|| "host is no longer loaded".to_string())?;
+        if host.is_pattern() {
+            return Err(format!("'{}' is a pattern, not a connectable host", host.alias()));
+        }
+        Ok(self.launch_command(host))
+    }
+
+    // ─── Form Editing ────────────────────────────────────────────────────
+
+    pub fn form_next_field(&mut self) {
+        self.form_field = self.form_field.next();
+    }
+
+    pub fn form_previous_field(&mut self) {
+        self.form_field = self.form_field.previous();
+    }
+
+    /// Moves a new host between the loaded config files. Existing hosts stay
+    /// in the file they were read from.
+    pub fn form_cycle_file(&mut self, forward: bool) {
+        if self.mode != Mode::AddHost || self.form_field != FormField::ConfigFile {
+            return;
+        }
+
+        let count = self.config.files.len().max(1);
+        let current = self.form_draft.file_index;
+        self.form_draft.file_index = if forward {
+            (current + 1) % count
+        } else {
+            (current + count - 1) % count
+        };
+    }
+
+    pub fn form_type_char(&mut self, c: char) {
+        if !self.form_field.accepts_char(c) {
+            return;
+        }
+        let mut value = self.read_form_field();
+        value.push(c);
+        self.write_form_field(value);
+    }
+
+    pub fn form_delete_char(&mut self) {
+        let mut value = self.read_form_field();
+        value.pop();
+        self.write_form_field(value);
+    }
+
+    fn read_form_field(&self) -> String {
+        match self.form_field {
+            FormField::Alias => self.form_draft.patterns_text(),
+            FormField::HostName => self.form_draft.hostname.clone(),
+            // Typing over an invalid port starts from an empty field.
+            FormField::Port if self.form_draft.invalid_port.is_some() => String::new(),
+            FormField::Port => self.form_draft.port.to_string(),
+            FormField::User => self.form_draft.user.clone(),
+            FormField::ConfigFile => self.source_display(self.form_draft.file_index),
+            _ => self.form_draft.list_text(&self.form_field),
+        }
+    }
+
+    fn write_form_field(&mut self, value: String) {
+        match self.form_field {
+            FormField::Alias => self.form_draft.set_patterns_text(&value),
+            FormField::HostName => self.form_draft.hostname = value,
+            FormField::Port => {
+                self.form_draft.port = value.parse().unwrap_or(22);
+                self.form_draft.invalid_port = None;
+            }
+            FormField::User => self.form_draft.user = value,
+            FormField::ConfigFile => {}
+            _ => self.form_draft.set_list_text(&self.form_field, &value),
+        }
+    }
+
+    // ─── Match Blocks ────────────────────────────────────────────────────
+
+    pub fn match_rules(&self) -> &[MatchRule] {
+        &self.match_rules
+    }
+
+    pub fn open_match_rules(&mut self) {
+        if self.match_rules.is_empty() {
+            self.notification = Some(("No Match blocks in the config".into(), false));
+            return;
+        }
+        self.match_cursor = self.match_cursor.min(self.match_rules.len() - 1);
+        self.mode = Mode::MatchRules;
+    }
+
+    pub fn match_cursor_up(&mut self) {
+        if self.match_cursor > 0 {
+            self.match_cursor -= 1;
+        }
+    }
+
+    pub fn match_cursor_down(&mut self) {
+        if self.match_cursor + 1 < self.match_rules.len() {
+            self.match_cursor += 1;
+        }
+    }
+
+    // ─── Export ──────────────────────────────────────────────────────────
+
+    /// Everything `list --json` reports; pattern blocks only when asked.
+    pub fn inventory(&self, include_patterns: bool) -> Inventory {
+        let hosts = (0..self.hosts.len())
+            .filter(|&i| include_patterns || !self.hosts[i].is_pattern())
+            .filter_map(|i| self.host_record(i))
+            .collect();
+        let diagnostics = self
+            .diagnostics
+            .iter()
+            .filter(|d| !self.belongs_to_host(d))
+            .map(|d| DiagnosticRecord::new(d, self.file_path(d.file_index)))
+            .collect();
+
+        Inventory {
+            schema_version: SCHEMA_VERSION,
+            config_path: self.config_path_display(),
+            files: (0..self.config.files.len()).map(|i| self.file_path(i)).collect(),
+            hosts,
+            diagnostics,
+        }
+    }
+
+    pub fn host_record(&self, index: usize) -> Option<HostRecord> {
+        let host = self.hosts.get(index)?;
+        let block_index = host.block_index?;
+        let document = &self.config.files.get(host.file_index)?.document;
+        let file = self.file_path(host.file_index);
+
+        let (line, end_line) = document.block_lines(block_index);
+        let mut lines: Vec<(usize, &Directive)> = document
+            .located_directives()
+            .into_iter()
+            .filter(|(_, block, _)| *block == Some(block_index))
+            .map(|(line, _, directive)| (line, directive))
+            .collect();
+        let extra_options = host
+            .extra_options
+            .iter()
+            .map(|(keyword, value)| {
+                let found = lines.iter().position(|(_, d)| d.is(keyword) && d.value == *value);
+                OptionRecord {
+                    keyword: keyword.clone(),
+                    value: value.clone(),
+                    line: found.map(|i| lines.remove(i).0),
+                }
+            })
+            .collect();
+        let diagnostics = self
+            .host_diagnostics(host)
+            .into_iter()
+            .map(|d| DiagnosticRecord::new(d, file.clone()))
+            .collect();
+
+        Some(HostRecord::new(
+            host,
+            SourceRecord { file, line, end_line },
+            extra_options,
+            diagnostics,
+        ))
+    }
+
+    fn belongs_to_host(&self, diagnostic: &Diagnostic) -> bool {
+        diagnostic.block_index.is_some()
+            && self
+                .hosts
+                .iter()
+                .any(|h| h.file_index == diagnostic.file_index && h.block_index == diagnostic.block_index)
+    }
+
+    fn file_path(&self, file_index: usize) -> String {
+        self.config
+            .path_of(file_index)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    // ─── Diagnostics ─────────────────────────────────────────────────────
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    pub fn host_diagnostics(&self, host: &SshHost) -> Vec<&Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.file_index == host.file_index && d.block_index == host.block_index)
+            .collect()
+    }
+
+    /// The most severe problem in a host's block, for the list badge.
+    pub fn host_severity(&self, host: &SshHost) -> Option<Severity> {
+        self.host_diagnostics(host).iter().map(|d| d.severity).min()
+    }
+
+    pub fn open_diagnostics(&mut self) {
+        if self.diagnostics.is_empty() {
+            self.notification = Some(("No problems found in the config".into(), false));
+            return;
+        }
+        self.diagnostic_cursor = self.diagnostic_cursor.min(self.diagnostics.len() - 1);
+        self.mode = Mode::Diagnostics;
+    }
+
+    pub fn diagnostic_cursor_up(&mut self) {
+        if self.diagnostic_cursor > 0 {
+            self.diagnostic_cursor -= 1;
+        }
+    }
+
+    pub fn diagnostic_cursor_down(&mut self) {
+        if self.diagnostic_cursor + 1 < self.diagnostics.len() {
+            self.diagnostic_cursor += 1;
+        }
+    }
+
+    /// Selects the host whose block contains the highlighted diagnostic.
+    pub fn jump_to_diagnostic(&mut self) {
+        let Some(diagnostic) = self.diagnostics.get(self.diagnostic_cursor) else {
+            return;
+        };
+        let (file_index, block_index) = (diagnostic.file_index, diagnostic.block_index);
+        self.select_block(file_index, block_index);
+    }
+
+    /// Moves the list cursor to the host defined by a block, clearing the
+    /// search if it hides that host.
+    fn select_block(&mut self, file_index: usize, block_index: Option<usize>) {
+        let Some(index) = self.hosts.iter().position(|h| {
+            block_index.is_some() && h.file_index == file_index && h.block_index == block_index
+        }) else {
+            self.notification = Some(("This line is not inside a Host block".into(), false));
+            return;
+        };
+
+        if !self.visible_indices.contains(&index) {
+            self.search_query.clear();
+            self.rebuild_filter();
+        }
+        if let Some(position) = self.visible_indices.iter().position(|&i| i == index) {
+            self.cursor = position;
+        }
+        self.mode = Mode::Normal;
+    }
+
+    // ─── Security Audit ──────────────────────────────────────────────────
+
+    pub fn open_audit(&mut self, key_repo: &dyn KeyRepository) {
+        self.audit_findings = Auditor::run(&self.config, &self.hosts, &self.settings.audit, key_repo);
+        if self.audit_findings.is_empty() {
+            self.notification = Some(("Audit found no risky settings".into(), false));
+            return;
+        }
+        self.audit_cursor = 0;
+        self.mode = Mode::Audit;
+    }
+
+    pub fn audit_cursor_up(&mut self) {
+        if self.audit_cursor > 0 {
+            self.audit_cursor -= 1;
+        }
+    }
+
+    pub fn audit_cursor_down(&mut self) {
+        if self.audit_cursor + 1 < self.audit_findings.len() {
+            self.audit_cursor += 1;
+        }
+    }
+
+    pub fn jump_to_audit_finding(&mut self) {
+        let Some(finding) = self.audit_findings.get(self.audit_cursor) else {
+            return;
+        };
+        let (file_index, block_index) = (finding.file_index, finding.block_index);
+        self.select_block(file_index, block_index);
+    }
+
+    // ─── Backups ─────────────────────────────────────────────────────────
+
+    pub fn open_backups(&mut self, ssh_repo: &dyn SshRepository) {
+        self.backups = ssh_repo.list_backups();
+        if self.backups.is_empty() {
+            self.notification = Some(("No backups yet".into(), false));
+            return;
+        }
+        self.backup_cursor = 0;
+        self.refresh_backup_diff(ssh_repo);
+        self.mode = Mode::Backups;
+    }
+
+    pub fn backup_cursor_up(&mut self, ssh_repo: &dyn SshRepository) {
+        if self.backup_cursor > 0 {
+            self.backup_cursor -= 1;
+            self.refresh_backup_diff(ssh_repo);
+        }
+    }
+
+    pub fn backup_cursor_down(&mut self, ssh_repo: &dyn SshRepository) {
+        if self.backup_cursor + 1 < self.backups.len() {
+            self.backup_cursor += 1;
+            self.refresh_backup_diff(ssh_repo);
+        }
+    }
+
+    pub fn retention_display(&self) -> String {
+        self.settings.backups.describe()
+    }
+
+    /// The loaded file a backup was taken of. `None` when that file is no
+    /// longer part of the config, in which case the backup is not restored.
+    pub fn backup_target(&self, backup: &BackupEntry) -> Option<usize> {
+        self.config.files.iter().position(|f| f.path == backup.source)
+    }
+
+    fn refresh_backup_diff(&mut self, ssh_repo: &dyn SshRepository) {
+        self.backup_diff.clear();
+        let Some(backup) = self.backups.get(self.backup_cursor) else {
+            return;
+        };
+
+        let Some(target) = self.backup_target(backup).map(|i| &self.config.files[i]) else {
+            return;
+        };
+        let current = ssh_repo.read_text(&target.path).unwrap_or_default();
+        match ssh_repo.read_text(&backup.path) {
+            // Shows what restoring would change: current → backup.
+            Ok(old) => self.backup_diff = diff_lines(&current, &old),
+            Err(e) => self.notification = Some((e, true)),
+        }
+    }
+
+    /// Replaces the target file with the selected backup. The current
+    /// version is backed up first so a restore can itself be undone.
+    pub fn restore_selected_backup(&mut self, ssh_repo: &dyn SshRepository) {
+        let Some(backup) = self.backups.get(self.backup_cursor).cloned() else {
+            return;
+        };
+        let Some(target_index) = self.backup_target(&backup) else {
+            self.notification = Some((
+                format!(
+                    "Not restored: {} is a backup of {}, which is not part of the loaded config",
+                    backup.file_name(),
+                    self.path_display(&backup.source)
+                ),
+                true,
+            ));
+            return;
+        };
+        let target = self.config.files[target_index].path.clone();
+
+        match ssh_repo.restore_backup(&backup, &target) {
+            Ok(_) => {
+                self.mode = Mode::Normal;
+                self.reload_from_disk(ssh_repo);
+                self.notification = Some((
+                    format!(
+                        "Restored {} from {}",
+                        self.source_display(target_index),
+                        backup.file_name()
+                    ),
+                    false,
+                ));
+            }
+            Err(e) => self.notification = Some((e, true)),
+        }
+    }
+
+    pub fn delete_selected_backup(&mut self, ssh_repo: &dyn SshRepository) {
+        let Some(backup) = self.backups.get(self.backup_cursor).cloned() else {
+            return;
+        };
+
+        match ssh_repo.delete_backup(&backup) {
+            Ok(()) => {
+                self.notification = Some((format!("Deleted {}", backup.file_name()), false));
+                self.reload_backups(ssh_repo);
+            }
+            Err(e) => self.notification = Some((e, true)),
+        }
+    }
+
+    /// Applies the retention settings now, from the backup browser.
+    pub fn prune_backups_now(&mut self, ssh_repo: &dyn SshRepository) {
+        let removed = self.prune_backups(ssh_repo);
+        self.notification = Some((
+            format!("Pruned {} backup(s) ({})", removed, self.retention_display()),
+            false,
+        ));
+        self.reload_backups(ssh_repo);
+    }
+
+    /// Deletes backups the retention settings no longer keep. Runs after
+    /// every save; failures are left for the next run.
+    fn prune_backups(&self, ssh_repo: &dyn SshRepository) -> usize {
+        let backups = ssh_repo.list_backups();
+        self.settings
+            .backups
+            .expired(&backups, Local::now().naive_local())
+            .into_iter()
+            .filter(|backup| ssh_repo.delete_backup(backup).is_ok())
+            .count()
+    }
+
+    fn reload_backups(&mut self, ssh_repo: &dyn SshRepository) {
+        self.backups = ssh_repo.list_backups();
+        if self.backups.is_empty() {
+            self.mode = Mode::Normal;
+            return;
+        }
+        self.backup_cursor = self.backup_cursor.min(self.backups.len() - 1);
+        self.refresh_backup_diff(ssh_repo);
+    }
+
+    // ─── Theme ───────────────────────────────────────────────────────────
+
+    pub fn open_theme_selector(&mut self) {
+        self.theme_cursor = self.theme_preference.theme_index;
+        self.mode = Mode::SelectTheme;
+    }
+
+    pub fn theme_cursor_up(&mut self) {
+        if self.theme_cursor > 0 {
+            self.theme_cursor -= 1;
+        }
+    }
+
+    pub fn theme_cursor_down(&mut self) {
+        if self.theme_cursor < self.available_themes.len() - 1 {
+            self.theme_cursor += 1;
+        }
+    }
+
+    pub fn apply_selected_theme(&mut self, theme_repo: &dyn ThemeRepository) {
+        let index = self.theme_cursor;
+        if let Some(new_theme) = self.available_themes.get(index) {
+            let mut theme = new_theme.clone();
+            if index == 0 {
+                theme.transparent = true;
+            } else {
+                theme.transparent = self.theme_preference.transparent;
+            }
+
+            self.theme = theme;
+            self.theme_preference.theme_index = index;
+            self.theme_preference.transparent = self.theme.transparent;
+            theme_repo.save_preference(&self.theme_preference);
+
+            self.notification = Some((format!("Theme: {}", new_theme.name), false));
+        }
+        self.mode = Mode::Normal;
+    }
+
+    pub fn toggle_transparency(&mut self, theme_repo: &dyn ThemeRepository) {
+        self.theme.transparent = !self.theme.transparent;
+        self.theme_preference.transparent = self.theme.transparent;
+        theme_repo.save_preference(&self.theme_preference);
+
+        let label = if self.theme.transparent { "ON" } else { "OFF" };
+        self.notification = Some((format!("Transparency: {}", label), false));
+    }
+
+    // ─── Misc ────────────────────────────────────────────────────────────
+
+    pub fn toggle_command_preview(&mut self) {
+        self.show_command = !self.show_command;
+    }
+
+    pub fn toggle_effective_view(&mut self) {
+        self.show_effective = !self.show_effective;
+    }
+
+    /// What ssh would apply to the selected host, across every matching block.
+    pub fn effective_config(&self) -> Option<EffectiveConfig> {
+        self.selected_host()
+            .map(|host| ConfigResolver::resolve(&self.config, host.alias()))
+    }
+
+    pub fn reload_from_disk(&mut self, ssh_repo: &dyn SshRepository) {
+        self.config = ssh_repo.load_all();
+        self.refresh_entries();
+        self.rebuild_filter();
+        self.notification = Some((
+            format!("Reloaded ({} hosts)", self.host_count()),
+            false,
+        ));
+    }
+
+    pub fn open_help(&mut self) {
+        self.mode = Mode::Help;
+    }
+
+    pub fn request_quit(&mut self) {
+        self.pending_action = Action::Quit;
+    }
+
+    pub fn clear_notification(&mut self) {
+        self.notification = None;
+    }
+
+    pub fn take_action(&mut self) -> Action {
+        let action = self.pending_action.clone();
+        self.pending_action = Action::Continue;
+        action
+    }
+}
+
+/// Compares two key paths, seeing through symlinks when both exist.
+fn same_file(a: &std::path::Path, b: &std::path::Path) -> bool {
+    a == b
+        || matches!(
+            (std::fs::canonicalize(a), std::fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::path::Path;
+
+    use super::*;
+    use crate::models::KnownHostsFile;
+    use crate::repositories::theme_repository::ThemeCatalog;
+
+    const KEY_A: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAICFoBIaDBK0+M48qTFL9YmSZnoxhHX/4jw684oD5FuMv";
+    const KEY_B: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIEtdhTCwV6XbnejhUwyAtqImEKfXAertAEezIh+NEmy2";
+
+    struct NoConfig;
+
+    impl SshRepository for NoConfig {
+        fn load_all(&self) -> ConfigSet {
+            ConfigSet::default()
+        }
+        fn save_file(&self, _file: &ConfigFile) -> Result<PathBuf, String> {
+            Err("read-only".into())
+        }
+        fn disk_stamp(&self, _path: &Path) -> Option<FileStamp> {
+            None
+        }
+        fn config_path(&self) -> PathBuf {
+            PathBuf::from("/nonexistent/config")
+        }
+        fn list_backups(&self) -> Vec<BackupEntry> {
+            Vec::new()
+        }
+        fn read_text(&self, path: &Path) -> Result<String, String> {
+            Err(format!("cannot read {}", path.display()))
+        }
+        fn restore_backup(&self, _backup: &BackupEntry, _target: &Path) -> Result<PathBuf, String> {
+            Err("read-only".into())
+        }
+        fn delete_backup(&self, _backup: &BackupEntry) -> Result<(), String> {
+            Err("read-only".into())
+        }
+    }
+
+    struct DefaultTheme;
+
+    impl ThemeRepository for DefaultTheme {
+        fn load_preference(&self) -> ThemePreference {
+            ThemePreference::default()
+        }
+        fn save_preference(&self, _preference: &ThemePreference) {}
+        fn catalog(&self) -> Vec<Theme> {
+            ThemeCatalog::all()
+        }
+    }
+
+    struct DefaultSettings;
+
+    impl SettingsRepository for DefaultSettings {
+        fn load(&self) -> Settings {
+            Settings::default()
+        }
+    }
+
+    /// known_hosts files kept in memory; counts the saves per file.
+    struct MemoryKnownHosts {
+        files: RefCell<Vec<(PathBuf, String, usize)>>,
+    }
+
+    impl MemoryKnownHosts {
+        fn new(files: &[(&str, &str)]) -> Self {
+            Self {
+                files: RefCell::new(files.iter().map(|(p, t)| (PathBuf::from(p), t.to_string(), 0)).collect()),
+            }
+        }
+
+        fn text(&self, path: &str) -> String {
+            self.files.borrow().iter().find(|f| f.0 == Path::new(path)).unwrap().1.clone()
+        }
+
+        fn saves(&self, path: &str) -> usize {
+            self.files.borrow().iter().find(|f| f.0 == Path::new(path)).unwrap().2
+        }
+    }
+
+    impl KnownHostsRepository for MemoryKnownHosts {
+        fn load(&self) -> KnownHosts {
+            let mut known = KnownHosts::default();
+            for (file_index, (path, text, _)) in self.files.borrow().iter().enumerate() {
+                known.entries.extend(
+                    text.lines()
+                        .enumerate()
+                        .filter_map(|(i, line)| KnownHostEntry::parse(line, file_index, i + 1)),
+                );
+                known.files.push(KnownHostsFile {
+                    path: path.clone(),
+                    lines: text.lines().map(String::from).collect(),
+                    system: false,
+                });
+            }
+            known
+        }
+
+        fn save(&self, path: &Path, content: &str) -> Result<PathBuf, String> {
+            let mut files = self.files.borrow_mut();
+            let file = files.iter_mut().find(|f| f.0 == path).ok_or("unknown file")?;
+            file.1 = content.to_string();
+            file.2 += 1;
+            Ok(path.with_extension("old"))
+        }
+    }
+
+    fn app_with(known_hosts: &MemoryKnownHosts) -> AppService {
+        let mut app = AppService::initialize(&NoConfig, &DefaultTheme, &DefaultSettings);
+        app.load_known_hosts(known_hosts);
+        app
+    }
+
+    #[test]
+    fn removing_interleaved_lines_writes_each_file_once() {
+        let repo = MemoryKnownHosts::new(&[
+            ("/kh/known_hosts", &format!("a1 {KEY_A}\nkeep {KEY_A}\na2 {KEY_B}\n")),
+            ("/kh/known_hosts2", &format!("b1 {KEY_B}\n")),
+        ]);
+        let mut app = app_with(&repo);
+
+        app.remove_known_hosts_lines(&[(0, 1), (1, 1), (0, 3)], "Removed", &repo);
+
+        assert_eq!(app.notification.as_ref().map(|n| n.1), Some(false), "{:?}", app.notification);
+        assert_eq!(repo.text("/kh/known_hosts"), format!("keep {KEY_A}\n"));
+        assert_eq!(repo.text("/kh/known_hosts2"), "");
+        assert_eq!(repo.saves("/kh/known_hosts"), 1);
+        assert_eq!(repo.saves("/kh/known_hosts2"), 1);
+    }
+
+    #[test]
+    fn paths_under_home_are_shortened() {
+        let home = dirs::home_dir().unwrap();
+        let repo = MemoryKnownHosts::new(&[
+            (home.join(".ssh/known_hosts").to_str().unwrap(), ""),
+            ("/etc/ssh/ssh_known_hosts", ""),
+        ]);
+        let app = app_with(&repo);
+
+        assert_eq!(app.path_display(&home.join(".ssh/id_ed25519")), "~/.ssh/id_ed25519");
+        assert_eq!(app.path_display(Path::new("/etc/ssh/ssh_config")), "/etc/ssh/ssh_config");
+        assert_eq!(app.known_hosts_source(0, 3), "~/.ssh/known_hosts:3");
+        assert_eq!(app.known_hosts_source(1, 7), "/etc/ssh/ssh_known_hosts:7");
+        assert_eq!(app.known_hosts_source(9, 1), "?");
+        assert_eq!(app.source_display(0), "?");
+    }
+
+    #[test]
+    fn hashing_uses_a_fresh_salt_per_name() {
+        let repo = MemoryKnownHosts::new(&[("/kh/known_hosts", &format!("a.example,b.example {KEY_A}\n"))]);
+        let mut app = app_with(&repo);
+
+        app.hash_known_hosts(&repo);
+
+        let text = repo.text("/kh/known_hosts");
+        let salts: Vec<&str> = text.lines().map(|l| l.split('|').nth(2).unwrap()).collect();
+        assert_eq!(salts.len(), 2, "{text}");
+        assert_ne!(salts[0], salts[1]);
+        assert!(salts.iter().all(|s| *s != "AAAAAAAAAAAAAAAAAAAAAAAAAAA="));
+
+        let known = repo.load();
+        assert!(known.entries.iter().any(|e| e.matches_name("a.example")));
+        assert!(known.entries.iter().any(|e| e.matches_name("b.example")));
+    }
+
+    #[test]
+    fn known_hosts_edits_wait_for_confirmation() {
+        let repo = MemoryKnownHosts::new(&[("/kh/known_hosts", &format!("a.example {KEY_A}\n"))]);
+        let mut app = app_with(&repo);
+        app.open_known_hosts();
+
+        app.begin_known_hosts_edit(KnownHostsEdit::DeleteEntry);
+        assert_eq!(app.mode, Mode::ConfirmKnownHostsEdit(KnownHostsEdit::DeleteEntry));
+        assert_eq!(repo.saves("/kh/known_hosts"), 0);
+        app.cancel_known_hosts_edit();
+        assert_eq!(app.mode, Mode::KnownHosts);
+
+        app.begin_known_hosts_edit(KnownHostsEdit::HashNames);
+        app.apply_known_hosts_edit(KnownHostsEdit::HashNames, &repo);
+        assert_eq!(app.mode, Mode::KnownHosts);
+        assert!(repo.text("/kh/known_hosts").starts_with("|1|"));
     }
 }
@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+
+use crate::models::pattern::wildcard_match;
+use crate::models::ssh_config::split_arguments;
+use crate::models::{AuditFinding, AuditSeverity, AuditSettings, ConfigLine, ConfigSet, KeyFile, SshHost};
+use crate::repositories::KeyRepository;
+
+const WEAK_CIPHERS: &[&str] = &[
+    "3des-cbc",
+    "aes128-cbc",
+    "aes192-cbc",
+    "aes256-cbc",
+    "blowfish-cbc",
+    "cast128-cbc",
+    "arcfour",
+    "arcfour128",
+    "arcfour256",
+    "rijndael-cbc@lysator.liu.se",
+];
+
+const WEAK_KEX: &[&str] = &[
+    "diffie-hellman-group1-sha1",
+    "diffie-hellman-group14-sha1",
+    "diffie-hellman-group-exchange-sha1",
+];
+
+const WEAK_MACS: &[&str] = &[
+    "hmac-md5",
+    "hmac-md5-96",
+    "hmac-sha1-96",
+    "hmac-ripemd160",
+    "hmac-ripemd160@openssh.com",
+    "hmac-md5-etm@openssh.com",
+    "hmac-md5-96-etm@openssh.com",
+    "hmac-sha1-96-etm@openssh.com",
+];
+
+const WEAK_KEY_ALGORITHMS: &[&str] = &[
+    "ssh-dss",
+    "ssh-rsa",
+    "ssh-dss-cert-v01@openssh.com",
+    "ssh-rsa-cert-v01@openssh.com",
+];
+
+/// Where a finding was made: a host block, a `Match` block or a file's
+/// global lines.
+struct Subject {
+    name: String,
+    file_index: usize,
+    block_index: Option<usize>,
+}
+
+/// Flags risky settings across every host block, every `Match` block and
+/// the global lines outside any block. Key files are inspected through `KeyRepository`.
+pub struct Auditor<'a> {
+    settings: &'a AuditSettings,
+    keys: &'a dyn KeyRepository,
+    inspected: HashMap<String, KeyFile>,
+    findings: Vec<AuditFinding>,
+}
+
+impl<'a> Auditor<'a> {
+    pub fn run(
+        set: &ConfigSet,
+        hosts: &[SshHost],
+        settings: &'a AuditSettings,
+        keys: &'a dyn KeyRepository,
+    ) -> Vec<AuditFinding> {
+        let mut auditor = Self {
+            settings,
+            keys,
+            inspected: HashMap::new(),
+            findings: Vec::new(),
+        };
+
+        for (file_index, file) in set.files.iter().enumerate() {
+            let global = Subject {
+                name: "(global)".into(),
+                file_index,
+                block_index: None,
+            };
+            auditor.check_lines(&global, &file.document.preamble);
+
+            // Match blocks apply to whichever hosts their criteria select,
+            // `Match all` to every one, so the agent allowlist never covers them.
+            for (block_index, block) in file.document.blocks.iter().enumerate() {
+                if !block.is_match() {
+                    continue;
+                }
+                let rule = Subject {
+                    name: format!("Match {}", block.header.value),
+                    file_index,
+                    block_index: Some(block_index),
+                };
+                auditor.check_lines(&rule, &block.body);
+            }
+        }
+
+        for host in hosts {
+            let subject = Subject {
+                name: host.alias().to_string(),
+                file_index: host.file_index,
+                block_index: host.block_index,
+            };
+            let allowed = auditor.agent_allowed(host);
+            for (keyword, value) in &host.extra_options {
+                auditor.check_option(&subject, keyword, value, allowed);
+            }
+            for identity in &host.identity_files {
+                auditor.check_key(&subject, &host.expand_host_tokens(identity));
+            }
+        }
+
+        let mut findings = auditor.findings;
+        findings.sort_by_key(|f| (f.severity, f.file_index, f.block_index));
+        findings
+    }
+
+    fn check_lines(&mut self, subject: &Subject, lines: &[ConfigLine]) {
+        for directive in lines.iter().filter_map(ConfigLine::as_directive) {
+            if directive.value.is_empty() {
+                continue;
+            }
+            if directive.is("identityfile") {
+                self.check_key(subject, &directive.value);
+            } else {
+                self.check_option(subject, &directive.keyword, &directive.value, false);
+            }
+        }
+    }
+
+    fn report(&mut self, subject: &Subject, severity: AuditSeverity, message: String) {
+        self.findings.push(AuditFinding {
+            severity,
+            subject: subject.name.clone(),
+            file_index: subject.file_index,
+            block_index: subject.block_index,
+            message,
+        });
+    }
+
+    fn agent_allowed(&self, host: &SshHost) -> bool {
+        host.patterns
+            .iter()
+            .filter(|p| !p.starts_with('!'))
+            .any(|p| {
+                self.settings
+                    .forward_agent_allowlist
+                    .iter()
+                    .any(|allowed| wildcard_match(&allowed.to_lowercase(), &p.to_lowercase()))
+            })
+    }
+
+    /// Values may be quoted, as ssh allows; the checks look at what ssh
+    /// would see once the quotes are gone.
+    fn check_option(&mut self, subject: &Subject, keyword: &str, value: &str, agent_allowed: bool) {
+        let value = &split_arguments(value).join(" ");
+        let lower = value.to_lowercase();
+        match keyword.to_lowercase().as_str() {
+            "forwardagent" if lower != "no" && !agent_allowed => self.report(
+                subject,
+                AuditSeverity::High,
+                format!(
+                    "ForwardAgent {}: admins of this host can use your agent keys (not in forward_agent_allowlist)",
+                    value
+                ),
+            ),
+            "stricthostkeychecking" if lower == "no" || lower == "off" => self.report(
+                subject,
+                AuditSeverity::High,
+                format!("StrictHostKeyChecking {}: changed host keys are accepted silently", value),
+            ),
+            "userknownhostsfile" if lower.split_whitespace().any(|f| f == "/dev/null") => self.report(
+                subject,
+                AuditSeverity::High,
+                "UserKnownHostsFile /dev/null: host keys are never remembered or verified".into(),
+            ),
+            "passwordauthentication" if lower == "yes" => self.report(
+                subject,
+                AuditSeverity::Medium,
+                "PasswordAuthentication yes: password logins can be phished or brute-forced".into(),
+            ),
+            "forwardx11trusted" if lower == "yes" => self.report(
+                subject,
+                AuditSeverity::Low,
+                "ForwardX11Trusted yes: remote X clients get full access to your display".into(),
+            ),
+            "ciphers" => self.check_algorithms(subject, "Ciphers", &lower, WEAK_CIPHERS),
+            "kexalgorithms" => self.check_algorithms(subject, "KexAlgorithms", &lower, WEAK_KEX),
+            "macs" => self.check_algorithms(subject, "MACs", &lower, WEAK_MACS),
+            "hostkeyalgorithms" | "pubkeyacceptedalgorithms" | "pubkeyacceptedkeytypes" => {
+                self.check_algorithms(subject, keyword, &lower, WEAK_KEY_ALGORITHMS)
+            }
+            _ => {}
+        }
+    }
+
+    /// A `-` prefix removes algorithms, so only additions and replacements count.
+    fn check_algorithms(&mut self, subject: &Subject, keyword: &str, value: &str, weak: &[&str]) {
+        if value.starts_with('-') {
+            return;
+        }
+
+        let enabled: Vec<&str> = value
+            .trim_start_matches(['+', '^'])
+            .split(',')
+            .filter(|name| weak.contains(name))
+            .collect();
+        if !enabled.is_empty() {
+            self.report(
+                subject,
+                AuditSeverity::Medium,
+                format!("{} enables deprecated {}", keyword, enabled.join(", ")),
+            );
+        }
+    }
+
+    fn check_key(&mut self, subject: &Subject, identity: &str) {
+        let identity = &split_arguments(identity).join(" ");
+        let keys = self.keys;
+        let key = self
+            .inspected
+            .entry(identity.to_string())
+            .or_insert_with(|| keys.inspect(identity))
+            .clone();
+        if !key.exists {
+            return;
+        }
+
+        if key.too_open() {
+            self.report(
+                subject,
+                AuditSeverity::High,
+                format!(
+                    "{} has mode {:04o}; ssh refuses keys readable by others (chmod 600 {})",
+                    identity,
+                    key.mode.unwrap_or(0),
+                    key.path.display()
+                ),
+            );
+        }
+
+        let Some(public) = &key.public else { return };
+        match (public.algorithm(), public.bits) {
+            ("DSA", _) => self.report(
+                subject,
+                AuditSeverity::High,
+                format!("{} is a DSA key; OpenSSH no longer accepts DSA", identity),
+            ),
+            ("RSA", Some(bits)) if bits < 2048 => self.report(
+                subject,
+                AuditSeverity::High,
+                format!("{} is RSA-{}; keys under 2048 bits are breakable", identity, bits),
+            ),
+            ("RSA", Some(bits)) if bits < 3072 => self.report(
+                subject,
+                AuditSeverity::Low,
+                format!("{} is RSA-{}; prefer ed25519 or RSA-3072+", identity, bits),
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ConfigBlock, ConfigFile, Directive, KeyGenRequest, SshConfig};
+    use std::path::PathBuf;
+
+    /// Every key is missing, so only option checks produce findings.
+    struct NoKeys;
+
+    impl KeyRepository for NoKeys {
+        fn inspect(&self, identity: &str) -> KeyFile {
+            KeyFile {
+                path: PathBuf::from(identity),
+                exists: false,
+                mode: None,
+                public: None,
+                encrypted: None,
+                public_path: None,
+                unresolved: false,
+            }
+        }
+        fn scan(&self) -> Vec<KeyFile> {
+            Vec::new()
+        }
+        fn generate(&self, _: &KeyGenRequest) -> Result<PathBuf, String> {
+            Err("not supported".into())
+        }
+    }
+
+    fn block(keyword: &str, value: &str, options: &[(&str, &str)]) -> ConfigBlock {
+        ConfigBlock {
+            leading: Vec::new(),
+            header: Directive::new("", keyword, " ", value),
+            body: options
+                .iter()
+                .map(|(k, v)| ConfigLine::Directive(Directive::new("    ", k, " ", v)))
+                .collect(),
+        }
+    }
+
+    fn config(blocks: Vec<ConfigBlock>) -> ConfigSet {
+        ConfigSet {
+            files: vec![ConfigFile {
+                path: PathBuf::from("config"),
+                document: SshConfig {
+                    preamble: Vec::new(),
+                    blocks,
+                    trailing_newline: true,
+                    crlf: false,
+                },
+                includes: Vec::new(),
+                stamp: None,
+                load_diagnostics: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn flags_options_inside_match_blocks() {
+        let set = config(vec![
+            block("Host", "web", &[("HostName", "web.example")]),
+            block(
+                "Match",
+                "all",
+                &[("StrictHostKeyChecking", "no"), ("ForwardAgent", "yes"), ("Ciphers", "+aes128-cbc")],
+            ),
+        ]);
+        let findings = Auditor::run(&set, &set.hosts(), &AuditSettings::default(), &NoKeys);
+
+        let mut messages: Vec<(&str, Option<usize>, &str)> = findings
+            .iter()
+            .map(|f| (f.subject.as_str(), f.block_index, f.message.split(':').next().unwrap()))
+            .collect();
+        messages.sort();
+        assert_eq!(
+            messages,
+            vec![
+                ("Match all", Some(1), "Ciphers enables deprecated aes128-cbc"),
+                ("Match all", Some(1), "ForwardAgent yes"),
+                ("Match all", Some(1), "StrictHostKeyChecking no"),
+            ]
+        );
+    }
+
+    #[test]
+    fn safe_match_blocks_are_quiet() {
+        let set = config(vec![block("Match", "host *.corp", &[("StrictHostKeyChecking", "yes")])]);
+        assert!(Auditor::run(&set, &set.hosts(), &AuditSettings::default(), &NoKeys).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quoted_values_in_match_blocks_are_checked() {
+        use crate::repositories::ssh_repository::fixture_home;
+        use crate::repositories::{FileKeyRepository, SshRepository};
+        use std::os::unix::fs::PermissionsExt;
+
+        let config = "Match all\n    IdentityFile \"~/.ssh/id web\"\n    Ciphers \"+aes128-cbc\"\n";
+        let key = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keys/ed25519"));
+        let repo = fixture_home("audit-quoted", &[(".ssh/config", config), (".ssh/id web", key)]);
+        let home = repo.config_path().parent().unwrap().parent().unwrap().to_path_buf();
+        let key_path = home.join(".ssh/id web");
+        std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let set = repo.load_all();
+        let keys = FileKeyRepository::with_home(home);
+        let findings = Auditor::run(&set, &set.hosts(), &AuditSettings::default(), &keys);
+
+        let messages: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|f| (f.subject.as_str(), f.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "Match all",
+                    &*format!(
+                        "~/.ssh/id web has mode 0644; ssh refuses keys readable by others (chmod 600 {})",
+                        key_path.display()
+                    )
+                ),
+                ("Match all", "Ciphers enables deprecated aes128-cbc"),
+            ]
+        );
+    }
+}
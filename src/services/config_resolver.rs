@@ -0,0 +1,313 @@
+use crate::models::pattern::wildcard_match;
+use crate::models::ssh_config::split_arguments;
+use crate::models::ssh_host::is_repeatable;
+use crate::models::{
+    ConfigSet, EffectiveConfig, EffectiveOption, MatchRule, OptionSource, MAX_INCLUDE_DEPTH,
+};
+
+/// Computes the effective options for an alias the way ssh reads its config:
+/// every file in order with `Include`s inlined, applying each matching
+/// `Host`/`Match` block and keeping the first value seen for each keyword.
+pub struct ConfigResolver<'a> {
+    set: &'a ConfigSet,
+    alias: String,
+    local_user: String,
+    result: EffectiveConfig,
+}
+
+impl<'a> ConfigResolver<'a> {
+    pub fn resolve(set: &'a ConfigSet, alias: &str) -> EffectiveConfig {
+        let local_user = std::env::var("USER")
+            .or_else(|_| std::env::var("LOGNAME"))
+            .unwrap_or_default();
+
+        let mut resolver = Self {
+            set,
+            alias: alias.to_string(),
+            local_user,
+            result: EffectiveConfig {
+                alias: alias.to_string(),
+                ..EffectiveConfig::default()
+            },
+        };
+
+        resolver.walk_file(0, true, 0);
+        resolver.fill_defaults();
+        resolver.result
+    }
+
+    fn walk_file(&mut self, file_index: usize, mut active: bool, depth: usize) {
+        let set = self.set;
+        let Some(file) = set.files.get(file_index) else {
+            return;
+        };
+        if depth > MAX_INCLUDE_DEPTH {
+            return;
+        }
+
+        let mut block: Option<String> = None;
+
+        for (line, directive) in file.document.numbered_directives() {
+            if directive.is("host") {
+                active = pattern_list_matches(&split_arguments(&directive.value), &self.alias);
+                block = Some(format!("Host {}", directive.value));
+                continue;
+            }
+            if directive.is("match") {
+                active = self.match_applies(&directive.value, file_index, line);
+                block = Some(format!("Match {}", directive.value));
+                continue;
+            }
+            if !active || directive.value.is_empty() {
+                continue;
+            }
+
+            if directive.is("include") {
+                let included = file
+                    .includes
+                    .iter()
+                    .find(|(l, _)| *l == line)
+                    .map(|(_, files)| files.as_slice())
+                    .unwrap_or_default();
+                for &index in included {
+                    self.walk_file(index, true, depth + 1);
+                }
+                continue;
+            }
+
+            self.record(
+                &directive.keyword,
+                &directive.value,
+                OptionSource::Line {
+                    file_index,
+                    line,
+                    block: block.clone(),
+                },
+            );
+        }
+    }
+
+    fn match_applies(&mut self, value: &str, file_index: usize, line: usize) -> bool {
+        let mut applies = true;
+
+        for criterion in MatchRule::parse_criteria(value) {
+            let argument = criterion.argument.as_deref().unwrap_or("");
+            let list: Vec<&str> = argument.split(',').collect();
+
+            let matched = match criterion.keyword.as_str() {
+                "all" => true,
+                "host" => pattern_list_matches(&list, self.current("hostname", &self.alias)),
+                "originalhost" => pattern_list_matches(&list, &self.alias),
+                "user" => pattern_list_matches(&list, self.current("user", &self.local_user)),
+                "localuser" => pattern_list_matches(&list, &self.local_user),
+                "tagged" => pattern_list_matches(&list, self.current("tag", "")),
+                other => {
+                    let file = self
+                        .set
+                        .path_of(file_index)
+                        .and_then(|p| p.file_name())
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    self.result.notes.push(format!(
+                        "{}:{} `Match {}` can't be evaluated offline; treated as not matching",
+                        file, line, other
+                    ));
+                    return false;
+                }
+            };
+
+            if matched == criterion.negated {
+                applies = false;
+            }
+        }
+
+        applies
+    }
+
+    fn current<'s>(&'s self, keyword: &str, fallback: &'s str) -> &'s str {
+        self.result.value_of(keyword).unwrap_or(fallback)
+    }
+
+    fn record(&mut self, keyword: &str, value: &str, source: OptionSource) {
+        if !is_repeatable(keyword) && self.result.value_of(keyword).is_some() {
+            return;
+        }
+
+        self.result.options.push(EffectiveOption {
+            keyword: keyword.to_string(),
+            value: value.to_string(),
+            source,
+        });
+    }
+
+    fn fill_defaults(&mut self) {
+        let defaults = [
+            ("HostName", self.alias.clone()),
+            ("User", self.local_user.clone()),
+            ("Port", "22".to_string()),
+        ];
+
+        for (keyword, value) in defaults {
+            if self.result.value_of(keyword).is_none() {
+                self.record(keyword, &value, OptionSource::Default);
+            }
+        }
+    }
+}
+
+/// ssh's pattern-list rule: any negated match rejects, otherwise at least
+/// one positive pattern has to match.
+pub fn pattern_list_matches<S: AsRef<str>>(patterns: &[S], name: &str) -> bool {
+    let name = name.to_lowercase();
+    let mut matched = false;
+
+    for pattern in patterns {
+        let pattern = pattern.as_ref().to_lowercase();
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, &name) => return false,
+            Some(_) => {}
+            None if wildcard_match(&pattern, &name) => matched = true,
+            None => {}
+        }
+    }
+
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::ssh_repository::fixture_home;
+    use crate::repositories::SshRepository;
+
+    fn resolve(name: &str, files: &[(&str, &str)], alias: &str) -> EffectiveConfig {
+        let set = fixture_home(name, files).load_all();
+        ConfigResolver::resolve(&set, alias)
+    }
+
+    fn values(config: &EffectiveConfig, keyword: &str) -> Vec<String> {
+        config
+            .options
+            .iter()
+            .filter(|o| o.keyword.eq_ignore_ascii_case(keyword))
+            .map(|o| o.value.clone())
+            .collect()
+    }
+
+    fn line_of(config: &EffectiveConfig, keyword: &str) -> Option<(usize, usize)> {
+        config
+            .options
+            .iter()
+            .find(|o| o.keyword.eq_ignore_ascii_case(keyword))
+            .and_then(|o| match o.source {
+                OptionSource::Line { file_index, line, .. } => Some((file_index, line)),
+                OptionSource::Default => None,
+            })
+    }
+
+    #[test]
+    fn first_value_wins_over_later_wildcards() {
+        let config = "Host web\n    User deploy\n    Port 2222\n\nHost *\n    User root\n    Compression yes\n";
+        let web = resolve("resolve-first", &[(".ssh/config", config)], "web");
+
+        assert_eq!(web.value_of("user"), Some("deploy"));
+        assert_eq!(web.value_of("port"), Some("2222"));
+        assert_eq!(web.value_of("compression"), Some("yes"));
+        assert_eq!(web.value_of("hostname"), Some("web"));
+        assert_eq!(values(&web, "user"), vec!["deploy"]);
+        assert_eq!(line_of(&web, "user"), Some((0, 2)));
+        assert_eq!(line_of(&web, "hostname"), None);
+
+        let other = resolve("resolve-first-other", &[(".ssh/config", config)], "db");
+        assert_eq!(other.value_of("user"), Some("root"));
+        assert_eq!(other.value_of("port"), Some("22"));
+    }
+
+    #[test]
+    fn repeatable_keywords_accumulate_in_order() {
+        let config = "Host web\n    IdentityFile ~/.ssh/web\n    LocalForward 8080 localhost:80\n\n\
+                      Host *\n    IdentityFile ~/.ssh/id_ed25519\n    LocalForward 9090 localhost:90\n";
+        let web = resolve("resolve-lists", &[(".ssh/config", config)], "web");
+
+        assert_eq!(values(&web, "IdentityFile"), vec!["~/.ssh/web", "~/.ssh/id_ed25519"]);
+        assert_eq!(values(&web, "LocalForward"), vec!["8080 localhost:80", "9090 localhost:90"]);
+    }
+
+    #[test]
+    fn includes_are_read_where_they_appear() {
+        let files = [
+            (
+                ".ssh/config",
+                "Include defaults.conf\nHost web\n    Include web.conf\n    User outer\nHost *\n    Port 2200\n",
+            ),
+            (".ssh/defaults.conf", "Host db\n    Port 2000\nHost *\n    Compression yes\n"),
+            (".ssh/web.conf", "User inner\nPort 2222\n"),
+        ];
+        let web = resolve("resolve-include", &files, "web");
+        assert_eq!(web.value_of("user"), Some("inner"));
+        assert_eq!(line_of(&web, "user"), Some((2, 1)));
+        assert_eq!(web.value_of("port"), Some("2222"));
+        assert_eq!(web.value_of("compression"), Some("yes"));
+
+        // The include inside `Host web` is skipped for other hosts, and the
+        // included file's blocks end with the file.
+        let db = resolve("resolve-include-db", &files, "db");
+        assert_eq!(db.value_of("port"), Some("2000"));
+        assert_eq!(line_of(&db, "port"), Some((1, 2)));
+        assert_ne!(db.value_of("user"), Some("inner"));
+        assert_ne!(db.value_of("user"), Some("outer"));
+    }
+
+    #[test]
+    fn negated_patterns_exclude_a_host() {
+        let config = "Host * !bastion\n    ProxyJump bastion\n\nHost *.corp !legacy.corp\n    User corp\n";
+        let files = [(".ssh/config", config)];
+
+        assert_eq!(resolve("resolve-neg-a", &files, "web").value_of("proxyjump"), Some("bastion"));
+        assert_eq!(resolve("resolve-neg-b", &files, "bastion").value_of("proxyjump"), None);
+        assert_eq!(resolve("resolve-neg-c", &files, "app.corp").value_of("user"), Some("corp"));
+        assert_ne!(resolve("resolve-neg-d", &files, "legacy.corp").value_of("user"), Some("corp"));
+    }
+
+    #[test]
+    fn match_criteria_use_the_values_seen_so_far() {
+        let config = "Host web\n    HostName web.example.com\n    User deploy\n    Tag prod\n\n\
+                      Match host *.example.com\n    Port 2222\n\n\
+                      Match originalhost web user deploy\n    Compression yes\n\n\
+                      Match tagged prod\n    LogLevel ERROR\n\n\
+                      Match originalhost web.example.com\n    ForwardAgent yes\n\n\
+                      Match !user deploy\n    BatchMode yes\n";
+        let web = resolve("resolve-match", &[(".ssh/config", config)], "web");
+
+        assert_eq!(web.value_of("port"), Some("2222"));
+        assert_eq!(web.value_of("compression"), Some("yes"));
+        assert_eq!(web.value_of("loglevel"), Some("ERROR"));
+        assert_eq!(web.value_of("forwardagent"), None);
+        assert_eq!(web.value_of("batchmode"), None);
+        assert!(web.notes.is_empty());
+
+        let db = resolve("resolve-match-db", &[(".ssh/config", config)], "db");
+        assert_eq!(db.value_of("port"), Some("22"));
+        assert_eq!(db.value_of("loglevel"), None);
+    }
+
+    #[test]
+    fn offline_only_match_criteria_leave_a_note() {
+        let config = "Match exec \"test -f /tmp/x\"\n    User exec\n\n\
+                      Match canonical\n    User canonical\n\n\
+                      Match final all\n    User final\n";
+        let web = resolve("resolve-notes", &[(".ssh/config", config)], "web");
+
+        assert_ne!(web.value_of("user"), Some("exec"));
+        assert_ne!(web.value_of("user"), Some("canonical"));
+        assert_ne!(web.value_of("user"), Some("final"));
+        assert_eq!(
+            web.notes,
+            vec![
+                "config:1 `Match exec` can't be evaluated offline; treated as not matching",
+                "config:4 `Match canonical` can't be evaluated offline; treated as not matching",
+                "config:7 `Match final` can't be evaluated offline; treated as not matching",
+            ]
+        );
+    }
+}
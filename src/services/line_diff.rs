@@ -0,0 +1,82 @@
+/// One line of a diff between two versions of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+    /// Unchanged lines folded away, with how many were skipped.
+    Skipped(usize),
+}
+
+/// Unchanged lines kept around each change when folding.
+const CONTEXT: usize = 2;
+
+/// Line diff of `old` against `new` based on the longest common
+/// subsequence. Long unchanged runs are folded into `Skipped`.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+
+    fold_unchanged(lines)
+}
+
+fn fold_unchanged(lines: Vec<DiffLine>) -> Vec<DiffLine> {
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| !matches!(l, DiffLine::Same(_)))
+        .map(|(i, _)| i)
+        .collect();
+
+    let near_change = |i: usize| {
+        changed
+            .iter()
+            .any(|&c| i + CONTEXT >= c && i <= c + CONTEXT)
+    };
+
+    let mut folded = Vec::new();
+    let mut skipped = 0;
+    for (i, line) in lines.into_iter().enumerate() {
+        if matches!(line, DiffLine::Same(_)) && !near_change(i) {
+            skipped += 1;
+            continue;
+        }
+        if skipped > 0 {
+            folded.push(DiffLine::Skipped(skipped));
+            skipped = 0;
+        }
+        folded.push(line);
+    }
+    if skipped > 0 {
+        folded.push(DiffLine::Skipped(skipped));
+    }
+    folded
+}
@@ -0,0 +1,177 @@
+use crate::models::keyword_catalog::{deprecation, lookup, suggest};
+use crate::models::pattern::wildcard_match;
+use crate::models::ssh_config::split_arguments;
+use crate::models::{ConfigSet, Diagnostic, Severity};
+
+/// Checks every directive of the config set against the keyword catalog:
+/// unknown or misspelled keywords, deprecated ones, and values of the
+/// wrong type.
+pub struct Linter;
+
+impl Linter {
+    /// Parse diagnostics plus lint findings, one entry per line at most
+    /// (a parse problem wins over a lint finding for the same line).
+    pub fn diagnose(set: &ConfigSet) -> Vec<Diagnostic> {
+        let mut out = set.diagnostics();
+        for finding in Self::lint(set) {
+            let reported = out
+                .iter()
+                .any(|d| d.file_index == finding.file_index && d.line == finding.line);
+            if !reported {
+                out.push(finding);
+            }
+        }
+
+        out.sort_by_key(|d| (d.file_index, d.line));
+        out
+    }
+
+    pub fn lint(set: &ConfigSet) -> Vec<Diagnostic> {
+        let ignored = Self::ignored_patterns(set);
+        let mut out = Vec::new();
+
+        for (file_index, file) in set.files.iter().enumerate() {
+            for (line, block_index, directive) in file.document.located_directives() {
+                if directive.value.is_empty() || directive.is("host") || directive.is("match") {
+                    continue;
+                }
+
+                let finding = match lookup(&directive.keyword) {
+                    Some(spec) => spec
+                        .value
+                        .check(&split_arguments(&directive.value))
+                        .err()
+                        .map(|reason| (Severity::Error, format!("{}: {}", spec.name, reason))),
+                    None => Self::unknown_keyword(&directive.keyword, &ignored),
+                };
+
+                if let Some((severity, message)) = finding {
+                    out.push(Diagnostic {
+                        file_index,
+                        line,
+                        block_index,
+                        severity,
+                        message,
+                    });
+                }
+            }
+        }
+
+        out
+    }
+
+    fn unknown_keyword(keyword: &str, ignored: &[String]) -> Option<(Severity, String)> {
+        if let Some(hint) = deprecation(keyword) {
+            return Some((Severity::Warning, format!("{} is deprecated: {}", keyword, hint)));
+        }
+
+        let lower = keyword.to_lowercase();
+        if ignored.iter().any(|pattern| wildcard_match(pattern, &lower)) {
+            return None;
+        }
+
+        let message = match suggest(keyword) {
+            Some(known) => format!("unknown keyword '{}', did you mean {}?", keyword, known),
+            None => format!("unknown keyword '{}'", keyword),
+        };
+        Some((Severity::Error, message))
+    }
+
+    /// Patterns from `IgnoreUnknown` lines; ssh skips unknown keywords
+    /// matching them instead of failing.
+    fn ignored_patterns(set: &ConfigSet) -> Vec<String> {
+        set.files
+            .iter()
+            .flat_map(|file| file.document.numbered_directives())
+            .filter(|(_, d)| d.is("ignoreunknown"))
+            .flat_map(|(_, d)| {
+                d.value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::ssh_repository::fixture_home;
+    use crate::repositories::SshRepository;
+
+    fn diagnose(name: &str, files: &[(&str, &str)]) -> Vec<(usize, usize, Severity, String)> {
+        let set = fixture_home(name, files).load_all();
+        Linter::diagnose(&set)
+            .into_iter()
+            .map(|d| (d.file_index, d.line, d.severity, d.message))
+            .collect()
+    }
+
+    #[test]
+    fn clean_config_has_no_findings() {
+        let config = "ControlMaster true\nHost web\n    StrictHostKeyChecking off\n    RequestTTY false\n";
+        assert!(diagnose("lint-clean", &[(".ssh/config", config)]).is_empty());
+    }
+
+    #[test]
+    fn bad_values_are_errors() {
+        let config = "Host web\n    Compression maybe\n    ConnectTimeout soon\n";
+        assert_eq!(
+            diagnose("lint-values", &[(".ssh/config", config)]),
+            vec![
+                (0, 2, Severity::Error, "Compression: 'maybe' is not yes or no".to_string()),
+                (
+                    0,
+                    3,
+                    Severity::Error,
+                    "ConnectTimeout: 'soon' is not a duration like 30, 5m or 1h30m".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keywords_are_errors_and_deprecated_ones_warnings() {
+        let config = "Host web\n    HostNmae web.example\n    Frobnicate yes\n    Protocol 2\n";
+        assert_eq!(
+            diagnose("lint-unknown", &[(".ssh/config", config)]),
+            vec![
+                (
+                    0,
+                    2,
+                    Severity::Error,
+                    "unknown keyword 'HostNmae', did you mean HostName?".to_string()
+                ),
+                (0, 3, Severity::Error, "unknown keyword 'Frobnicate'".to_string()),
+                (
+                    0,
+                    4,
+                    Severity::Warning,
+                    "Protocol is deprecated: SSH protocol 1 is gone; remove it".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignore_unknown_suppresses_matching_keywords_in_any_file() {
+        let files = [
+            (".ssh/config", "IgnoreUnknown UseKeyring,Vendor*\nInclude extra.conf\nHost web\n    UseKeyring yes\n"),
+            (".ssh/extra.conf", "Host db\n    VendorOption 1\n    Other 2\n    Protocol 2\n"),
+        ];
+        let found = diagnose("lint-ignore", &files);
+        assert_eq!(
+            found.iter().map(|(f, l, s, _)| (*f, *l, *s)).collect::<Vec<_>>(),
+            vec![(1, 3, Severity::Error), (1, 4, Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn parse_problems_win_over_lint_findings_on_the_same_line() {
+        let found = diagnose("lint-parse", &[(".ssh/config", "Host web\n    Port abc\n")]);
+        assert_eq!(found.len(), 1, "{:?}", found);
+        assert_eq!((found[0].1, found[0].2), (2, Severity::Error));
+    }
+}
@@ -1,3 +1,15 @@
 pub mod app_service;
+pub mod auditor;
+pub mod config_resolver;
+pub mod line_diff;
+pub mod linter;
+pub mod prober;
+pub mod ssh_launcher;
 
 pub use app_service::AppService;
+pub use auditor::Auditor;
+pub use config_resolver::ConfigResolver;
+pub use line_diff::{diff_lines, DiffLine};
+pub use linter::Linter;
+pub use prober::Prober;
+pub use ssh_launcher::{run_ssh, run_ssh_capturing_stderr};
@@ -0,0 +1,237 @@
+use std::io::{BufRead, BufReader, Read};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::models::{ProbeResult, ProbeStatus, ProbeTarget};
+
+/// Checks TCP reachability on a fixed pool of worker threads, so at most
+/// `concurrency` connects are in flight. Results are collected with
+/// `try_result` without blocking the caller.
+pub struct Prober {
+    jobs: Sender<ProbeTarget>,
+    results: Receiver<ProbeResult>,
+}
+
+impl Prober {
+    pub fn new(concurrency: usize, timeout: Duration) -> Self {
+        let (jobs, job_queue) = mpsc::channel::<ProbeTarget>();
+        let (result_sender, results) = mpsc::channel();
+        let job_queue = Arc::new(Mutex::new(job_queue));
+
+        for _ in 0..concurrency.max(1) {
+            let queue = Arc::clone(&job_queue);
+            let sender = result_sender.clone();
+            thread::spawn(move || loop {
+                let next = queue.lock().ok().and_then(|q| q.recv().ok());
+                let Some(target) = next else { break };
+                let (status, banner) = probe(&target, timeout);
+                let result = ProbeResult {
+                    alias: target.alias,
+                    status,
+                    banner,
+                };
+                if sender.send(result).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Self { jobs, results }
+    }
+
+    pub fn submit(&self, targets: Vec<ProbeTarget>) {
+        for target in targets {
+            let _ = self.jobs.send(target);
+        }
+    }
+
+    pub fn try_result(&self) -> Option<ProbeResult> {
+        self.results.try_recv().ok()
+    }
+}
+
+/// Longest identification line RFC 4253 allows, CR LF included.
+const MAX_BANNER_LINE: usize = 255;
+/// Lines a server may send before its identification, counted generously.
+const MAX_PRE_BANNER_BYTES: usize = 8192;
+
+/// One TCP connect to `host:port`, trying each resolved address in turn,
+/// then the server's identification line. Nothing is sent, so no login
+/// is attempted. The latency covers the connect only.
+pub fn probe(target: &ProbeTarget, timeout: Duration) -> (ProbeStatus, Option<String>) {
+    let addresses = match (target.host.as_str(), target.port).to_socket_addrs() {
+        Ok(addresses) => addresses.collect::<Vec<_>>(),
+        Err(e) => {
+            let status = ProbeStatus::Unreachable(format!("cannot resolve {}: {}", target.host, e));
+            return (status, None);
+        }
+    };
+
+    let mut last_error = format!("{} has no addresses", target.host);
+    for address in addresses {
+        let started = Instant::now();
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => {
+                let latency = started.elapsed();
+                return (ProbeStatus::Reachable(latency), read_banner(stream, timeout));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                last_error = format!("no answer within {} ms", timeout.as_millis())
+            }
+            Err(e) => last_error = e.to_string().to_lowercase(),
+        }
+    }
+    (ProbeStatus::Unreachable(last_error), None)
+}
+
+/// Servers may send other lines first; the identification is the first
+/// line starting with `SSH-`.
+fn read_banner(stream: TcpStream, timeout: Duration) -> Option<String> {
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    let mut reader = BufReader::new(stream.take(MAX_PRE_BANNER_BYTES as u64));
+
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).ok()? == 0 {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\r', '\n']);
+        if text.starts_with("SSH-") && line.len() <= MAX_BANNER_LINE {
+            return Some(text.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn target(port: u16) -> ProbeTarget {
+        ProbeTarget {
+            alias: "local".into(),
+            host: "127.0.0.1".into(),
+            port,
+        }
+    }
+
+    /// Accepts one connection and writes `greeting`, or nothing when empty.
+    fn fake_server(greeting: &str) -> u16 {
+        let greeting = greeting.to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let _ = stream.write_all(greeting.as_bytes());
+                thread::sleep(TIMEOUT * 3);
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn listener_on_localhost_is_reachable() {
+        let port = fake_server("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3\r\n");
+        let (status, banner) = probe(&target(port), TIMEOUT);
+
+        assert!(matches!(status, ProbeStatus::Reachable(_)), "{status:?}");
+        assert_eq!(banner.as_deref(), Some("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3"));
+    }
+
+    #[test]
+    fn banner_follows_pre_banner_lines() {
+        let port = fake_server("Welcome to the lab\r\nAuthorized use only\r\nSSH-2.0-dropbear_2022.83\r\n");
+        let (status, banner) = probe(&target(port), TIMEOUT);
+
+        assert!(matches!(status, ProbeStatus::Reachable(_)), "{status:?}");
+        assert_eq!(banner.as_deref(), Some("SSH-2.0-dropbear_2022.83"));
+    }
+
+    #[test]
+    fn silent_server_gives_no_banner() {
+        let port = fake_server("");
+        let started = Instant::now();
+        let (status, banner) = probe(&target(port), TIMEOUT);
+
+        assert!(matches!(status, ProbeStatus::Reachable(_)), "{status:?}");
+        assert_eq!(banner, None);
+        assert!(started.elapsed() < TIMEOUT * 3);
+    }
+
+    #[test]
+    fn overlong_identification_is_ignored() {
+        let port = fake_server(&format!("SSH-2.0-{}\r\n", "x".repeat(300)));
+        let (_, banner) = probe(&target(port), TIMEOUT);
+
+        assert_eq!(banner, None);
+    }
+
+    #[test]
+    fn closed_port_is_unreachable() {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let (status, banner) = probe(&target(port), TIMEOUT);
+
+        assert!(matches!(status, ProbeStatus::Unreachable(_)), "{status:?}");
+        assert_eq!(banner, None);
+    }
+
+    #[test]
+    fn pool_reports_every_submitted_target() {
+        let open = fake_server("");
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let prober = Prober::new(2, TIMEOUT);
+        prober.submit(vec![
+            ProbeTarget { alias: "open".into(), ..target(open) },
+            ProbeTarget { alias: "closed".into(), ..target(closed) },
+        ]);
+
+        let mut results = Vec::new();
+        let deadline = Instant::now() + TIMEOUT * 6;
+        while results.len() < 2 && Instant::now() < deadline {
+            match prober.try_result() {
+                Some(result) => results.push(result),
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        results.sort_by(|a, b| a.alias.cmp(&b.alias));
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0].status, ProbeStatus::Unreachable(_)));
+        assert!(matches!(results[1].status, ProbeStatus::Reachable(_)));
+    }
+
+    /// A listener whose accept queue is full drops new SYNs, so the
+    /// connect itself runs into the timeout.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn listener_that_never_accepts_times_out() {
+        use std::os::fd::AsRawFd;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // SAFETY: re-listening on a socket we own only changes its backlog.
+        assert_eq!(unsafe { libc::listen(listener.as_raw_fd(), 0) }, 0);
+
+        let mut queued = Vec::new();
+        while let Ok(stream) = TcpStream::connect_timeout(&address, Duration::from_millis(200)) {
+            queued.push(stream);
+            assert!(queued.len() < 64, "accept queue never filled");
+        }
+
+        let started = Instant::now();
+        let (status, banner) = probe(&target(address.port()), TIMEOUT);
+
+        assert_eq!(status, ProbeStatus::Unreachable(format!("no answer within {} ms", TIMEOUT.as_millis())));
+        assert_eq!(banner, None);
+        assert!(started.elapsed() >= TIMEOUT);
+    }
+}
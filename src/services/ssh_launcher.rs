@@ -0,0 +1,57 @@
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::models::LaunchCommand;
+
+/// Keep only the tail of a chatty session's stderr; failures are at the end.
+const MAX_CAPTURED: usize = 16 * 1024;
+
+/// After ssh exits, how long to wait for the rest of its stderr. A process
+/// ssh left behind (e.g. a `ControlPersist` master) may hold the pipe open.
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Runs the launch command in the foreground with the terminal attached.
+pub fn run_ssh(command: &LaunchCommand) -> io::Result<ExitStatus> {
+    Command::new(&command.program).args(&command.args).status()
+}
+
+/// Like `run_ssh`, but also returns what ssh wrote to stderr so a failure
+/// can be diagnosed. Stderr is still copied to the terminal as it arrives;
+/// prompts go to the tty and are unaffected.
+pub fn run_ssh_capturing_stderr(command: &LaunchCommand) -> io::Result<(ExitStatus, String)> {
+    let mut child = Command::new(&command.program)
+        .args(&command.args)
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut pipe = child.stderr.take().expect("stderr is piped");
+
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let (done_tx, done_rx) = mpsc::channel();
+    let sink = Arc::clone(&captured);
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut stderr = io::stderr();
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            stderr.write_all(&buf[..n]).ok();
+            stderr.flush().ok();
+
+            let mut captured = sink.lock().unwrap();
+            captured.extend_from_slice(&buf[..n]);
+            let excess = captured.len().saturating_sub(MAX_CAPTURED);
+            captured.drain(..excess);
+        }
+        done_tx.send(()).ok();
+    });
+
+    let status = child.wait()?;
+    done_rx.recv_timeout(DRAIN_TIMEOUT).ok();
+    let text = String::from_utf8_lossy(&captured.lock().unwrap()).into_owned();
+    Ok((status, text))
+}
@@ -1,5 +1,7 @@
 pub mod panels;
+pub mod picker;
 pub mod popups;
 pub mod renderer;
 
+pub use picker::{render_picker, PickerBackend, PICKER_CHROME};
 pub use renderer::render;
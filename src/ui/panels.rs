@@ -1,3 +1,4 @@
+use chrono::Local;
 use ratatui::{
     layout::{Alignment, Constraint, Margin, Rect},
     style::{Modifier, Style},
@@ -9,7 +10,8 @@ use ratatui::{
     Frame,
 };
 
-use crate::models::Mode;
+use crate::models::history::ago_display;
+use crate::models::{Marker, Mode, OptionSource, ProbeStatus, Severity, SortMode, TrustStatus};
 use crate::services::AppService;
 
 pub fn draw_header(frame: &mut Frame, app: &AppService, area: Rect) {
@@ -24,12 +26,26 @@ pub fn draw_header(frame: &mut Frame, app: &AppService, area: Rect) {
 
 
     let transparency_badge = if t.transparent { " [T]" } else { "" };
+    let included_badge = match app.config_file_count() {
+        0 | 1 => String::new(),
+        n => format!(" (+{} included)", n - 1),
+    };
+    let problems = app.diagnostics();
+    let has_errors = problems.iter().any(|d| d.severity == Severity::Error);
+    let problem_badge = match problems.len() {
+        0 => String::new(),
+        n => format!("  {} {} problem(s) [!]", if has_errors { "✖" } else { "⚠" }, n),
+    };
+    let problem_style = if has_errors { t.error() } else { t.warning() };
 
     let line = Line::from(vec![
         Span::styled("  SSH ", Style::default().fg(t.accent.to_color()).add_modifier(Modifier::BOLD)),
         Span::styled("Manager ", Style::default().fg(t.accent_secondary.to_color()).add_modifier(Modifier::BOLD)),
         Span::styled(format!("  {} hosts", app.host_count()), t.muted()),
+        Span::styled(format!("  {} patterns", app.pattern_count()), t.muted()),
         Span::styled(format!("  {}", app.config_path_display()), t.muted()),
+        Span::styled(included_badge, t.muted()),
+        Span::styled(problem_badge, problem_style),
         Span::styled(format!("  {}{}", t.name, transparency_badge), t.muted()),
     ]).centered();
 
@@ -61,12 +77,16 @@ pub fn draw_host_list(frame: &mut Frame, app: &AppService, area: Rect) {
 
     let border = if is_focused { t.border_focused() } else { t.border() };
     let title_style = if is_focused { t.title() } else { t.muted() };
+    let list_title = match app.sort_mode {
+        SortMode::Config => " ~/.ssh/config ".to_string(),
+        mode => format!(" ~/.ssh/config · {} ", mode.label()),
+    };
 
     let block = Block::default()
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
         .border_style(border)
-        .title(Span::styled(" ~/.ssh/config ", title_style))
+        .title(Span::styled(list_title, title_style))
         .padding(Padding::new(1, 1, 0, 0))
         .style(t.base());
 
@@ -85,33 +105,104 @@ pub fn draw_host_list(frame: &mut Frame, app: &AppService, area: Rect) {
         return;
     }
 
-    let header = Row::new(["", "Alias", "HostName", "User"])
+    let header = Row::new(["", "", "", "Alias", "HostName", "User"])
         .style(t.header())
         .height(1);
 
-    let rows: Vec<Row> = entries
-        .iter()
-        .enumerate()
-        .map(|(i, (_, host))| {
-            let marker = if i == app.cursor { "▸" } else { " " };
-            let style = if i == app.cursor { t.selected() } else { t.base() };
+    let mut rows: Vec<Row> = Vec::with_capacity(entries.len() + 1);
+    for (i, (_, host)) in entries.iter().enumerate() {
+        let starts_patterns = host.is_pattern() && (i == 0 || !entries[i - 1].1.is_pattern());
+        if starts_patterns {
+            rows.push(
+                Row::new([
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from(""),
+                    Cell::from("── Defaults / Patterns ──"),
+                ])
+                    .style(t.accent_secondary())
+                    .height(1),
+            );
+        }
+
+        let marker = if i == app.cursor { "▸" } else { " " };
+        let style = if i == app.cursor {
+            t.selected()
+        } else if host.is_pattern() {
+            t.muted()
+        } else {
+            t.base()
+        };
+        let target = if host.is_pattern() { "(pattern)" } else { host.display_host() };
+
+        let alias_cell = match app.host_severity(host) {
+            Some(severity) => {
+                let badge_style = match severity {
+                    Severity::Error => t.bold_error(),
+                    Severity::Warning => t.bold_warning(),
+                };
+                Cell::from(Line::from(vec![
+                    Span::raw(host.alias()),
+                    Span::styled(format!(" {}", severity.symbol()), badge_style),
+                ]))
+            }
+            None => Cell::from(host.alias()),
+        };
+
+        let probe_cell = match app.probe_status(host) {
+            Some(status) => {
+                let dot_style = match status {
+                    ProbeStatus::Reachable(_) => t.success(),
+                    ProbeStatus::Unreachable(_) => t.error(),
+                    ProbeStatus::Pending => t.warning(),
+                    ProbeStatus::Skipped(_) => t.muted(),
+                };
+                let dot = if matches!(status, ProbeStatus::Skipped(_)) { "○" } else { "●" };
+                let mut spans = vec![
+                    Span::styled(dot, dot_style),
+                    Span::styled(format!(" {}", status.short_display()), t.muted()),
+                ];
+                if app.host_banner(host).is_some_and(|b| b.is_changed()) {
+                    spans.push(Span::styled(" ⚑", t.bold_warning()));
+                }
+                Cell::from(Line::from(spans))
+            }
+            None => Cell::from(""),
+        };
 
+        let trust_cell = match app.host_trust(host) {
+            Some(trust) => {
+                let trust_style = match trust.status {
+                    TrustStatus::Trusted => t.success(),
+                    TrustStatus::Unknown => t.muted(),
+                    TrustStatus::Revoked => t.bold_error(),
+                };
+                Cell::from(Span::styled(trust.status.symbol(), trust_style))
+            }
+            None => Cell::from(""),
+        };
+
+        rows.push(
             Row::new([
                 Cell::from(marker),
-                Cell::from(host.alias.as_str()),
-                Cell::from(host.display_host()),
+                trust_cell,
+                probe_cell,
+                alias_cell,
+                Cell::from(target),
                 Cell::from(host.user.as_str()),
             ])
             .style(style)
-            .height(1)
-        })
-        .collect();
+            .height(1),
+        );
+    }
 
     let widths = [
         Constraint::Length(2),
-        Constraint::Percentage(30),
-        Constraint::Percentage(40),
+        Constraint::Length(2),
+        Constraint::Length(10),
         Constraint::Percentage(28),
+        Constraint::Percentage(36),
+        Constraint::Percentage(24),
     ];
 
     frame.render_widget(Table::new(rows, widths).header(header).block(block), area);
@@ -133,6 +224,11 @@ pub fn draw_host_list(frame: &mut Frame, app: &AppService, area: Rect) {
 pub fn draw_detail_panel(frame: &mut Frame, app: &AppService, area: Rect) {
     let t = &app.theme;
 
+    if app.show_effective {
+        draw_effective_panel(frame, app, area);
+        return;
+    }
+
     let block = Block::default()
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded)
@@ -156,19 +252,43 @@ pub fn draw_detail_panel(frame: &mut Frame, app: &AppService, area: Rect) {
     let value = t.base();
     let dim = t.muted();
 
-    let port_display = host.port.to_string();
-    let port_style = if host.has_custom_port() { value } else { dim };
+    let port_display = match &host.invalid_port {
+        Some(raw) => format!("{} (invalid)", raw),
+        None => host.port.to_string(),
+    };
+    let port_style = if host.invalid_port.is_some() {
+        t.error()
+    } else if host.has_custom_port() {
+        value
+    } else {
+        dim
+    };
 
     let user_display: &str = if host.user.is_empty() { "(default)" } else { &host.user };
     let user_style = if host.user.is_empty() { dim } else { value };
 
-    let key_display: &str = if host.has_identity_file() { &host.identity_file } else { "(default)" };
+    let key_display = host.identity_files.first().map_or("(default)", String::as_str);
     let key_style = if host.has_identity_file() { value } else { dim };
 
-    let ssh_command = host.as_ssh_command();
+    let ssh_command = app.launch_command(host).display();
+    let other_patterns = host.other_patterns().join(" ");
+    let source = app.source_display(host.file_index);
 
-    let mut lines = vec![
-        detail_row("Host          ", &host.alias, label, value),
+    let mut lines = Vec::new();
+    if host.is_pattern() {
+        lines.push(Line::from(Span::styled(
+            "Pattern block: supplies defaults to every matching host",
+            t.accent_secondary(),
+        )));
+        lines.push(Line::from(""));
+    }
+
+    lines.push(detail_row("Host          ", host.alias(), label, value));
+    if !other_patterns.is_empty() {
+        lines.push(detail_row("Also matches  ", &other_patterns, label, dim));
+    }
+
+    lines.extend([
         Line::from(""),
         detail_row("HostName      ", host.display_host(), label, value),
         Line::from(""),
@@ -177,7 +297,145 @@ pub fn draw_detail_panel(frame: &mut Frame, app: &AppService, area: Rect) {
         detail_row("User          ", user_display, label, user_style),
         Line::from(""),
         detail_row("IdentityFile  ", key_display, label, key_style),
-    ];
+    ]);
+    for extra_key in host.identity_files.iter().skip(1) {
+        lines.push(detail_row("              ", extra_key, label, value));
+    }
+
+    for (keyword, values) in host.lists().into_iter().skip(1) {
+        if values.is_empty() {
+            continue;
+        }
+        lines.push(Line::from(""));
+        for (i, v) in values.iter().enumerate() {
+            let formatted_key = if i == 0 {
+                format!("{:<14}", format!("{} ", keyword))
+            } else {
+                " ".repeat(14)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(formatted_key, label),
+                Span::styled(v.as_str(), value),
+            ]));
+        }
+    }
+
+    lines.push(Line::from(""));
+    lines.push(detail_row("Source        ", &source, label, dim));
+
+    if let Some(status) = app.probe_status(host) {
+        let status_style = match status {
+            ProbeStatus::Reachable(_) => t.success(),
+            ProbeStatus::Unreachable(_) => t.error(),
+            ProbeStatus::Pending | ProbeStatus::Skipped(_) => dim,
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Reachability  ", label),
+            Span::styled(status.describe(), status_style),
+        ]));
+    }
+
+    if let Some(record) = app.host_banner(host) {
+        let now = Local::now().naive_local();
+        lines.push(Line::from(vec![
+            Span::styled("SSH banner    ", label),
+            Span::styled(record.banner.as_str(), value),
+            Span::styled(format!(" (seen {})", ago_display(record.last_seen, now)), dim),
+        ]));
+        if let (Some(protocol), Some(software)) = (record.protocol(), record.software()) {
+            lines.push(Line::from(vec![
+                Span::styled("Server        ", label),
+                Span::styled(software, value),
+                Span::styled(format!(" (protocol {})", protocol), dim),
+            ]));
+        }
+        if let (true, Some(previous), Some(changed)) = (record.is_changed(), &record.previous, record.changed) {
+            lines.push(Line::from(vec![
+                Span::styled("Changed ⚑     ", t.bold_warning()),
+                Span::styled(
+                    format!("{} from {} (B accepts)", changed.format("%Y-%m-%d %H:%M"), previous),
+                    t.warning(),
+                ),
+            ]));
+        }
+    }
+
+    if !host.is_pattern() {
+        lines.push(Line::from(""));
+        match app.host_history(host) {
+            Some(history) => {
+                let now = Local::now().naive_local();
+                let last = history.last;
+                let times = if history.count == 1 { "time" } else { "times" };
+                lines.push(Line::from(vec![
+                    Span::styled("Connected     ", label),
+                    Span::styled(format!("{} {}, last ", history.count, times), value),
+                    Span::styled(last.started.format("%Y-%m-%d %H:%M").to_string(), value),
+                    Span::styled(format!(" ({})", ago_display(last.started, now)), dim),
+                ]));
+                let (exit_display, exit_style) = match last.exit_code {
+                    Some(0) => ("0".to_string(), t.success()),
+                    Some(code) => (code.to_string(), t.error()),
+                    None => ("none (ssh did not start or was killed)".to_string(), t.warning()),
+                };
+                lines.push(Line::from(vec![
+                    Span::styled("Last exit     ", label),
+                    Span::styled(exit_display, exit_style),
+                    Span::styled(format!("  after {}", duration_display(last.duration_secs)), dim),
+                ]));
+            }
+            None => lines.push(detail_row("Connected     ", "never", label, dim)),
+        }
+    }
+
+    if let Some(trust) = app.host_trust(host) {
+        let status_style = match trust.status {
+            TrustStatus::Trusted => t.success(),
+            TrustStatus::Unknown => dim,
+            TrustStatus::Revoked => t.bold_error(),
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Host key      ", label),
+            Span::styled(format!("{} {}", trust.status.symbol(), trust.status.label()), status_style),
+            Span::styled(format!(" as {}", trust.lookup_name), dim),
+        ]));
+        if trust.status == TrustStatus::Unknown {
+            lines.push(Line::from(Span::styled(
+                "Not in known_hosts: ssh will ask to confirm the key on first connect",
+                dim,
+            )));
+        }
+        for key in &trust.keys {
+            let (prefix, key_style) = match key.marker {
+                Some(Marker::Revoked) => ("revoked ", t.error()),
+                Some(Marker::CertAuthority) => ("CA ", value),
+                None => ("", value),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<14}", format!("{}{} ", prefix, key.algorithm)), key_style),
+                Span::styled(key.fingerprint.as_str(), key_style),
+                Span::styled(format!("  {}", app.known_hosts_source(key.file_index, key.line)), dim),
+            ]));
+        }
+    }
+
+    let problems = app.host_diagnostics(host);
+    if !problems.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled("── Problems ──", t.accent_secondary())));
+        for problem in problems {
+            let style = match problem.severity {
+                Severity::Error => t.error(),
+                Severity::Warning => t.warning(),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} line {:<5}", problem.severity.symbol(), problem.line), style),
+                Span::styled(problem.message.as_str(), value),
+            ]));
+        }
+    }
 
     if host.has_extra_options() {
         lines.push(Line::from(""));
@@ -191,7 +449,7 @@ pub fn draw_detail_panel(frame: &mut Frame, app: &AppService, area: Rect) {
         }
     }
 
-    if app.show_command {
+    if app.show_command && !host.is_pattern() {
         lines.push(Line::from(""));
         lines.push(detail_row(
             "Command       ",
@@ -207,6 +465,76 @@ pub fn draw_detail_panel(frame: &mut Frame, app: &AppService, area: Rect) {
     );
 }
 
+fn draw_effective_panel(frame: &mut Frame, app: &AppService, area: Rect) {
+    let t = &app.theme;
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(t.border())
+        .title(Span::styled(" Effective ", t.title()))
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let Some(effective) = app.effective_config() else {
+        frame.render_widget(
+            Paragraph::new("Select a host to resolve its options")
+                .style(t.muted())
+                .alignment(Alignment::Center)
+                .block(block),
+            area,
+        );
+        return;
+    };
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("ssh -G ", t.muted()),
+            Span::styled(effective.alias.clone(), t.bold_accent()),
+        ]),
+        Line::from(""),
+    ];
+
+    for option in &effective.options {
+        let origin = match &option.source {
+            OptionSource::Line { file_index, line, block } => format!(
+                "  ← {}:{}{}",
+                app.source_display(*file_index),
+                line,
+                block.as_ref().map(|b| format!(" ({})", b)).unwrap_or_default(),
+            ),
+            OptionSource::Default => "  ← default".to_string(),
+        };
+        let value_style = if option.source == OptionSource::Default { t.muted() } else { t.base() };
+
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<22}", option.keyword), t.bold_accent()),
+            Span::styled(option.value.clone(), value_style),
+            Span::styled(origin, t.muted()),
+        ]));
+    }
+
+    if !effective.notes.is_empty() {
+        lines.push(Line::from(""));
+        for note in &effective.notes {
+            lines.push(Line::from(Span::styled(note.clone(), t.warning())));
+        }
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines).block(block).wrap(Wrap { trim: true }),
+        area,
+    );
+}
+
+fn duration_display(secs: u64) -> String {
+    match secs {
+        s if s >= 3600 => format!("{}h {}m", s / 3600, s % 3600 / 60),
+        s if s >= 60 => format!("{}m {}s", s / 60, s % 60),
+        s => format!("{}s", s),
+    }
+}
+
 fn detail_row<'a>(label: &'a str, value: &'a str, label_style: Style, value_style: Style) -> Line<'a> {
     Line::from(vec![
         Span::styled(label, label_style),
@@ -227,7 +555,17 @@ pub fn draw_status_bar(frame: &mut Frame, app: &AppService, area: Rect) {
                 Mode::AddHost => "ADD",
                 Mode::EditHost(_) => "EDIT",
                 Mode::ConfirmDelete(_) => "DELETE",
+                Mode::ConfirmKnownHostsEdit(_) => "CONFIRM",
+                Mode::SaveConflict => "CONFLICT",
                 Mode::SelectTheme => "THEME",
+                Mode::MatchRules => "MATCH",
+                Mode::Diagnostics => "PROBLEMS",
+                Mode::Audit => "AUDIT",
+                Mode::Backups => "BACKUPS",
+                Mode::KnownHosts => "KNOWN HOSTS",
+                Mode::Keys => "KEYS",
+                Mode::SessionFailure => "SSH FAILED",
+                Mode::GenerateKey => "NEW KEY",
                 Mode::Help => "HELP",
             };
             (format!(" {} ", mode_label), t.status_bar())
@@ -249,10 +587,20 @@ pub fn draw_status_bar(frame: &mut Frame, app: &AppService, area: Rect) {
             Span::styled("e", k), Span::styled(" edit ", d), sep.clone(),
             Span::styled("d", k), Span::styled(" delete ", d), sep.clone(),
             Span::styled("c", k), Span::styled(" cmd ", d), sep.clone(),
+            Span::styled("v", k), Span::styled(" effective ", d), sep.clone(),
             Span::styled("/", k), Span::styled(" search ", d), sep.clone(),
+            Span::styled("s", k), Span::styled(" sort ", d), sep.clone(),
+            Span::styled("m", k), Span::styled(" match ", d), sep.clone(),
+            Span::styled("b", k), Span::styled(" backups ", d), sep.clone(),
+            Span::styled("!", k), Span::styled(" problems ", d), sep.clone(),
+            Span::styled("A", k), Span::styled(" audit ", d), sep.clone(),
+            Span::styled("K", k), Span::styled(" known hosts ", d), sep.clone(),
+            Span::styled("i", k), Span::styled(" keys ", d), sep.clone(),
             Span::styled("t", k), Span::styled(" themes ", d), sep.clone(),
             Span::styled("T", k), Span::styled(" transparent ", d), sep.clone(),
             Span::styled("r", k), Span::styled(" reload ", d), sep.clone(),
+            Span::styled("p", k), Span::styled(" ping ", d), sep.clone(),
+            Span::styled("B", k), Span::styled(" accept banner ", d), sep.clone(),
             Span::styled("?", k), Span::styled(" help ", d), sep.clone(),
             Span::styled("q", k), Span::styled(" quit ", d),
         ]).centered(),
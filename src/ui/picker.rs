@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{self, Read, Stderr, Write};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use ratatui::{
+    backend::{Backend, ClearType, CrosstermBackend, WindowSize},
+    buffer::Cell as BufferCell,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use crate::services::AppService;
+
+/// Rows the inline viewport needs besides the host list: prompt and hints.
+pub const PICKER_CHROME: u16 = 2;
+
+/// The `--pick` view. It is drawn inline below the shell prompt, so it
+/// paints no background and keeps to a prompt, the list and one hint line.
+pub fn render_picker(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = frame.size();
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+        .split(area);
+
+    let entries = app.visible_hosts();
+    let prompt = Line::from(vec![
+        Span::styled("> ", t.bold_accent()),
+        Span::raw(app.search_query.as_str()),
+        Span::styled("▎", Style::default().fg(t.input_cursor.to_color())),
+        Span::styled(
+            format!("  {}/{}", entries.len(), app.host_count()),
+            t.muted(),
+        ),
+    ]);
+    frame.render_widget(Paragraph::new(prompt), layout[0]);
+
+    let rows: Vec<Row> = entries
+        .iter()
+        .map(|(index, host)| {
+            let mark = if app.is_marked(*index) { "●" } else { " " };
+            Row::new([
+                Cell::from(Span::styled(mark, t.accent())),
+                Cell::from(host.alias()),
+                Cell::from(host.display_host()),
+                Cell::from(host.user.as_str()),
+            ])
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(1),
+        Constraint::Percentage(35),
+        Constraint::Percentage(40),
+        Constraint::Percentage(25),
+    ];
+    let mut state = TableState::default().with_selected((!entries.is_empty()).then_some(app.cursor));
+    frame.render_stateful_widget(
+        Table::new(rows, widths).highlight_style(t.selected()).highlight_symbol("▸ "),
+        layout[1],
+        &mut state,
+    );
+
+    let hints = if app.pick_marks.is_empty() {
+        "↑↓ move  Tab mark  Enter pick  Esc cancel".to_string()
+    } else {
+        format!("↑↓ move  Tab mark  Enter pick {} marked  Esc cancel", app.pick_marks.len())
+    };
+    frame.render_widget(Paragraph::new(hints).style(t.muted()), layout[2]);
+}
+
+/// A crossterm backend on stderr. crossterm asks the terminal for the
+/// cursor position through stdout, which inside `$(...)` is the pipe the
+/// result goes to, so the position is asked on stderr and read from the tty.
+pub struct PickerBackend {
+    inner: CrosstermBackend<Stderr>,
+}
+
+impl PickerBackend {
+    pub fn new() -> Self {
+        Self {
+            inner: CrosstermBackend::new(io::stderr()),
+        }
+    }
+}
+
+impl Backend for PickerBackend {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a BufferCell)>,
+    {
+        self.inner.draw(content)
+    }
+
+    fn append_lines(&mut self, n: u16) -> io::Result<()> {
+        self.inner.append_lines(n)
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.inner.hide_cursor()
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.inner.show_cursor()
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        tty_cursor_position()
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.inner.set_cursor(x, y)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.inner.clear()
+    }
+
+    fn clear_region(&mut self, clear_type: ClearType) -> io::Result<()> {
+        self.inner.clear_region(clear_type)
+    }
+
+    fn size(&self) -> io::Result<Rect> {
+        self.inner.size()
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        self.inner.window_size()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Backend::flush(&mut self.inner)
+    }
+}
+
+/// Sends `ESC [6n` and parses the `ESC [row;colR` reply. Needs raw mode.
+fn tty_cursor_position() -> io::Result<(u16, u16)> {
+    let mut tty = File::open("/dev/tty")?;
+    let mut stderr = io::stderr();
+    stderr.write_all(b"\x1b[6n")?;
+    stderr.flush()?;
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reply = Vec::new();
+        let mut byte = [0u8; 1];
+        while tty.read(&mut byte).is_ok_and(|n| n == 1) {
+            reply.push(byte[0]);
+            if byte[0] == b'R' {
+                break;
+            }
+        }
+        let _ = sender.send(reply);
+    });
+
+    let reply = receiver
+        .recv_timeout(Duration::from_secs(2))
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "terminal did not report the cursor position"))?;
+    let text = String::from_utf8_lossy(&reply);
+    let position = text
+        .rsplit_once("\x1b[")
+        .and_then(|(_, rest)| rest.strip_suffix('R'))
+        .and_then(|rest| rest.split_once(';'))
+        .and_then(|(row, col)| Some((col.parse::<u16>().ok()?, row.parse::<u16>().ok()?)));
+
+    match position {
+        Some((col, row)) => Ok((col.saturating_sub(1), row.saturating_sub(1))),
+        None => Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected cursor position reply")),
+    }
+}
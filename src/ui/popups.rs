@@ -6,8 +6,8 @@ use ratatui::{
     Frame,
 };
 
-use crate::models::FormField;
-use crate::services::AppService;
+use crate::models::{AuditSeverity, FormField, IssueKind, KeyGenField, KnownHostsEdit, Marker, Severity};
+use crate::services::{AppService, DiffLine};
 
 fn centered_popup(width_pct: u16, height_pct: u16, area: Rect) -> Rect {
     let vertical = Layout::default()
@@ -31,7 +31,7 @@ fn centered_popup(width_pct: u16, height_pct: u16, area: Rect) -> Rect {
 
 pub fn draw_form(frame: &mut Frame, app: &AppService, title: &str) {
     let t = &app.theme;
-    let area = centered_popup(60, 55, frame.size());
+    let area = centered_popup(64, 80, frame.size());
     frame.render_widget(Clear, area);
 
     let block = Block::default()
@@ -52,7 +52,6 @@ pub fn draw_form(frame: &mut Frame, app: &AppService, title: &str) {
     for _ in &fields {
         constraints.push(Constraint::Length(1));
         constraints.push(Constraint::Length(1));
-        constraints.push(Constraint::Length(1));
     }
     constraints.push(Constraint::Length(2));
     constraints.push(Constraint::Min(0));
@@ -63,30 +62,24 @@ pub fn draw_form(frame: &mut Frame, app: &AppService, title: &str) {
         .split(inner);
 
     for (i, field) in fields.iter().enumerate() {
-        let label_row = i * 3;
-        let value_row = i * 3 + 1;
         let is_active = *field == app.form_field;
-
         let label_style = if is_active { t.bold_accent() } else { t.muted() };
 
-        let label_line = Line::from(vec![
-            Span::styled(format!("  {} ", field.label()), label_style),
-            Span::styled(format!("({})", field.placeholder()), t.muted()),
-        ]);
-        frame.render_widget(Paragraph::new(label_line), rows[label_row]);
-
-        let value = read_field_display(&app.form_draft, field);
+        let value = read_field_display(app, field);
         let input_style = if is_active { t.input() } else { t.base() };
         let cursor = if is_active { "▎" } else { "" };
+        let hint = if value.is_empty() { field.placeholder() } else { "" };
 
-        let value_line = Line::from(vec![
-            Span::styled(format!("  {}", value), input_style),
+        let line = Line::from(vec![
+            Span::styled(format!("  {:<17}", field.label()), label_style),
+            Span::styled(value, input_style),
             Span::styled(cursor, Style::default().fg(t.input_cursor.to_color())),
+            Span::styled(format!(" {}", hint), t.muted()),
         ]);
-        frame.render_widget(Paragraph::new(value_line), rows[value_row]);
+        frame.render_widget(Paragraph::new(line), rows[i * 2]);
     }
 
-    let footer_row = fields.len() * 3;
+    let footer_row = fields.len() * 2;
     if footer_row < rows.len() {
         let footer = Paragraph::new(Line::from(vec![
             Span::styled("Tab", t.bold_accent()),
@@ -95,6 +88,8 @@ pub fn draw_form(frame: &mut Frame, app: &AppService, title: &str) {
             Span::styled(" prev  ", t.muted()),
             Span::styled("Ctrl+S / Enter", t.bold_accent()),
             Span::styled(" save  ", t.muted()),
+            Span::styled("Ctrl+G", t.bold_accent()),
+            Span::styled(" new key  ", t.muted()),
             Span::styled("Esc", t.bold_accent()),
             Span::styled(" cancel", t.muted()),
         ]))
@@ -103,13 +98,131 @@ pub fn draw_form(frame: &mut Frame, app: &AppService, title: &str) {
     }
 }
 
-fn read_field_display(host: &crate::models::SshHost, field: &FormField) -> String {
+pub fn draw_keygen(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(56, 44, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.accent())
+        .title(Span::styled(" Generate SSH Key ", t.title()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    if let Some(path) = &app.keygen_created {
+        let host = match app.form_draft.alias() {
+            "" => "the new host".to_string(),
+            alias => format!("'{}'", alias),
+        };
+        let body = Text::from(vec![
+            Line::from(""),
+            Line::from(Span::styled(
+                format!("Created {}", app.path_display(path)),
+                t.base().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(Span::styled("Private key 0600, public key 0644.", t.muted())),
+            Line::from(""),
+            Line::from(Span::styled(format!("Use it as IdentityFile of {}?", host), t.base())),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("y / Enter", t.bold_accent()),
+                Span::styled(" use it    ", t.muted()),
+                Span::styled("n / Esc", t.bold_accent()),
+                Span::styled(" back to the form", t.muted()),
+            ]),
+        ]);
+        frame.render_widget(
+            Paragraph::new(body).block(block).alignment(Alignment::Center),
+            area,
+        );
+        return;
+    }
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let fields = KeyGenField::all();
+    let mut constraints: Vec<Constraint> = Vec::new();
+    for _ in &fields {
+        constraints.push(Constraint::Length(1));
+        constraints.push(Constraint::Length(1));
+    }
+    constraints.push(Constraint::Length(2));
+    constraints.push(Constraint::Min(0));
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner);
+
+    for (i, field) in fields.iter().enumerate() {
+        let is_active = *field == app.keygen_field;
+        let label_style = if is_active { t.bold_accent() } else { t.muted() };
+
+        let value = read_keygen_display(app, field);
+        let input_style = if is_active { t.input() } else { t.base() };
+        let cursor = if is_active { "▎" } else { "" };
+        let hint = if value.is_empty() || *field == KeyGenField::Algorithm {
+            field.placeholder()
+        } else {
+            ""
+        };
+
+        let line = Line::from(vec![
+            Span::styled(format!("  {:<12}", field.label()), label_style),
+            Span::styled(value, input_style),
+            Span::styled(cursor, Style::default().fg(t.input_cursor.to_color())),
+            Span::styled(format!(" {}", hint), t.muted()),
+        ]);
+        frame.render_widget(Paragraph::new(line), rows[i * 2]);
+    }
+
+    let footer_row = fields.len() * 2;
+    if footer_row < rows.len() {
+        let footer = Paragraph::new(Line::from(vec![
+            Span::styled("Tab", t.bold_accent()),
+            Span::styled(" next  ", t.muted()),
+            Span::styled("Enter", t.bold_accent()),
+            Span::styled(" generate  ", t.muted()),
+            Span::styled("Esc", t.bold_accent()),
+            Span::styled(" back", t.muted()),
+        ]))
+        .alignment(Alignment::Center);
+        frame.render_widget(footer, rows[footer_row]);
+    }
+}
+
+fn read_keygen_display(app: &AppService, field: &KeyGenField) -> String {
+    let request = &app.keygen;
+    let value = match field {
+        KeyGenField::Algorithm => request.algorithm.label(),
+        KeyGenField::FileName => &request.file_name,
+        KeyGenField::Comment => &request.comment,
+        KeyGenField::Passphrase => &request.passphrase,
+        KeyGenField::ConfirmPassphrase => &app.keygen_confirm,
+    };
+    if field.is_secret() {
+        "•".repeat(value.chars().count())
+    } else {
+        value.to_string()
+    }
+}
+
+fn read_field_display(app: &AppService, field: &FormField) -> String {
+    let host = &app.form_draft;
     match field {
-        FormField::Alias => host.alias.clone(),
+        FormField::Alias => host.patterns_text(),
         FormField::HostName => host.hostname.clone(),
-        FormField::Port => host.port.to_string(),
+        FormField::Port => match &host.invalid_port {
+            Some(raw) => format!("{} (invalid, kept as written)", raw),
+            None => host.port.to_string(),
+        },
         FormField::User => host.user.clone(),
-        FormField::IdentityFile => host.identity_file.clone(),
+        FormField::ConfigFile => app.source_display(host.file_index),
+        _ => host.list_text(field),
     }
 }
 
@@ -120,7 +233,7 @@ pub fn draw_delete_confirmation(frame: &mut Frame, app: &AppService, index: usiz
 
     let alias = app
         .host_at(index)
-        .map(|h| h.alias.as_str())
+        .map(|h| h.alias())
         .unwrap_or("?");
 
     let block = Block::default()
@@ -155,6 +268,90 @@ pub fn draw_delete_confirmation(frame: &mut Frame, app: &AppService, index: usiz
     );
 }
 
+pub fn draw_known_hosts_confirmation(frame: &mut Frame, app: &AppService, edit: KnownHostsEdit) {
+    let t = &app.theme;
+    let area = centered_popup(60, 30, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.error())
+        .title(Span::styled(" Confirm known_hosts Change ", t.bold_error()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 1))
+        .style(t.base());
+
+    let body = Text::from(vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            app.known_hosts_edit_prompt(edit),
+            t.base().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "The file is first copied to known_hosts.old_<timestamp>.",
+            t.muted(),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("y", t.bold_error()),
+            Span::styled(" confirm    ", t.muted()),
+            Span::styled("n / Esc", t.bold_accent()),
+            Span::styled(" cancel", t.muted()),
+        ]),
+    ]);
+
+    frame.render_widget(
+        Paragraph::new(body).block(block).alignment(Alignment::Center).wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+pub fn draw_save_conflict(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(56, 32, frame.size());
+    frame.render_widget(Clear, area);
+
+    let file = app.pending_conflict_file().unwrap_or_default();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.warning())
+        .title(Span::styled(" File Changed On Disk ", t.bold_warning()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 1))
+        .style(t.base());
+
+    let body = Text::from(vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("{} was modified outside lazyssh.", file),
+            t.base().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled("Your change has not been written yet.", t.muted())),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("r", t.bold_accent()),
+            Span::styled(" reload, drop my change    ", t.muted()),
+            Span::styled("o", t.bold_error()),
+            Span::styled(" overwrite (with backup)    ", t.muted()),
+            Span::styled("Esc", t.bold_accent()),
+            Span::styled(" cancel", t.muted()),
+        ]),
+    ]);
+
+    frame.render_widget(
+        Paragraph::new(body)
+            .block(block)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        area,
+    );
+}
+
 pub fn draw_theme_selector(frame: &mut Frame, app: &AppService) {
     let t = &app.theme;
     let area = centered_popup(55, 60, frame.size());
@@ -221,6 +418,669 @@ pub fn draw_theme_selector(frame: &mut Frame, app: &AppService) {
     frame.render_widget(Paragraph::new(lines).alignment(Alignment::Center), inner);
 }
 
+pub fn draw_match_rules(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(70, 70, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.accent_secondary())
+        .title(Span::styled(" Match Blocks ", t.title()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Read-only: Match blocks are kept exactly as written.",
+            t.muted(),
+        )),
+        Line::from(""),
+    ];
+    let mut selected_line = 0;
+
+    for (i, rule) in app.match_rules().iter().enumerate() {
+        let is_pointed = i == app.match_cursor;
+        if is_pointed {
+            selected_line = lines.len();
+        }
+
+        let pointer = if is_pointed { "▸ " } else { "  " };
+        let header_style = if is_pointed { t.selected() } else { t.bold_accent() };
+
+        lines.push(Line::from(vec![
+            Span::styled(pointer, header_style),
+            Span::styled(format!("Match {}", rule.summary()), header_style),
+        ]));
+        lines.push(Line::from(Span::styled(
+            format!("    {}:{}", app.source_display(rule.file_index), rule.line),
+            t.muted(),
+        )));
+
+        for criterion in &rule.criteria {
+            let bang = if criterion.negated { "not " } else { "" };
+            let argument = criterion.argument.as_deref().unwrap_or("");
+            lines.push(Line::from(vec![
+                Span::styled(format!("    {}{:<13}", bang, criterion.keyword), t.accent_secondary()),
+                Span::styled(argument.to_string(), t.base()),
+            ]));
+        }
+
+        for (key, value) in &rule.options {
+            lines.push(Line::from(vec![
+                Span::styled(format!("      {:<20}", key), t.muted()),
+                Span::styled(value.clone(), t.base()),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(vec![
+        Span::styled("↑/↓", t.bold_accent()),
+        Span::styled(" navigate  ", t.muted()),
+        Span::styled("Esc", t.bold_accent()),
+        Span::styled(" close", t.muted()),
+    ]));
+
+    let visible = inner.height as usize;
+    let scroll = selected_line.saturating_sub(visible / 3) as u16;
+
+    frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), inner);
+}
+
+pub fn draw_diagnostics(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(74, 70, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.accent_secondary())
+        .title(Span::styled(" Problems ", t.title()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Lines with problems are left exactly as written.",
+            t.muted(),
+        )),
+    ];
+    let mut selected_line = 0;
+    let mut current_file = None;
+
+    for (i, diagnostic) in app.diagnostics().iter().enumerate() {
+        if current_file != Some(diagnostic.file_index) {
+            current_file = Some(diagnostic.file_index);
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                app.source_display(diagnostic.file_index),
+                t.bold_accent_secondary(),
+            )));
+        }
+
+        let is_pointed = i == app.diagnostic_cursor;
+        if is_pointed {
+            selected_line = lines.len();
+        }
+
+        let pointer = if is_pointed { "▸ " } else { "  " };
+        let severity_style = match diagnostic.severity {
+            Severity::Error => t.bold_error(),
+            Severity::Warning => t.bold_warning(),
+        };
+        let location = if diagnostic.line == 0 {
+            "file".to_string()
+        } else {
+            format!("line {}", diagnostic.line)
+        };
+
+        lines.push(Line::from(vec![
+            Span::styled(pointer, if is_pointed { t.selected() } else { t.base() }),
+            Span::styled(format!("{} ", diagnostic.severity.symbol()), severity_style),
+            Span::styled(format!("{:<10}", location), t.muted()),
+            Span::styled(
+                diagnostic.message.clone(),
+                if is_pointed { t.selected() } else { t.base() },
+            ),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("↑/↓", t.bold_accent()),
+        Span::styled(" navigate  ", t.muted()),
+        Span::styled("Enter", t.bold_accent()),
+        Span::styled(" go to host  ", t.muted()),
+        Span::styled("Esc", t.bold_accent()),
+        Span::styled(" close", t.muted()),
+    ]));
+
+    let visible = inner.height as usize;
+    let scroll = selected_line.saturating_sub(visible / 3) as u16;
+
+    frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), inner);
+}
+
+pub fn draw_audit(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(78, 74, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.accent_secondary())
+        .title(Span::styled(" Security Audit ", t.title()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    let mut selected_line = 0;
+
+    for severity in AuditSeverity::all() {
+        let group: Vec<(usize, _)> = app
+            .audit_findings
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.severity == severity)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+
+        let heading_style = match severity {
+            AuditSeverity::High => t.bold_error(),
+            AuditSeverity::Medium => t.bold_warning(),
+            AuditSeverity::Low => t.bold_accent_secondary(),
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{} ({})", severity.label(), group.len()),
+            heading_style,
+        )));
+
+        for (i, finding) in group {
+            let is_pointed = i == app.audit_cursor;
+            if is_pointed {
+                selected_line = lines.len();
+            }
+            let pointer = if is_pointed { "▸ " } else { "  " };
+            let style = if is_pointed { t.selected() } else { t.base() };
+
+            lines.push(Line::from(vec![
+                Span::styled(pointer, style),
+                Span::styled(format!("{:<18}", finding.subject), t.bold_accent()),
+                Span::styled(finding.message.clone(), style),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(vec![
+        Span::styled("↑/↓", t.bold_accent()),
+        Span::styled(" navigate  ", t.muted()),
+        Span::styled("Enter", t.bold_accent()),
+        Span::styled(" go to host  ", t.muted()),
+        Span::styled("Esc", t.bold_accent()),
+        Span::styled(" close", t.muted()),
+    ]));
+
+    let visible = inner.height as usize;
+    let scroll = selected_line.saturating_sub(visible / 3) as u16;
+
+    frame.render_widget(
+        Paragraph::new(lines).wrap(Wrap { trim: false }).scroll((scroll, 0)),
+        inner,
+    );
+}
+
+pub fn draw_backups(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(86, 80, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.accent_secondary())
+        .title(Span::styled(" Backups ", t.title()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(2)])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(rows[0]);
+
+    let mut list = Vec::new();
+    for (i, backup) in app.backups.iter().enumerate() {
+        let is_pointed = i == app.backup_cursor;
+        let pointer = if is_pointed { "▸ " } else { "  " };
+        let style = if is_pointed { t.selected() } else { t.base() };
+
+        list.push(Line::from(vec![
+            Span::styled(pointer, style),
+            Span::styled(backup.file_name(), style),
+        ]));
+        list.push(Line::from(Span::styled(
+            format!("    {} ago, {}", backup.age_display(), backup.size_display()),
+            t.muted(),
+        )));
+    }
+
+    let visible = columns[0].height as usize;
+    let scroll = (app.backup_cursor * 2).saturating_sub(visible / 3) as u16;
+    frame.render_widget(Paragraph::new(list).scroll((scroll, 0)), columns[0]);
+
+    let selected = app.backups.get(app.backup_cursor);
+    let target = selected.and_then(|b| app.backup_target(b));
+
+    let mut diff = match (selected, target) {
+        (Some(backup), None) => vec![
+            Line::from(Span::styled(
+                format!("Taken of {}, which is no longer loaded.", app.path_display(&backup.source)),
+                t.error(),
+            )),
+            Line::from(Span::styled("It cannot be restored from here.", t.muted())),
+        ],
+        _ => vec![
+            Line::from(Span::styled(
+                format!("Restoring changes {}:", target.map(|i| app.source_display(i)).unwrap_or_default()),
+                t.muted(),
+            )),
+            Line::from(""),
+        ],
+    };
+    if target.is_some() && app.backup_diff.is_empty() {
+        diff.push(Line::from(Span::styled("Identical to the current file", t.success())));
+    }
+    for line in &app.backup_diff {
+        diff.push(match line {
+            DiffLine::Same(text) => Line::from(Span::styled(format!("  {}", text), t.base())),
+            DiffLine::Added(text) => Line::from(Span::styled(format!("+ {}", text), t.success())),
+            DiffLine::Removed(text) => Line::from(Span::styled(format!("- {}", text), t.error())),
+            DiffLine::Skipped(count) => Line::from(Span::styled(
+                format!("  ⋯ {} unchanged line(s)", count),
+                t.muted(),
+            )),
+        });
+    }
+    frame.render_widget(Paragraph::new(diff), columns[1]);
+
+    let footer = vec![
+        Line::from(Span::styled(
+            format!("Retention: {}", app.retention_display()),
+            t.muted(),
+        )),
+        Line::from(vec![
+            Span::styled("↑/↓", t.bold_accent()),
+            Span::styled(" navigate  ", t.muted()),
+            Span::styled("r", t.bold_accent()),
+            Span::styled(" restore  ", t.muted()),
+            Span::styled("d", t.bold_accent()),
+            Span::styled(" delete  ", t.muted()),
+            Span::styled("p", t.bold_accent()),
+            Span::styled(" prune  ", t.muted()),
+            Span::styled("Esc", t.bold_accent()),
+            Span::styled(" close", t.muted()),
+        ]),
+    ];
+    frame.render_widget(Paragraph::new(footer), rows[1]);
+}
+
+pub fn draw_known_hosts(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(86, 80, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.accent_secondary())
+        .title(Span::styled(" Known Hosts ", t.title()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let issues = app.known_hosts_issues();
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(if issues.is_empty() { 0 } else { issues.len().min(4) as u16 + 2 }),
+            Constraint::Min(3),
+            Constraint::Length(2),
+        ])
+        .split(inner);
+
+    let filter_line = if app.known_hosts_filtering || !app.known_hosts_filter.is_empty() {
+        Line::from(vec![
+            Span::styled("Filter: ", t.bold_accent()),
+            Span::styled(app.known_hosts_filter.as_str(), t.base()),
+            Span::styled(if app.known_hosts_filtering { "█" } else { "" }, t.accent()),
+        ])
+    } else {
+        Line::from(Span::styled("Press / to filter by host, key type or fingerprint", t.muted()))
+    };
+    let selected_line = match app.known_hosts_of_selected_host() {
+        Some((alias, trust, entries)) => Line::from(vec![
+            Span::styled("Selected host: ", t.muted()),
+            Span::styled(alias, t.bold_accent()),
+            Span::styled(
+                format!(" as {}, {} removable entries (R)", trust.lookup_name, entries.len()),
+                t.muted(),
+            ),
+        ]),
+        None => Line::from(""),
+    };
+    frame.render_widget(Paragraph::new(vec![filter_line, selected_line]), rows[0]);
+
+    if !issues.is_empty() {
+        let mut lines = vec![Line::from(Span::styled(
+            format!("Issues ({})", issues.len()),
+            t.bold_warning(),
+        ))];
+        for issue in issues.iter().take(4) {
+            let style = match issue.kind {
+                IssueKind::Conflict => t.error(),
+                IssueKind::Duplicate => t.warning(),
+            };
+            let label = match issue.kind {
+                IssueKind::Conflict => "conflict  ",
+                IssueKind::Duplicate => "duplicate ",
+            };
+            lines.push(Line::from(vec![
+                Span::styled(label, style),
+                Span::styled(app.known_hosts_issue_display(issue), t.base()),
+            ]));
+        }
+        frame.render_widget(Paragraph::new(lines), rows[1]);
+    }
+
+    let mut list = Vec::new();
+    for (position, &index) in app.known_hosts_visible.iter().enumerate() {
+        let Some(entry) = app.known_host_entry(index) else { continue };
+        let is_pointed = position == app.known_hosts_cursor;
+        let pointer = if is_pointed { "▸ " } else { "  " };
+        let style = if is_pointed { t.selected() } else { t.base() };
+
+        let (marker, marker_style) = match entry.marker {
+            Some(Marker::Revoked) => ("@revoked ", t.error()),
+            Some(Marker::CertAuthority) => ("@ca ", t.accent_secondary()),
+            None => ("", style),
+        };
+        let issue = match app.known_hosts_issue_of(index).map(|i| i.kind) {
+            Some(IssueKind::Conflict) => Span::styled(" ✖", t.bold_error()),
+            Some(IssueKind::Duplicate) => Span::styled(" ⚠", t.bold_warning()),
+            None => Span::raw(""),
+        };
+
+        list.push(Line::from(vec![
+            Span::styled(pointer, style),
+            Span::styled(marker, marker_style),
+            Span::styled(app.known_host_names(entry), style),
+            issue,
+        ]));
+        list.push(Line::from(Span::styled(
+            format!(
+                "    {} {}  {}",
+                entry.key.algorithm(),
+                entry.key.fingerprint(),
+                app.known_hosts_source(entry.file_index, entry.line)
+            ),
+            t.muted(),
+        )));
+    }
+    if list.is_empty() {
+        list.push(Line::from(Span::styled("No entries", t.muted())));
+    }
+
+    let visible = rows[2].height as usize;
+    let scroll = (app.known_hosts_cursor * 2).saturating_sub(visible / 3) as u16;
+    frame.render_widget(Paragraph::new(list).scroll((scroll, 0)), rows[2]);
+
+    let footer = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("↑/↓", t.bold_accent()),
+            Span::styled(" navigate  ", t.muted()),
+            Span::styled("/", t.bold_accent()),
+            Span::styled(" filter  ", t.muted()),
+            Span::styled("d", t.bold_accent()),
+            Span::styled(" delete entry  ", t.muted()),
+            Span::styled("R", t.bold_accent()),
+            Span::styled(" remove selected host  ", t.muted()),
+            Span::styled("H", t.bold_accent()),
+            Span::styled(" hash names  ", t.muted()),
+            Span::styled("Esc", t.bold_accent()),
+            Span::styled(" close", t.muted()),
+        ]),
+    ];
+    frame.render_widget(Paragraph::new(footer), rows[3]);
+}
+
+pub fn draw_keys(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let area = centered_popup(86, 80, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.accent_secondary())
+        .title(Span::styled(" SSH Keys ", t.title()))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(2)])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(rows[0]);
+
+    let mut list = Vec::new();
+    for (i, usage) in app.keys.iter().enumerate() {
+        let is_pointed = i == app.key_cursor;
+        let pointer = if is_pointed { "▸ " } else { "  " };
+        let style = if is_pointed { t.selected() } else { t.base() };
+
+        let (flag, flag_style) = if usage.is_missing() {
+            (" ✖ missing", t.bold_error())
+        } else if usage.key.unresolved {
+            (" ? unresolved", t.muted())
+        } else if usage.is_unused() {
+            (" ○ unused", t.muted())
+        } else {
+            ("", t.base())
+        };
+        list.push(Line::from(vec![
+            Span::styled(pointer, style),
+            Span::styled(app.path_display(&usage.key.path), style),
+            Span::styled(flag, flag_style),
+        ]));
+
+        let summary = match &usage.key.public {
+            Some(public) => match public.bits {
+                Some(bits) => format!("    {} {}", public.algorithm(), bits),
+                None => format!("    {}", public.algorithm()),
+            },
+            None => "    unknown type".to_string(),
+        };
+        list.push(Line::from(Span::styled(summary, t.muted())));
+    }
+    if list.is_empty() {
+        list.push(Line::from(Span::styled("No keys in ~/.ssh or IdentityFile lines", t.muted())));
+    }
+
+    let visible = columns[0].height as usize;
+    let scroll = (app.key_cursor * 2).saturating_sub(visible / 3) as u16;
+    frame.render_widget(Paragraph::new(list).scroll((scroll, 0)), columns[0]);
+
+    let label = t.bold_accent();
+    let value = t.base();
+    let dim = t.muted();
+    let mut details = Vec::new();
+    if let Some(usage) = app.keys.get(app.key_cursor) {
+        let key = &usage.key;
+        let public = key.public.as_ref();
+        let (file_display, file_style) = match (key.exists, &key.public_path) {
+            _ if key.unresolved => ("path uses tokens only ssh can expand".to_string(), dim),
+            (true, Some(_)) => ("private and .pub".to_string(), value),
+            (true, None) => ("private only, no .pub".to_string(), dim),
+            (false, Some(_)) => ("private key missing, .pub only".to_string(), t.error()),
+            (false, None) => ("not found".to_string(), t.error()),
+        };
+        let (mode_display, mode_style) = if key.too_open() {
+            (format!("{} (too open: chmod 600)", key.mode_display()), t.error())
+        } else {
+            (key.mode_display(), value)
+        };
+        let hosts_display = if usage.hosts.is_empty() {
+            if key.is_default_name() {
+                "no host names it; ssh tries it by default".to_string()
+            } else {
+                "no host".to_string()
+            }
+        } else {
+            usage.hosts.join(", ")
+        };
+
+        details.extend([
+            key_row("Files         ", file_display, label, file_style),
+            key_row(
+                "Type          ",
+                public.map_or("unknown".into(), |p| p.key_type.clone()),
+                label,
+                value,
+            ),
+            key_row(
+                "Bits          ",
+                public.and_then(|p| p.bits).map_or("-".into(), |b| b.to_string()),
+                label,
+                value,
+            ),
+            key_row(
+                "Fingerprint   ",
+                public.filter(|p| !p.blob.is_empty()).map_or("-".into(), |p| p.fingerprint()),
+                label,
+                value,
+            ),
+            key_row(
+                "Comment       ",
+                public.map(|p| p.comment.clone()).filter(|c| !c.is_empty()).unwrap_or("-".into()),
+                label,
+                value,
+            ),
+            key_row("Passphrase    ", key.passphrase_display().to_string(), label, value),
+            key_row("Permissions   ", mode_display, label, mode_style),
+            Line::from(""),
+            key_row("Used by       ", hosts_display, label, if usage.is_unused() { dim } else { value }),
+        ]);
+    }
+    frame.render_widget(Paragraph::new(details).wrap(Wrap { trim: false }), columns[1]);
+
+    let missing = app.keys.iter().filter(|k| k.is_missing()).count();
+    let unused = app.keys.iter().filter(|k| k.is_unused()).count();
+    let footer = vec![
+        Line::from(Span::styled(
+            format!("{} keys, {} missing, {} unused", app.keys.len(), missing, unused),
+            dim,
+        )),
+        Line::from(vec![
+            Span::styled("↑/↓", t.bold_accent()),
+            Span::styled(" navigate  ", dim),
+            Span::styled("Esc", t.bold_accent()),
+            Span::styled(" close", dim),
+        ]),
+    ];
+    frame.render_widget(Paragraph::new(footer), rows[1]);
+}
+
+fn key_row<'a>(label: &'a str, value: String, label_style: Style, value_style: Style) -> Line<'a> {
+    Line::from(vec![
+        Span::styled(label, label_style),
+        Span::styled(value, value_style),
+    ])
+}
+
+pub fn draw_session_failure(frame: &mut Frame, app: &AppService) {
+    let t = &app.theme;
+    let Some(diagnosis) = &app.session_diagnosis else {
+        return;
+    };
+    let area = centered_popup(70, 56, frame.size());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .border_style(t.error())
+        .title(Span::styled(
+            format!(" ssh {} failed: {} ", diagnosis.alias, diagnosis.failure.title()),
+            t.bold_error(),
+        ))
+        .title_alignment(Alignment::Center)
+        .padding(Padding::new(2, 2, 1, 0))
+        .style(t.base());
+
+    let mut lines = vec![
+        Line::from(Span::styled(diagnosis.failure.explanation(), t.base())),
+        Line::from(""),
+    ];
+    for evidence in &diagnosis.evidence {
+        lines.push(Line::from(Span::styled(format!("  {}", evidence), t.muted())));
+    }
+    lines.push(Line::from(""));
+
+    for remedy in diagnosis.failure.remedies() {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {}  ", remedy.key()), t.bold_accent()),
+            Span::styled(remedy.label(), t.base()),
+        ]));
+    }
+    lines.push(Line::from(vec![
+        Span::styled("  Esc", t.bold_accent()),
+        Span::styled(" close", t.muted()),
+    ]));
+
+    frame.render_widget(
+        Paragraph::new(lines).block(block).wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
 pub fn draw_help(frame: &mut Frame, app: &AppService) {
     let t = &app.theme;
     let area = centered_popup(60, 78, frame.size());
@@ -240,7 +1100,7 @@ pub fn draw_help(frame: &mut Frame, app: &AppService) {
     let section = t.bold_accent_secondary();
 
     let lines = vec![
-        Line::from(Span::styled("Reads and writes ~/.ssh/config directly.", d)),
+        Line::from(Span::styled("Reads and writes ~/.ssh/config and its Include files directly.", d)),
         Line::from(Span::styled("A backup is created before every change.", t.muted())),
         Line::from(""),
         Line::from(Span::styled("Navigation", section)),
@@ -251,13 +1111,23 @@ pub fn draw_help(frame: &mut Frame, app: &AppService) {
         Line::from(""),
         Line::from(Span::styled("Actions", section)),
         Line::from(""),
-        help_row("  Enter         ", "SSH into selected host", k, d),
+        help_row("  Enter         ", "SSH into selected host (not patterns)", k, d),
         help_row("  a             ", "Add new host", k, d),
         help_row("  e             ", "Edit selected host", k, d),
         help_row("  d             ", "Delete (with backup)", k, d),
         help_row("  c             ", "Toggle SSH command display", k, d),
+        help_row("  v             ", "Toggle effective config (like ssh -G)", k, d),
         help_row("  /             ", "Search hosts", k, d),
+        help_row("  s             ", "Sort by config order / most used", k, d),
         help_row("  r             ", "Reload from disk", k, d),
+        help_row("  p             ", "Check which hosts accept TCP connections", k, d),
+        help_row("  B             ", "Accept the changed SSH banner of a host", k, d),
+        help_row("  m             ", "View Match blocks (read-only)", k, d),
+        help_row("  b             ", "Browse, diff and restore backups", k, d),
+        help_row("  !             ", "List config problems and lint findings", k, d),
+        help_row("  A             ", "Security audit of all hosts", k, d),
+        help_row("  K             ", "Manage known_hosts entries", k, d),
+        help_row("  i             ", "SSH key inventory", k, d),
         Line::from(""),
         Line::from(Span::styled("Appearance", section)),
         Line::from(""),
@@ -267,7 +1137,9 @@ pub fn draw_help(frame: &mut Frame, app: &AppService) {
         Line::from(Span::styled("Form", section)),
         Line::from(""),
         help_row("  Tab / S-Tab   ", "Next / previous field", k, d),
+        help_row("  ← / →         ", "Choose config file for a new host", k, d),
         help_row("  Ctrl+S/Enter  ", "Save to ~/.ssh/config", k, d),
+        help_row("  Ctrl+G        ", "Generate a key and use it as IdentityFile", k, d),
         help_row("  Esc           ", "Cancel / close", k, d),
         Line::from(""),
         Line::from(Span::styled("Press Esc to close", t.muted())),
@@ -32,7 +32,27 @@ pub fn render(frame: &mut Frame, app: &AppService) {
         Mode::AddHost => popups::draw_form(frame, app, " + Add SSH Host "),
         Mode::EditHost(_) => popups::draw_form(frame, app, " Edit SSH Host "),
         Mode::ConfirmDelete(idx) => popups::draw_delete_confirmation(frame, app, *idx),
+        Mode::SaveConflict => popups::draw_save_conflict(frame, app),
         Mode::SelectTheme => popups::draw_theme_selector(frame, app),
+        Mode::MatchRules => popups::draw_match_rules(frame, app),
+        Mode::Diagnostics => popups::draw_diagnostics(frame, app),
+        Mode::Audit => popups::draw_audit(frame, app),
+        Mode::Backups => popups::draw_backups(frame, app),
+        Mode::KnownHosts => popups::draw_known_hosts(frame, app),
+        Mode::ConfirmKnownHostsEdit(edit) => {
+            popups::draw_known_hosts(frame, app);
+            popups::draw_known_hosts_confirmation(frame, app, *edit);
+        }
+        Mode::Keys => popups::draw_keys(frame, app),
+        Mode::SessionFailure => popups::draw_session_failure(frame, app),
+        Mode::GenerateKey => {
+            let title = match app.keygen_return {
+                Mode::EditHost(_) => " Edit SSH Host ",
+                _ => " + Add SSH Host ",
+            };
+            popups::draw_form(frame, app, title);
+            popups::draw_keygen(frame, app);
+        }
         Mode::Help => popups::draw_help(frame, app),
         _ => {}
     }